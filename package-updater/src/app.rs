@@ -1,5 +1,7 @@
 use cosmic::app::{Core, Task};
 use cosmic::cosmic_config::Config;
+use cosmic::cctk::sctk::reexports::client::protocol::wl_output::WlOutput;
+use cosmic::iced::event::{self, wayland, PlatformSpecific};
 use cosmic::iced::platform_specific::shell::wayland::commands::popup::{destroy_popup, get_popup};
 use cosmic::iced::window;
 use cosmic::iced::{time, window::Id, Limits, Subscription};
@@ -8,11 +10,20 @@ use cosmic::widget::{
     toggler, Space,
 };
 use cosmic::Element;
+use std::collections::{HashSet, VecDeque};
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
-use crate::config::{NixOSMode, PackageUpdaterConfig};
-use crate::package_manager::{PackageManager, PackageManagerDetector, UpdateChecker, UpdateInfo};
+use crate::config::{
+    AurCheckBackend, IconStyle, NixOSMode, NixOSUpdateCount, NotificationUrgency,
+    PackageUpdaterConfig, RemoteHost, ZeroBadgeBehavior,
+};
+use crate::custom_groups::CustomGroups;
+use crate::package_manager::{
+    CheckError, CheckOptions, CheckProgress, LockReset, PackageManager, PackageManagerDetector,
+    PackageUpdate, UpdateChecker, UpdateInfo,
+};
+use crate::runner::ProcessRunner;
 
 // Timing constants
 const STARTUP_DELAY_SECS: u64 = 2;
@@ -20,19 +31,64 @@ const POST_UPDATE_STABILIZATION_SECS: u64 = 3;
 const SYNC_DEBOUNCE_SECS: u64 = 10;
 const MARKER_FILE_POLL_INTERVAL_MS: u64 = 500;
 const FILE_WATCHER_DEBOUNCE_MS: u64 = 100;
+/// Wait before setting up a failed sync file watcher again
+const SYNC_WATCHER_RETRY_SECS: u64 = 60;
+const PACKAGE_DB_SETTLE_SECS: u64 = 5;
+/// How long "Undo" stays offered after ignoring a package
+const IGNORE_UNDO_SECS: u64 = 5;
+const CHECKING_ANIMATION_INTERVAL_MS: u64 = 250;
+const CLOCK_REFRESH_INTERVAL_SECS: u64 = 30;
+/// Local hour a "snooze until tomorrow" ends at
+const SNOOZE_TOMORROW_HOUR: u32 = 8;
+
+/// Icon frames cycled through on the panel while a check is running
+const CHECKING_ICON_FRAMES: &[&str] = &[
+    "view-refresh-symbolic",
+    "emblem-synchronizing-symbolic",
+    "process-working-symbolic",
+    "emblem-synchronizing-symbolic",
+];
+/// Opacity of the panel count while it may be stale (checking or failed)
+const STALE_BADGE_ALPHA: f32 = 0.5;
 
 // UI dimension constants
 const POPUP_MIN_HEIGHT: f32 = 350.0;
 const POPUP_MAX_HEIGHT: f32 = 800.0;
+const POPUP_EMPTY_MIN_HEIGHT: f32 = 220.0;
+const POPUP_HEIGHT_LIMIT: f32 = 1400.0;
+const POPUP_LIST_CHROME_HEIGHT: f32 = 300.0;
+/// Height left free on the output around the popup, for the panel and gaps
+const POPUP_OUTPUT_MARGIN: f32 = 80.0;
 const POPUP_MIN_WIDTH: f32 = 450.0;
 const POPUP_MAX_WIDTH: f32 = 550.0;
+const COMPACT_POPUP_MIN_HEIGHT: f32 = 250.0;
+const COMPACT_POPUP_MIN_WIDTH: f32 = 320.0;
 const PACKAGE_LIST_HEIGHT: f32 = 100.0;
+const PACKAGE_LIST_MAX_HEIGHT: f32 = 900.0;
+const PACKAGE_ROW_HEIGHT: f32 = 18.0;
+// Panel count text, regular and with `large_badge_text`
+const BADGE_TEXT_SIZE: u16 = 12;
+const LARGE_BADGE_TEXT_SIZE: u16 = 16;
+/// Package names longer than this are shortened with an ellipsis
+const MAX_PACKAGE_NAME_CHARS: usize = 36;
+// Fixed package row columns, so versions line up down the list
+const PACKAGE_VERSION_COLUMN_WIDTH: f32 = 150.0;
+const PACKAGE_SIZE_COLUMN_WIDTH: f32 = 56.0;
+const PACKAGE_ACTION_COLUMN_WIDTH: f32 = 24.0;
+/// Minimum time between automatic package manager discoveries
+const DISCOVERY_DEBOUNCE_SECS: u64 = 5;
+const CONTEXT_MENU_WIDTH: f32 = 220.0;
+/// Number of recent successful checks averaged for the interval warning
+const CHECK_DURATION_SAMPLES: usize = 5;
+/// Longest a single check command may run before the check fails
+const CHECK_COMMAND_TIMEOUT_SECS: u64 = 15 * 60;
 const ILLUSTRATION_WIDTH: f32 = 110.0;
 const ILLUSTRATION_HEIGHT: f32 = 150.0;
 
 pub struct CosmicAppletPackageUpdater {
     core: Core,
     popup: Option<Id>,
+    context_menu: Option<Id>,
     active_tab: PopupTab,
     config: PackageUpdaterConfig,
     config_handler: Config,
@@ -40,6 +96,105 @@ pub struct CosmicAppletPackageUpdater {
     check_state: CheckState,
     available_package_managers: Vec<PackageManager>,
     ignore_next_sync: bool,
+    animation_frame: usize,
+    /// Number of pending updates when a terminal update finished, used to
+    /// report what the update applied once the follow-up check completes
+    pre_update_count: Option<usize>,
+    /// Cancels the running update check when fired
+    cancel_check: Option<tokio::sync::oneshot::Sender<()>>,
+    /// Cancels the running remote host checks when fired
+    cancel_remote_check: Option<tokio::sync::oneshot::Sender<()>>,
+    last_discovery: Option<Instant>,
+    /// Terminal used when `preferred_terminal` is blank
+    default_terminal: String,
+    /// Set when the custom check command just entered was not found
+    custom_check_warning: Option<String>,
+    /// Custom check command as last typed, which lookups have to match
+    custom_check_input: String,
+    /// Whether cross-instance sync works; `None` until the watcher reported
+    sync_watcher_active: Option<bool>,
+    /// Result of the last "Reset update lock", shown under the button
+    lock_reset_status: Option<String>,
+    /// Updates are being installed without a terminal
+    installing: bool,
+    /// Updates (name, new version) that appeared since the previous check,
+    /// marked as new until the popup is closed
+    new_packages: HashSet<(String, String)>,
+    /// Pending versions of the result before the current one, from the
+    /// previous session for the first check
+    previous_packages: Option<crate::change_summary::PendingVersions>,
+    /// Whether a check succeeded since startup, so the first result isn't all new
+    has_checked: bool,
+    /// Render the whole package list instead of the first `max_displayed_packages`
+    show_all_packages: bool,
+    /// Text of the apt suites input as typed, so separators survive editing
+    apt_origins_input: String,
+    /// Text of the ignored packages input as typed
+    ignored_packages_input: String,
+    /// Package just ignored from its row, kept while "Undo" is offered
+    ignore_undo: Option<IgnoreUndo>,
+    /// Logical height of each output, to keep the popup on screen
+    output_heights: Vec<(WlOutput, f32)>,
+    /// When the running check started
+    check_started: Option<Instant>,
+    /// State before the running check, restored if it is cancelled
+    state_before_check: Option<CheckState>,
+    /// Durations of the most recent successful checks, oldest first
+    check_durations: VecDeque<Duration>,
+    /// Confirmation (or failure) of the last export, shown under the button
+    export_status: Option<String>,
+    /// Updates found so far by the running check, shown until it completes
+    live_packages: Vec<PackageUpdate>,
+    /// Latest result per configured remote host, in config order
+    remote_results: Vec<RemoteCheck>,
+    /// Text of the remote hosts input as typed
+    remote_hosts_input: String,
+    /// Compiled `custom_repo_patterns`
+    custom_groups: CustomGroups,
+    /// Text of the custom repository patterns input as typed
+    custom_patterns_input: String,
+    /// Command lines the latest check ran
+    check_commands: Vec<String>,
+    /// Command line of the last update launched in a terminal
+    last_update_command: Option<String>,
+    /// Whether the "Last command" section is expanded
+    show_commands: bool,
+    /// The running check mirrors another instance's check, which already
+    /// sent any notification
+    sync_triggered_check: bool,
+    /// A check was requested while one was running; started when it ends
+    check_requested: bool,
+}
+
+/// Latest check of one remote host
+#[derive(Debug, Clone)]
+pub struct RemoteCheck {
+    pub target: String,
+    pub result: Result<UpdateInfo, CheckError>,
+    /// When `result` arrived
+    pub at: Instant,
+    /// Last time the host answered, kept across failed checks
+    pub last_success: Option<Instant>,
+}
+
+/// What "Undo" puts back after ignoring a package from its row
+#[derive(Debug, Clone)]
+struct IgnoreUndo {
+    name: String,
+    /// Updates of the package dropped from the last result
+    removed: Vec<PackageUpdate>,
+    /// Updates of the package dropped from the running check's list
+    live_removed: Vec<PackageUpdate>,
+}
+
+/// How long to mute notifications and the panel highlight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Snooze {
+    Hours(u64),
+    /// Until the next morning
+    Tomorrow,
+    /// End an active snooze
+    Resume,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -53,35 +208,129 @@ pub enum CheckState {
     Idle,
     Checking,
     Completed { at: Instant },
-    Error { message: String, at: Option<Instant> },
+    /// `at` is the last successful check, `since` is when the error occurred
+    Error {
+        message: String,
+        at: Option<Instant>,
+        since: Instant,
+    },
+}
+
+impl CheckState {
+    /// Time of the last successful check, if known
+    fn last_check_at(&self) -> Option<Instant> {
+        match self {
+            CheckState::Completed { at } => Some(*at),
+            CheckState::Error { at, .. } => *at,
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     TogglePopup,
+    ToggleContextMenu,
+    MenuCheckForUpdates,
+    OpenSettings,
+    Snooze(Snooze),
     PopupClosed(Id),
     SwitchTab(PopupTab),
+    ShowAllPackages,
+    ExportUpdates,
+    UpdatesExported(Result<PathBuf, String>),
     CheckForUpdates,
     DelayedStartupCheck,
-    UpdatesChecked(Result<UpdateInfo, String>),
+    UpdatesChecked(Result<UpdateInfo, CheckError>),
+    PreviousUpdatesLoaded(Option<crate::change_summary::PendingVersions>),
+    RemoteHostsChecked(Vec<(String, Result<UpdateInfo, CheckError>)>),
+    SetRemoteHosts(String),
+    SetCustomRepoPatterns(String),
+    ToggleShowCommands,
+    CancelCheck,
+    CheckCancelled,
+    CheckProgressed(CheckProgress),
     ConfigChanged(PackageUpdaterConfig),
     LaunchTerminalUpdate,
     TerminalFinished,
+    NotificationSent,
+    /// Open a package's web page with `xdg-open`
+    OpenPackagePage(String),
+    PackagePageOpened,
+    /// The "Update now" notification action was used (`true`) or the
+    /// notification went away (`false`)
+    NotificationActionFinished(bool),
     Timer,
+    AnimationTick,
+    /// Re-render so relative times such as "Last checked" stay current
+    ClockTick,
+    /// An output appeared, changed or went away; its logical height if known
+    OutputChanged(WlOutput, Option<f32>),
     DiscoverPackageManagers,
+    PackageManagersDiscovered(Vec<PackageManager>),
     SelectPackageManager(PackageManager),
     SetCheckInterval(u32),
+    SetBadgeWarningThreshold(u32),
     ToggleAutoCheck(bool),
+    ToggleCheckOnlyOnAc(bool),
     ToggleIncludeAur(bool),
     ToggleShowNotifications(bool),
     ToggleShowUpdateCount(bool),
+    ToggleLargeBadgeText(bool),
+    ToggleMiddleClickUpdate(bool),
     SetPreferredTerminal(String),
     SyncFileChanged,
+    /// Whether the sync file watcher is running (cross-instance sync)
+    SyncWatcherStatus(bool),
+    ResetLock,
+    LockResetFinished(Result<LockReset, String>),
     SetNixOSMode(NixOSMode),
+    SetNixOSUpdateCount(NixOSUpdateCount),
     SetNixOSConfigPath(String),
     AutoDetectNixOSMode,
+    /// Look up the configuration the running system was built from
+    DetectNixOSConfigPath,
     SetNixOSHostname(String),
+    SetHomeManagerPath(String),
     AutoDetectNixOSHostname,
+    SetPreUpdateHook(String),
+    SetPostUpdateHook(String),
+    SetCustomUpdateLauncher(String),
+    ToggleInstallWithoutTerminal(bool),
+    /// A background install ended; `None` when PolicyKit is unavailable and
+    /// the terminal is used instead
+    InstallFinished(Option<Result<(), String>>),
+    SetCustomCheckCommand(String),
+    /// Whether the program of a typed custom check command was found
+    CustomCheckCommandFound(String, bool),
+    ToggleDebugLogging(bool),
+    ToggleApkRefreshIndex(bool),
+    ToggleZypperRefreshRepos(bool),
+    ToggleCompactLayout(bool),
+    ToggleShowIllustration(bool),
+    ToggleAurSingleQuery(bool),
+    SetAurCheckBackend(AurCheckBackend),
+    ToggleForeignPackageCount(bool),
+    ToggleAurRebuildHints(bool),
+    ToggleAurFirst(bool),
+    ToggleDownloadSize(bool),
+    ToggleWatchPackageDatabase(bool),
+    PackageDatabaseChanged,
+    TogglePreferNala(bool),
+    ToggleSudoAskpass(bool),
+    SetIconStyle(IconStyle),
+    SetZeroBadgeBehavior(ZeroBadgeBehavior),
+    SetNotificationUrgency(NotificationUrgency),
+    ToggleAptDistUpgradeSimulation(bool),
+    SetAptOrigins(String),
+    SetIgnoredPackages(String),
+    /// Ignore a package from its row in the list
+    IgnorePackage(String),
+    UndoIgnorePackage,
+    /// The "Undo" offer for the named package timed out
+    IgnoreUndoExpired(String),
+    ToggleSecurityOnly(bool),
+    TogglePreferSystemManager(bool),
 }
 
 impl cosmic::Application for CosmicAppletPackageUpdater {
@@ -105,46 +354,74 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
 
     fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
         let (config_handler, config) = PackageUpdaterConfig::load();
-        let available_package_managers = PackageManagerDetector::detect_available();
+        crate::check_log::set_enabled(config.debug_logging);
+        let apt_origins_input = config.apt_origins.join(", ");
+        let ignored_packages_input = config.ignored_packages.join(", ");
+        let remote_hosts_input = RemoteHost::format_list(&config.remote_hosts);
+        let custom_groups = CustomGroups::compile(&config.custom_repo_patterns);
+        let custom_patterns_input = crate::custom_groups::format_list(&config.custom_repo_patterns);
 
         let app = Self {
             core,
             popup: None,
+            context_menu: None,
             active_tab: PopupTab::Updates,
             config,
             config_handler,
             update_info: UpdateInfo::new(),
             check_state: CheckState::Idle,
-            available_package_managers,
+            available_package_managers: Vec::new(),
             ignore_next_sync: true,
+            animation_frame: 0,
+            pre_update_count: None,
+            cancel_check: None,
+            cancel_remote_check: None,
+            last_discovery: None,
+            default_terminal: crate::terminal::default_terminal(),
+            custom_check_warning: None,
+            custom_check_input: String::new(),
+            sync_watcher_active: None,
+            lock_reset_status: None,
+            installing: false,
+            new_packages: HashSet::new(),
+            previous_packages: None,
+            has_checked: false,
+            show_all_packages: false,
+            apt_origins_input,
+            ignored_packages_input,
+            ignore_undo: None,
+            output_heights: Vec::new(),
+            check_started: None,
+            state_before_check: None,
+            check_durations: VecDeque::with_capacity(CHECK_DURATION_SAMPLES),
+            export_status: None,
+            live_packages: Vec::new(),
+            remote_results: Vec::new(),
+            remote_hosts_input,
+            custom_groups,
+            custom_patterns_input,
+            check_commands: Vec::new(),
+            last_update_command: None,
+            show_commands: false,
+            sync_triggered_check: false,
+            check_requested: false,
         };
 
-        let mut tasks = vec![];
-
-        // Auto-discover package managers on startup if none is configured
-        if app.config.package_manager.is_none() {
-            tasks.push(Task::done(cosmic::Action::App(
-                Message::DiscoverPackageManagers,
-            )));
-        }
+        // Discover package managers in the background; when none is
+        // configured, picking one also triggers the startup check
+        let mut tasks = vec![Task::done(cosmic::Action::App(
+            Message::DiscoverPackageManagers,
+        ))];
 
         // Check for updates on startup if enabled and package manager is available
-        if app.config.auto_check_on_startup {
-            if app.config.package_manager.is_some() {
-                // Add a delay to allow system to stabilize
-                tasks.push(Task::perform(
-                    async move {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(STARTUP_DELAY_SECS))
-                            .await;
-                    },
-                    |_| cosmic::Action::App(Message::CheckForUpdates),
-                ));
-            } else {
-                // Delay the update check until after package manager discovery
-                tasks.push(Task::done(cosmic::Action::App(
-                    Message::DelayedStartupCheck,
-                )));
-            }
+        if app.config.auto_check_on_startup && app.config.package_manager.is_some() {
+            // Add a delay to allow system to stabilize
+            tasks.push(Task::perform(
+                async move {
+                    tokio::time::sleep(tokio::time::Duration::from_secs(STARTUP_DELAY_SECS)).await;
+                },
+                |_| cosmic::Action::App(Message::CheckForUpdates),
+            ));
         }
 
         (app, Task::batch(tasks))
@@ -155,12 +432,15 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
     }
 
     fn view(&self) -> Element<'_, Self::Message> {
+        // `show_update_count` alone picks the layout. The zero behavior only
+        // changes the badge text, so the button never collapses to the plain
+        // icon when the count drops to zero.
         if self.config.show_update_count {
-            // Always show custom button with icon and count (empty string when 0)
-            let count_text = if self.update_info.total_updates > 0 {
-                format!("{}", self.update_info.total_updates)
+            let count_text = self.badge_text();
+            let count_size = if self.config.large_badge_text {
+                LARGE_BADGE_TEXT_SIZE
             } else {
-                String::new()
+                BADGE_TEXT_SIZE
             };
 
             let custom_button = button::custom(
@@ -168,7 +448,7 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
                     .align_y(cosmic::iced::Alignment::Center)
                     .spacing(2)
                     .push(cosmic::widget::icon::from_name(self.get_icon_name()).size(16))
-                    .push(text(count_text).size(12)),
+                    .push(text(count_text).size(count_size).class(self.badge_class())),
             )
             .padding([8, 4])
             .class(cosmic::theme::Button::AppletIcon)
@@ -176,17 +456,12 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
 
             let limits = Limits::NONE.min_width(1.0).min_height(1.0);
 
-            let content: Element<_> = if self.update_info.has_updates() {
-                cosmic::widget::mouse_area(custom_button)
-                    .on_middle_press(Message::LaunchTerminalUpdate)
-                    .into()
-            } else {
-                custom_button.into()
-            };
-
-            autosize::autosize(content, cosmic::widget::Id::unique())
-                .limits(limits)
-                .into()
+            autosize::autosize(
+                self.panel_mouse_area(custom_button),
+                cosmic::widget::Id::unique(),
+            )
+            .limits(limits)
+            .into()
         } else {
             let icon_button = self
                 .core
@@ -194,17 +469,15 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
                 .icon_button(self.get_icon_name())
                 .on_press(Message::TogglePopup);
 
-            if self.update_info.has_updates() {
-                cosmic::widget::mouse_area(icon_button)
-                    .on_middle_press(Message::LaunchTerminalUpdate)
-                    .into()
-            } else {
-                icon_button.into()
-            }
+            self.panel_mouse_area(icon_button)
         }
     }
 
-    fn view_window(&self, _id: Id) -> Element<'_, Self::Message> {
+    fn view_window(&self, id: Id) -> Element<'_, Self::Message> {
+        if self.context_menu == Some(id) {
+            return self.view_context_menu();
+        }
+
         let cosmic::cosmic_theme::Spacing {
             space_s, space_m, ..
         } = cosmic::theme::active().cosmic().spacing;
@@ -240,52 +513,39 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
             PopupTab::Settings => self.view_settings_tab(),
         };
 
-        // Package illustration - dynamic based on check state
-        let (icon_name, status_label) = match &self.check_state {
-            CheckState::Checking => ("view-refresh-symbolic", "Checking...".to_string()),
-            CheckState::Error { .. } => ("dialog-error-symbolic", "Error".to_string()),
-            _ if self.update_info.has_updates() => (
-                "software-update-available-symbolic",
-                format!("{} Updates", self.update_info.total_updates),
-            ),
-            _ => ("package-x-generic", "Up to Date".to_string()),
-        };
-
-        let package_illustration = cosmic::widget::container(
-            column()
-                .align_x(cosmic::iced::Alignment::Center)
-                .spacing(12)
-                .push(cosmic::widget::icon::from_name(icon_name).size(48))
-                .push(
-                    text(status_label)
-                        .size(11)
-                        .align_x(cosmic::iced::Alignment::Center),
-                ),
-        )
-        .width(cosmic::iced::Length::Fixed(ILLUSTRATION_WIDTH))
-        .height(cosmic::iced::Length::Fixed(ILLUSTRATION_HEIGHT))
-        .align_x(cosmic::iced::alignment::Horizontal::Center)
-        .align_y(cosmic::iced::alignment::Vertical::Center)
-        .style(|_theme| cosmic::widget::container::Style {
-            background: None,
-            ..Default::default()
-        })
-        .padding(12);
-
-        // Main content area with illustration
-        let main_content = row()
-            .spacing(space_m)
-            .push(
+        // Compact layout stacks everything vertically without the illustration
+        let (main_content, padding): (Element<_>, u16) = if self.config.compact_layout {
+            (
                 column()
                     .spacing(space_s)
                     .width(cosmic::iced::Length::Fill)
-                    .push(tab_content),
+                    .push(tab_content)
+                    .into(),
+                space_s,
             )
-            .push(package_illustration);
+        } else {
+            let content = column()
+                .spacing(space_s)
+                .width(cosmic::iced::Length::Fill)
+                .push(tab_content);
+            if self.config.show_illustration {
+                // Main content area with illustration
+                (
+                    row()
+                        .spacing(space_m)
+                        .push(content)
+                        .push(self.build_illustration())
+                        .into(),
+                    space_m,
+                )
+            } else {
+                (content.into(), space_m)
+            }
+        };
 
         let content = column()
             .spacing(space_s)
-            .padding(space_m)
+            .padding(padding)
             .push(tabs)
             .push(divider::horizontal::default())
             .push(main_content);
@@ -293,146 +553,336 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
         self.core
             .applet
             .popup_container(content)
-            .limits(
-                Limits::NONE
-                    .min_height(POPUP_MIN_HEIGHT)
-                    .max_height(POPUP_MAX_HEIGHT)
-                    .min_width(POPUP_MIN_WIDTH)
-                    .max_width(POPUP_MAX_WIDTH),
-            )
+            .limits(self.popup_limits())
             .into()
     }
 
     fn update(&mut self, message: Self::Message) -> Task<Self::Message> {
         match message {
             Message::TogglePopup => self.handle_toggle_popup(),
+            Message::ToggleContextMenu => self.handle_toggle_context_menu(),
+            Message::MenuCheckForUpdates => {
+                let close = self.close_context_menu();
+                Task::batch(vec![
+                    close,
+                    Task::done(cosmic::Action::App(Message::CheckForUpdates)),
+                ])
+            }
+            Message::OpenSettings => {
+                let close = self.close_context_menu();
+                let switch = self.handle_switch_tab(PopupTab::Settings);
+                let open = if self.popup.is_none() {
+                    self.handle_toggle_popup()
+                } else {
+                    Task::none()
+                };
+                Task::batch(vec![close, switch, open])
+            }
+            Message::Snooze(snooze) => {
+                let close = self.close_context_menu();
+                let until = Self::snooze_until(snooze);
+                let update = self.update_config(|c| c.snooze_until = until);
+                Task::batch(vec![close, update])
+            }
             Message::PopupClosed(id) => self.handle_popup_closed(id),
             Message::SwitchTab(tab) => self.handle_switch_tab(tab),
+            Message::ExportUpdates => {
+                if let Some(pm) = self.config.package_manager {
+                    let info = self.update_info.clone();
+                    return Task::perform(
+                        async move {
+                            tokio::task::spawn_blocking(move || {
+                                crate::export::export_updates(pm, &info).map_err(|e| e.to_string())
+                            })
+                            .await
+                            .unwrap_or_else(|e| Err(e.to_string()))
+                        },
+                        |result| cosmic::Action::App(Message::UpdatesExported(result)),
+                    );
+                }
+                Task::none()
+            }
+            Message::UpdatesExported(result) => {
+                self.export_status = Some(match result {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+                Task::none()
+            }
+            Message::PreviousUpdatesLoaded(previous) => {
+                // A later check may have set the previous result meanwhile
+                if self.previous_packages.is_none() {
+                    self.previous_packages = previous;
+                }
+                Task::none()
+            }
+            Message::ShowAllPackages => {
+                self.show_all_packages = true;
+                Task::none()
+            }
             Message::CheckForUpdates => {
+                if matches!(self.check_state, CheckState::Checking) {
+                    // Settings changes and finished installs can land mid-check;
+                    // starting a second check would orphan the first one's
+                    // cancel handle, so run it once this one is done
+                    self.check_requested = true;
+                    return Task::none();
+                }
                 if let Some(pm) = self.config.package_manager {
+                    self.state_before_check = Some(self.check_state.clone());
                     self.check_state = CheckState::Checking;
-                    let checker = UpdateChecker::new(pm);
+                    self.animation_frame = 0;
+                    self.check_started = Some(Instant::now());
+                    let checker = UpdateChecker::with_options(pm, self.check_options());
                     let include_aur = self.config.include_aur_updates;
                     let nixos_config = self.config.nixos_config.clone();
-                    return Task::perform(
-                        async move { checker.check_updates(include_aur, &nixos_config).await },
-                        |result| {
-                            cosmic::Action::App(Message::UpdatesChecked(
-                                result.map_err(|e| e.to_string()),
-                            ))
+                    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+                    self.cancel_check = Some(cancel_tx);
+                    self.live_packages.clear();
+                    if let Some(undo) = &mut self.ignore_undo {
+                        undo.live_removed.clear();
+                    }
+                    self.check_commands.clear();
+                    use futures::StreamExt;
+                    let check = async_stream::stream! {
+                        let started = Instant::now();
+                        let mut cancel_rx = cancel_rx;
+                        let progress = checker.check_updates_with_progress(include_aur, &nixos_config);
+                        futures::pin_mut!(progress);
+
+                        loop {
+                            // Dropping the progress stream releases the lock and
+                            // kills its child processes (kill_on_drop)
+                            let event = tokio::select! {
+                                event = progress.next() => Some(event),
+                                Ok(()) = &mut cancel_rx => None,
+                            };
+                            let Some(event) = event else {
+                                crate::check_log::warn(format!(
+                                    "Update check cancelled after {:?}",
+                                    started.elapsed()
+                                ));
+                                yield Message::CheckCancelled;
+                                break;
+                            };
+
+                            let message = match event {
+                                Some(CheckProgress::Finished(update_info)) => {
+                                    let result = Ok(update_info);
+                                    crate::check_log::record_check(pm, &result, started.elapsed());
+                                    Message::UpdatesChecked(result)
+                                }
+                                Some(CheckProgress::Failed(error)) => {
+                                    let result = Err(error);
+                                    crate::check_log::record_check(pm, &result, started.elapsed());
+                                    Message::UpdatesChecked(result)
+                                }
+                                Some(event) => Message::CheckProgressed(event),
+                                None => break,
+                            };
+                            yield message;
+                        }
+                    };
+                    let local = Task::run(check, cosmic::Action::App);
+                    if self.config.remote_hosts.is_empty() {
+                        return local;
+                    }
+                    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+                    self.cancel_remote_check = Some(cancel_tx);
+                    let remote = Task::perform(
+                        Self::check_remote_hosts(
+                            self.config.remote_hosts.clone(),
+                            // The custom command is set up on this machine only
+                            CheckOptions {
+                                custom_check_command: None,
+                                ..self.check_options()
+                            },
+                            self.config.include_aur_updates,
+                            cancel_rx,
+                        ),
+                        |results| {
+                            cosmic::Action::App(match results {
+                                Some(results) => Message::RemoteHostsChecked(results),
+                                None => Message::CheckCancelled,
+                            })
                         },
                     );
+                    return Task::batch([local, remote]);
                 }
                 Task::none()
             }
-            Message::UpdatesChecked(result) => {
-                match result {
-                    Ok(update_info) => {
-                        self.update_info = update_info;
-                        self.check_state = CheckState::Completed { at: Instant::now() };
-                    }
-                    Err(error) => {
-                        let last_check = match &self.check_state {
-                            CheckState::Completed { at } => Some(*at),
-                            CheckState::Error { at, .. } => *at,
-                            _ => None,
-                        };
-                        // Handle specific Wayland errors that might occur after system updates
-                        let message = if error.contains("Protocol error") || error.contains("wl_surface") {
-                            "Display system updated. Please restart the applet if issues persist.".to_string()
+            Message::ToggleShowCommands => {
+                self.show_commands = !self.show_commands;
+                Task::none()
+            }
+            Message::RemoteHostsChecked(results) => {
+                self.cancel_remote_check = None;
+                let at = Instant::now();
+                self.remote_results = results
+                    .into_iter()
+                    .map(|(target, result)| {
+                        let last_success = if result.is_ok() {
+                            Some(at)
                         } else {
-                            error
+                            self.remote_results
+                                .iter()
+                                .find(|check| check.target == target)
+                                .and_then(|check| check.last_success)
                         };
-                        self.check_state = CheckState::Error { message, at: last_check };
+                        let result = result.map(|mut info| {
+                            info.remove_packages(&self.config.ignored_packages);
+                            info
+                        });
+                        RemoteCheck {
+                            target,
+                            result,
+                            at,
+                            last_success,
+                        }
+                    })
+                    .collect();
+                Task::none()
+            }
+            Message::SetRemoteHosts(input) => {
+                let hosts = RemoteHost::parse_list(&input);
+                self.remote_hosts_input = input;
+                // Drop results of hosts that are no longer configured
+                self.remote_results
+                    .retain(|check| hosts.iter().any(|host| host.target == check.target));
+                self.update_config(|c| c.remote_hosts = hosts)
+            }
+            Message::SetCustomRepoPatterns(input) => {
+                let patterns = crate::custom_groups::parse_list(&input);
+                self.custom_patterns_input = input;
+                self.update_config(|c| c.custom_repo_patterns = patterns)
+            }
+            Message::CancelCheck => {
+                if let Some(cancel) = self.cancel_remote_check.take() {
+                    let _ = cancel.send(());
+                }
+                if let Some(cancel) = self.cancel_check.take() {
+                    let _ = cancel.send(());
+                    self.sync_triggered_check = false;
+                    self.check_requested = false;
+                    self.check_state = self.state_before_check.take().unwrap_or(CheckState::Idle);
+                    self.check_started = None;
+                    self.live_packages.clear();
+                }
+                Task::none()
+            }
+            Message::CheckCancelled => Task::none(),
+            Message::CheckProgressed(progress) => {
+                match progress {
+                    CheckProgress::FoundPackage(package) => {
+                        if !self.config.ignored_packages.contains(&package.name) {
+                            self.live_packages.push(package);
+                        }
                     }
+                    CheckProgress::RanCommand(command) => self.check_commands.push(command),
+                    _ => {}
                 }
                 Task::none()
             }
+            Message::UpdatesChecked(result) => {
+                let task = self.handle_updates_checked(result);
+                if std::mem::take(&mut self.check_requested) {
+                    return Task::batch([
+                        task,
+                        Task::done(cosmic::Action::App(Message::CheckForUpdates)),
+                    ]);
+                }
+                task
+            }
             Message::LaunchTerminalUpdate => {
-                if let Some(pm) = self.config.package_manager {
-                    let terminal = self.config.preferred_terminal.clone();
-                    let nixos_config = self.config.nixos_config.clone();
-                    let command = pm.system_update_command(Some(&nixos_config));
-
+                if let Some(launcher) = self
+                    .config
+                    .custom_update_launcher
+                    .clone()
+                    .filter(|launcher| !launcher.trim().is_empty())
+                {
+                    self.last_update_command = Some(format!(
+                        "sh -c {}",
+                        shell_escape::escape(launcher.as_str().into())
+                    ));
+                    return Self::run_update_launcher(launcher);
+                }
+                if let Some(pm) = self.background_install_manager() {
+                    if self.installing {
+                        return Task::none();
+                    }
+                    self.installing = true;
+                    if let Some((command, args)) = pm.noninteractive_update_command() {
+                        self.last_update_command =
+                            Some(crate::polkit::pkexec_command_line(command, args));
+                    }
                     return Task::perform(
                         async move {
-                            // Create a unique marker file to track when the terminal closes
-                            let runtime_dir =
-                                std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|e| {
-                                    eprintln!(
-                                        "Warning: XDG_RUNTIME_DIR not set: {}. Using /tmp",
-                                        e
-                                    );
-                                    "/tmp".to_string()
-                                });
-                            let marker_file = format!(
-                                "{}/cosmic-package-updater-terminal-{}.marker",
-                                runtime_dir,
-                                std::process::id()
-                            );
-
-                            // Create the marker file
-                            if let Err(e) = std::fs::File::create(&marker_file) {
-                                eprintln!("Warning: Failed to create marker file: {}", e);
-                            }
-
-                            // Build command that removes marker file when done
-                            // Use shell-escape for proper escaping
-                            let escaped_marker = shell_escape::escape(marker_file.clone().into());
-                            let wrapped_command = format!(
-                                "{} && echo 'Update completed. Press Enter to exit...' && read; rm -f {}",
-                                command.replace("\"", "\\\""),
-                                escaped_marker
-                            );
-
-                            // Spawn the terminal (it will return immediately due to daemonization)
-                            match tokio::process::Command::new(&terminal)
-                                .arg("-e")
-                                .arg("sh")
-                                .arg("-c")
-                                .arg(&wrapped_command)
-                                .spawn()
-                            {
-                                Ok(_) => {
-                                    // Poll for marker file deletion (terminal closed)
-                                    loop {
-                                        if !std::path::Path::new(&marker_file).exists() {
-                                            break;
-                                        }
-                                        tokio::time::sleep(tokio::time::Duration::from_millis(
-                                            MARKER_FILE_POLL_INTERVAL_MS,
-                                        ))
-                                        .await;
-                                    }
-
-                                    // Add a delay to allow system to stabilize after update
-                                    tokio::time::sleep(tokio::time::Duration::from_secs(
-                                        POST_UPDATE_STABILIZATION_SECS,
-                                    ))
-                                    .await;
-                                }
-                                Err(e) => {
-                                    eprintln!("Failed to spawn terminal: {}", e);
-                                    // Clean up marker file on error
-                                    if let Err(e) = std::fs::remove_file(&marker_file) {
-                                        eprintln!("Warning: Failed to remove marker file: {}", e);
-                                    }
-                                }
+                            if !crate::polkit::PolkitAuth::is_available().await {
+                                return None;
                             }
+                            let result = UpdateChecker::new(pm).install_updates().await;
+                            Some(result.map_err(|e| e.to_string()))
                         },
-                        |()| cosmic::Action::App(Message::TerminalFinished),
+                        |result| cosmic::Action::App(Message::InstallFinished(result)),
                     );
                 }
-                Task::none()
+                self.launch_terminal_update()
+            }
+            Message::InstallFinished(result) => {
+                self.installing = false;
+                match result {
+                    None => self.launch_terminal_update(),
+                    Some(Ok(())) => {
+                        self.pre_update_count = Some(self.update_info.total_updates);
+                        Task::done(cosmic::Action::App(Message::CheckForUpdates))
+                    }
+                    Some(Err(error)) => {
+                        self.check_state = CheckState::Error {
+                            message: format!("Installing updates failed: {}", error),
+                            at: self.check_state.last_check_at(),
+                            since: Instant::now(),
+                        };
+                        Task::none()
+                    }
+                }
             }
             Message::TerminalFinished => {
                 // Terminal has finished, trigger update check immediately
+                self.pre_update_count = Some(self.update_info.total_updates);
                 Task::done(cosmic::Action::App(Message::CheckForUpdates))
             }
+            Message::NotificationSent => Task::none(),
+            Message::OpenPackagePage(url) => Task::perform(
+                async move {
+                    match tokio::process::Command::new("xdg-open").arg(&url).status().await {
+                        Ok(status) if !status.success() => {
+                            crate::check_log::warn(format!(
+                                "xdg-open {} exited with {}",
+                                url, status
+                            ));
+                        }
+                        Err(e) => crate::check_log::warn(format!("Failed to run xdg-open: {}", e)),
+                        Ok(_) => {}
+                    }
+                },
+                |()| cosmic::Action::App(Message::PackagePageOpened),
+            ),
+            Message::PackagePageOpened => Task::none(),
+            Message::NotificationActionFinished(invoked) => {
+                if invoked && self.update_info.has_updates() {
+                    Task::done(cosmic::Action::App(Message::LaunchTerminalUpdate))
+                } else {
+                    Task::none()
+                }
+            }
             Message::ConfigChanged(config) => {
                 let old_package_manager = self.config.package_manager;
+                if config.custom_repo_patterns != self.config.custom_repo_patterns {
+                    self.custom_groups = CustomGroups::compile(&config.custom_repo_patterns);
+                }
                 self.config = config;
                 PackageUpdaterConfig::set_entry(&self.config_handler, &self.config);
+                crate::check_log::set_enabled(self.config.debug_logging);
 
                 // If package manager was just auto-configured and startup check is enabled,
                 // trigger the delayed startup check
@@ -446,18 +896,45 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
                 }
             }
             Message::Timer => {
+                self.clear_stale_error();
+
                 // Automatically check for updates if a package manager is configured
                 // and we're not already checking
-                if !matches!(self.check_state, CheckState::Checking) && self.config.package_manager.is_some() {
+                if !matches!(self.check_state, CheckState::Checking)
+                    && self.config.package_manager.is_some()
+                    && !self.on_battery()
+                {
                     Task::done(cosmic::Action::App(Message::CheckForUpdates))
                 } else {
                     Task::none()
                 }
             }
+            Message::ClockTick => Task::none(),
+            Message::OutputChanged(output, height) => {
+                self.output_heights.retain(|(known, _)| *known != output);
+                if let Some(height) = height {
+                    self.output_heights.push((output, height));
+                }
+                Task::none()
+            }
+            Message::AnimationTick => {
+                self.animation_frame = (self.animation_frame + 1) % CHECKING_ICON_FRAMES.len();
+                Task::none()
+            }
             Message::DiscoverPackageManagers => {
-                self.available_package_managers = PackageManagerDetector::detect_available();
+                self.last_discovery = Some(Instant::now());
+                Task::perform(PackageManagerDetector::detect_available_async(), |found| {
+                    cosmic::Action::App(Message::PackageManagersDiscovered(found))
+                })
+            }
+            Message::PackageManagersDiscovered(found) => {
+                self.available_package_managers = found;
                 if self.config.package_manager.is_none() {
-                    if let Some(preferred) = PackageManagerDetector::get_preferred() {
+                    if let Some(preferred) = PackageManagerDetector::get_preferred(
+                        &self.available_package_managers,
+                        self.config.prefer_system_manager,
+                        self.config.prefer_nala,
+                    ) {
                         let mut config = self.config.clone();
                         config.package_manager = Some(preferred);
                         return Task::done(cosmic::Action::App(Message::ConfigChanged(config)));
@@ -467,7 +944,10 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
             }
             Message::DelayedStartupCheck => {
                 // Triggered after package manager discovery to perform startup update check
-                if self.config.auto_check_on_startup && self.config.package_manager.is_some() {
+                if self.config.auto_check_on_startup
+                    && self.config.package_manager.is_some()
+                    && !self.on_battery()
+                {
                     // Add a delay to allow system to stabilize
                     Task::perform(
                         async move {
@@ -483,14 +963,22 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
                 }
             }
             Message::SelectPackageManager(pm) => {
+                self.custom_check_warning = None;
+                self.custom_check_input.clear();
                 self.update_config(|c| c.package_manager = Some(pm))
             }
             Message::SetCheckInterval(interval) => {
                 self.update_config(|c| c.check_interval_minutes = interval)
             }
+            Message::SetBadgeWarningThreshold(threshold) => {
+                self.update_config(|c| c.badge_warning_threshold = threshold)
+            }
             Message::ToggleAutoCheck(enabled) => {
                 self.update_config(|c| c.auto_check_on_startup = enabled)
             }
+            Message::ToggleCheckOnlyOnAc(enabled) => {
+                self.update_config(|c| c.check_only_on_ac = enabled)
+            }
             Message::ToggleIncludeAur(enabled) => {
                 self.update_config(|c| c.include_aur_updates = enabled)
             }
@@ -500,9 +988,41 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
             Message::ToggleShowUpdateCount(enabled) => {
                 self.update_config(|c| c.show_update_count = enabled)
             }
+            Message::ToggleLargeBadgeText(enabled) => {
+                self.update_config(|c| c.large_badge_text = enabled)
+            }
+            Message::ToggleMiddleClickUpdate(enabled) => {
+                self.update_config(|c| c.enable_middle_click_update = enabled)
+            }
             Message::SetPreferredTerminal(terminal) => {
                 self.update_config(|c| c.preferred_terminal = terminal)
             }
+            Message::SyncWatcherStatus(active) => {
+                self.sync_watcher_active = Some(active);
+                Task::none()
+            }
+            Message::ResetLock => Task::perform(
+                async {
+                    tokio::task::spawn_blocking(|| {
+                        UpdateChecker::reset_lock().map_err(|e| e.to_string())
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.to_string()))
+                },
+                |result| cosmic::Action::App(Message::LockResetFinished(result)),
+            ),
+            Message::LockResetFinished(result) => {
+                self.lock_reset_status = Some(match result {
+                    Ok(LockReset::NotHeld) => "The update lock is not held.".to_string(),
+                    Ok(LockReset::Removed) => "Update lock reset.".to_string(),
+                    Ok(LockReset::HeldBy(pid)) => format!(
+                        "The lock is held by a running instance (PID {}); not reset.",
+                        pid
+                    ),
+                    Err(e) => format!("Failed to reset the update lock: {}", e),
+                });
+                Task::none()
+            }
             Message::SyncFileChanged => {
                 // Ignore the first sync event on startup (file creation triggers watcher)
                 if self.ignore_next_sync {
@@ -513,16 +1033,13 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
                 // Another instance completed an update check, sync our state
                 // Only sync if we're not already checking and haven't checked very recently
                 if !matches!(self.check_state, CheckState::Checking) && self.config.package_manager.is_some() {
-                    let last_check_at = match &self.check_state {
-                        CheckState::Completed { at } => Some(*at),
-                        CheckState::Error { at, .. } => *at,
-                        _ => None,
-                    };
+                    let last_check_at = self.check_state.last_check_at();
                     let should_sync = last_check_at.map_or(true, |last| {
                         last.elapsed().as_secs() > SYNC_DEBOUNCE_SECS
                     });
 
                     if should_sync {
+                        self.sync_triggered_check = true;
                         Task::done(cosmic::Action::App(Message::CheckForUpdates))
                     } else {
                         Task::none()
@@ -534,6 +1051,9 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
             Message::SetNixOSMode(mode) => {
                 self.update_config(|c| c.nixos_config.mode = mode)
             }
+            Message::SetNixOSUpdateCount(count) => {
+                self.update_config(|c| c.nixos_config.update_count = count)
+            }
             Message::SetNixOSConfigPath(path) => {
                 self.update_config(|c| c.nixos_config.config_path = path)
             }
@@ -542,6 +1062,15 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
                 let detected_mode = PackageManagerDetector::detect_nixos_mode(&config_path);
                 self.update_config(|c| c.nixos_config.mode = detected_mode)
             }
+            Message::DetectNixOSConfigPath => {
+                Task::perform(PackageManagerDetector::detect_nixos_config_path(), |path| {
+                    cosmic::Action::App(Message::SetNixOSConfigPath(path))
+                })
+            }
+            Message::SetHomeManagerPath(path) => {
+                let path = if path.trim().is_empty() { None } else { Some(path) };
+                self.update_config(|c| c.nixos_config.home_manager_path = path)
+            }
             Message::SetNixOSHostname(hostname) => {
                 let hostname = if hostname.trim().is_empty() {
                     None
@@ -554,6 +1083,199 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
                 let detected = crate::config::detect_hostname();
                 self.update_config(|c| c.nixos_config.hostname = detected)
             }
+            Message::SetPreUpdateHook(hook) => {
+                let hook = if hook.trim().is_empty() { None } else { Some(hook) };
+                self.update_config(|c| c.pre_update_hook = hook)
+            }
+            Message::SetPostUpdateHook(hook) => {
+                let hook = if hook.trim().is_empty() { None } else { Some(hook) };
+                self.update_config(|c| c.post_update_hook = hook)
+            }
+            Message::ToggleInstallWithoutTerminal(enabled) => {
+                self.update_config(|c| c.install_without_terminal = enabled)
+            }
+            Message::SetCustomUpdateLauncher(launcher) => {
+                let launcher = if launcher.trim().is_empty() {
+                    None
+                } else {
+                    Some(launcher)
+                };
+                self.update_config(|c| c.custom_update_launcher = launcher)
+            }
+            Message::SetCustomCheckCommand(command) => {
+                let Some(pm) = self.config.package_manager else {
+                    return Task::none();
+                };
+                if command.trim().is_empty() {
+                    self.custom_check_input.clear();
+                    self.custom_check_warning = None;
+                    return self.update_config(|c| {
+                        c.custom_check_commands.remove(&pm);
+                    });
+                }
+                self.custom_check_input = command.clone();
+                let lookup = command.clone();
+                let typed = command.clone();
+                // Looked up off the UI thread, as it runs `which`
+                let validate = Task::perform(
+                    async move { crate::package_manager::command_exists(&lookup).await },
+                    move |found| {
+                        cosmic::Action::App(Message::CustomCheckCommandFound(typed.clone(), found))
+                    },
+                );
+                Task::batch([
+                    self.update_config(|c| {
+                        c.custom_check_commands.insert(pm, command);
+                    }),
+                    validate,
+                ])
+            }
+            Message::CustomCheckCommandFound(command, found) => {
+                // Only the answer for what is typed now counts
+                if command == self.custom_check_input {
+                    self.custom_check_warning = (!found).then(|| {
+                        format!(
+                            "Warning: '{}' was not found; checks will fail until it is installed.",
+                            command.trim()
+                        )
+                    });
+                }
+                Task::none()
+            }
+            Message::ToggleDebugLogging(enabled) => {
+                self.update_config(|c| c.debug_logging = enabled)
+            }
+            Message::ToggleApkRefreshIndex(enabled) => {
+                self.update_config(|c| c.apk_refresh_index = enabled)
+            }
+            Message::ToggleZypperRefreshRepos(enabled) => {
+                self.update_config(|c| c.zypper_refresh_repos = enabled)
+            }
+            Message::ToggleCompactLayout(enabled) => {
+                self.update_config(|c| c.compact_layout = enabled)
+            }
+            Message::ToggleShowIllustration(enabled) => {
+                self.update_config(|c| c.show_illustration = enabled)
+            }
+            Message::ToggleAurSingleQuery(enabled) => {
+                self.update_config(|c| c.aur_single_query = enabled)
+            }
+            Message::SetAurCheckBackend(backend) => {
+                self.update_config(|c| c.aur_check_backend = backend)
+            }
+            Message::ToggleForeignPackageCount(enabled) => {
+                self.update_config(|c| c.show_foreign_package_count = enabled)
+            }
+            Message::ToggleWatchPackageDatabase(enabled) => {
+                self.update_config(|c| c.watch_package_database = enabled)
+            }
+            Message::PackageDatabaseChanged => {
+                if matches!(self.check_state, CheckState::Checking) {
+                    Task::none()
+                } else {
+                    Task::done(cosmic::Action::App(Message::CheckForUpdates))
+                }
+            }
+            Message::ToggleDownloadSize(enabled) => {
+                self.update_config(|c| c.show_download_size = enabled)
+            }
+            Message::ToggleAurFirst(enabled) => self.update_config(|c| c.aur_first = enabled),
+            Message::ToggleAurRebuildHints(enabled) => {
+                self.update_config(|c| c.aur_rebuild_hints = enabled)
+            }
+            Message::SetIconStyle(style) => self.update_config(|c| c.icon_theme = style),
+            Message::SetNotificationUrgency(urgency) => {
+                self.update_config(|c| c.notification_urgency = urgency)
+            }
+            Message::SetZeroBadgeBehavior(behavior) => {
+                self.update_config(|c| c.zero_badge_behavior = behavior)
+            }
+            Message::ToggleAptDistUpgradeSimulation(enabled) => {
+                self.update_config(|c| c.apt_use_dist_upgrade_simulation = enabled)
+            }
+            Message::SetAptOrigins(input) => {
+                let origins = input
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|origin| !origin.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                self.apt_origins_input = input;
+                self.update_config(|c| c.apt_origins = origins)
+            }
+            Message::SetIgnoredPackages(input) => {
+                let names: Vec<String> = input
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                // Names removed here show up again at the next check
+                self.update_info.remove_packages(&names);
+                self.ignored_packages_input = input;
+                self.update_config(|c| c.ignored_packages = names)
+            }
+            Message::IgnorePackage(name) => {
+                let removed = self
+                    .update_info
+                    .remove_packages(std::slice::from_ref(&name));
+                let (live_removed, live_kept) = std::mem::take(&mut self.live_packages)
+                    .into_iter()
+                    .partition(|p| p.name == name);
+                self.live_packages = live_kept;
+                let mut ignored = self.config.ignored_packages.clone();
+                if !ignored.contains(&name) {
+                    ignored.push(name.clone());
+                }
+                self.ignored_packages_input = ignored.join(", ");
+                self.ignore_undo = Some(IgnoreUndo {
+                    name: name.clone(),
+                    removed,
+                    live_removed,
+                });
+                let expire = Task::perform(
+                    tokio::time::sleep(Duration::from_secs(IGNORE_UNDO_SECS)),
+                    move |()| cosmic::Action::App(Message::IgnoreUndoExpired(name)),
+                );
+                Task::batch([self.update_config(|c| c.ignored_packages = ignored), expire])
+            }
+            Message::UndoIgnorePackage => {
+                let Some(undo) = self.ignore_undo.take() else {
+                    return Task::none();
+                };
+                // Taken off the ignored list in the settings and checked
+                // since: the result already holds the current updates
+                if !self.update_info.has_update_for(&undo.name) {
+                    self.update_info.restore_packages(undo.removed);
+                }
+                if matches!(self.check_state, CheckState::Checking) {
+                    self.live_packages.extend(undo.live_removed);
+                }
+                let mut ignored = self.config.ignored_packages.clone();
+                ignored.retain(|ignored| *ignored != undo.name);
+                self.ignored_packages_input = ignored.join(", ");
+                self.update_config(|c| c.ignored_packages = ignored)
+            }
+            Message::IgnoreUndoExpired(name) => {
+                if self
+                    .ignore_undo
+                    .as_ref()
+                    .is_some_and(|undo| undo.name == name)
+                {
+                    self.ignore_undo = None;
+                }
+                Task::none()
+            }
+            Message::ToggleSecurityOnly(enabled) => {
+                self.update_config(|c| c.security_only = enabled)
+            }
+            Message::TogglePreferNala(enabled) => self.update_config(|c| c.prefer_nala = enabled),
+            Message::ToggleSudoAskpass(enabled) => {
+                self.update_config(|c| c.sudo_askpass = enabled)
+            }
+            Message::TogglePreferSystemManager(enabled) => {
+                self.update_config(|c| c.prefer_system_manager = enabled)
+            }
         }
     }
 
@@ -574,6 +1296,55 @@ impl cosmic::Application for CosmicAppletPackageUpdater {
             subscriptions.push(sync_subscription);
         }
 
+        // Re-check after packages change outside the applet
+        if self.config.watch_package_database {
+            if let Some(path) = self
+                .config
+                .package_manager
+                .and_then(|pm| pm.database_path())
+            {
+                subscriptions.push(Subscription::run_with_id(
+                    ("package_db_watcher", path),
+                    Self::watch_package_database(PathBuf::from(path)),
+                ));
+            }
+        }
+
+        // Drive the panel icon animation only while a check is running
+        if matches!(self.check_state, CheckState::Checking) {
+            subscriptions.push(
+                time::every(Duration::from_millis(CHECKING_ANIMATION_INTERVAL_MS))
+                    .map(|_| Message::AnimationTick),
+            );
+        }
+
+        // Keep "Last checked: N minutes ago" current while the popup is open,
+        // and restore the panel icon once a snooze runs out
+        if self.popup.is_some() || self.is_snoozed() {
+            subscriptions.push(
+                time::every(Duration::from_secs(CLOCK_REFRESH_INTERVAL_SECS))
+                    .map(|_| Message::ClockTick),
+            );
+        }
+
+        // Track output sizes so the popup never outgrows the screen
+        subscriptions.push(event::listen_with(|event, _, _| match event {
+            cosmic::iced::Event::PlatformSpecific(PlatformSpecific::Wayland(
+                wayland::Event::Output(output_event, output),
+            )) => {
+                let height = match output_event {
+                    wayland::OutputEvent::Created(info) => info.and_then(|info| info.logical_size),
+                    wayland::OutputEvent::InfoUpdate(info) => info.logical_size,
+                    wayland::OutputEvent::Removed => None,
+                };
+                Some(Message::OutputChanged(
+                    output,
+                    height.map(|(_, height)| height as f32),
+                ))
+            }
+            _ => None,
+        }));
+
         if subscriptions.is_empty() {
             Subscription::none()
         } else {
@@ -590,7 +1361,487 @@ impl CosmicAppletPackageUpdater {
         Task::done(cosmic::Action::App(Message::ConfigChanged(config)))
     }
 
-    fn get_sync_path() -> PathBuf {
+    /// Whether the security-only filter applies to the current package manager
+    fn security_only(&self) -> bool {
+        self.security_only_for(self.config.package_manager)
+    }
+
+    /// Whether the security-only filter applies to `package_manager`, which
+    /// has to mark security updates
+    fn security_only_for(&self, package_manager: Option<PackageManager>) -> bool {
+        self.config.security_only
+            && matches!(
+                package_manager,
+                Some(PackageManager::Apt | PackageManager::Dnf)
+            )
+    }
+
+    /// Number shown next to the panel icon
+    fn badge_count(&self) -> usize {
+        let local = if self.security_only() {
+            self.update_info.security_updates
+        } else {
+            self.update_info.total_updates
+        };
+        local + self.remote_update_count()
+    }
+
+    /// Updates pending on the remote hosts that answered
+    fn remote_update_count(&self) -> usize {
+        self.remote_results
+            .iter()
+            .filter_map(|check| {
+                let info = check.result.as_ref().ok()?;
+                Some(self.remote_shown_count(&check.target, info))
+            })
+            .sum()
+    }
+
+    /// Updates counted for remote host `target`, honouring the security-only
+    /// filter when the host runs apt or dnf
+    fn remote_shown_count(&self, target: &str, info: &UpdateInfo) -> usize {
+        let package_manager = self
+            .config
+            .remote_hosts
+            .iter()
+            .find(|host| host.target == target)
+            .map(|host| host.package_manager);
+        if self.security_only_for(package_manager) {
+            info.security_updates
+        } else {
+            info.total_updates
+        }
+    }
+
+    /// Oldest of the last successful checks across the local manager and
+    /// the remote hosts, so "Last checked" never overstates freshness
+    fn overall_last_check(&self) -> Option<Instant> {
+        self.check_state
+            .last_check_at()
+            .into_iter()
+            .chain(self.remote_results.iter().filter_map(|check| check.last_success))
+            .min()
+    }
+
+    /// Check every remote host over SSH, concurrently.
+    ///
+    /// Hosts are independent: one unreachable host only fails its own entry.
+    /// Returns `None` when `cancel` fires first, which stops the ssh
+    /// processes.
+    async fn check_remote_hosts(
+        hosts: Vec<RemoteHost>,
+        options: CheckOptions,
+        include_aur: bool,
+        cancel: tokio::sync::oneshot::Receiver<()>,
+    ) -> Option<Vec<(String, Result<UpdateInfo, CheckError>)>> {
+        let nixos_config = crate::config::NixOSConfig::default();
+        let checks = hosts.into_iter().map(|host| {
+            let options = options.clone();
+            let nixos_config = &nixos_config;
+            async move {
+                let started = Instant::now();
+                let runner = ProcessRunner::Ssh(host.target.clone());
+                let label = runner.label().to_string();
+                let checker =
+                    UpdateChecker::with_options(host.package_manager, options).with_runner(runner);
+                let result = checker.check_updates(include_aur, nixos_config).await;
+                if let Err(e) = &result {
+                    crate::check_log::warn(format!(
+                        "Remote check of {} failed after {:?}: {}",
+                        label,
+                        started.elapsed(),
+                        e
+                    ));
+                }
+                (host.target, result)
+            }
+        });
+        tokio::select! {
+            results = futures::future::join_all(checks) => Some(results),
+            Ok(()) = cancel => None,
+        }
+    }
+
+    /// Keep the duration of a successful check for the rolling average
+    fn record_check_duration(&mut self, duration: Duration) {
+        if self.check_durations.len() == CHECK_DURATION_SAMPLES {
+            self.check_durations.pop_front();
+        }
+        self.check_durations.push_back(duration);
+    }
+
+    /// Average duration of the recent successful checks
+    fn average_check_duration(&self) -> Option<Duration> {
+        if self.check_durations.is_empty() {
+            return None;
+        }
+        let total: Duration = self.check_durations.iter().sum();
+        Some(total / self.check_durations.len() as u32)
+    }
+
+    /// Warning shown when checks may overlap because the interval is shorter
+    /// than about twice the time a check takes
+    fn check_interval_warning(&self) -> Option<String> {
+        let average = self.average_check_duration()?;
+        let interval = Duration::from_secs(self.config.check_interval_minutes as u64 * 60);
+        if interval >= average * 2 {
+            return None;
+        }
+        Some(format!(
+            "Checks currently take about {}s; an interval this short may overlap with a running check.",
+            average.as_secs().max(1)
+        ))
+    }
+
+    /// Panel badge text: the count, or the configured zero behavior.
+    ///
+    /// The check mark only appears after a successful check, so an error or a
+    /// running check never looks like "up to date". `ShowZero` renders "0" in
+    /// every state.
+    fn badge_text(&self) -> String {
+        let count = self.badge_count();
+        if count > 0 {
+            return count.to_string();
+        }
+
+        let zero_text = match self.config.zero_badge_behavior {
+            ZeroBadgeBehavior::Hidden => "",
+            ZeroBadgeBehavior::ShowZero => "0",
+            ZeroBadgeBehavior::Checkmark => {
+                if matches!(self.check_state, CheckState::Completed { .. }) {
+                    "✓"
+                } else {
+                    ""
+                }
+            }
+        };
+        zero_text.to_string()
+    }
+
+    /// Text style of the panel badge: dimmed while the count may be stale
+    /// (a check is running or the last one failed, which the icon shows),
+    /// destructive when security updates are pending, warning once the count
+    /// reaches the configured threshold
+    fn badge_class(&self) -> cosmic::theme::Text {
+        let threshold = self.config.badge_warning_threshold as usize;
+
+        if matches!(
+            self.check_state,
+            CheckState::Checking | CheckState::Error { .. }
+        ) {
+            cosmic::theme::Text::Custom(|theme| {
+                let mut color: cosmic::iced::Color = theme.cosmic().on_bg_color().into();
+                color.a *= STALE_BADGE_ALPHA;
+                cosmic::iced::widget::text::Style { color: Some(color) }
+            })
+        } else if self.is_snoozed() {
+            cosmic::theme::Text::Default
+        } else if self.update_info.security_updates > 0 {
+            cosmic::theme::Text::Custom(|theme| cosmic::iced::widget::text::Style {
+                color: Some(theme.cosmic().destructive_color().into()),
+            })
+        } else if threshold > 0 && self.badge_count() >= threshold {
+            cosmic::theme::Text::Custom(|theme| cosmic::iced::widget::text::Style {
+                color: Some(theme.cosmic().warning_color().into()),
+            })
+        } else {
+            cosmic::theme::Text::Default
+        }
+    }
+
+    /// Packages shown in the package list, honouring the security-only filter
+    fn shown_packages(&self) -> impl Iterator<Item = &crate::package_manager::PackageUpdate> {
+        let security_only = self.security_only();
+        self.update_info
+            .packages
+            .iter()
+            .filter(move |p| !security_only || p.is_security)
+            .filter(|p| !p.phased)
+    }
+
+    /// Build the status illustration shown beside the tab content
+    fn build_illustration(&self) -> Element<'_, Message> {
+        // Package illustration - dynamic based on check state
+        let (icon_name, status_label) = match &self.check_state {
+            CheckState::Checking => ("view-refresh-symbolic", "Checking...".to_string()),
+            CheckState::Error { .. } => ("dialog-error-symbolic", "Error".to_string()),
+            _ if self.update_info.has_updates() => (
+                "software-update-available-symbolic",
+                format!("{} Updates", self.update_info.total_updates),
+            ),
+            _ => ("package-x-generic", "Up to Date".to_string()),
+        };
+        let icon_name = self.config.icon_theme.icon_name(icon_name);
+
+        cosmic::widget::container(
+            column()
+                .align_x(cosmic::iced::Alignment::Center)
+                .spacing(12)
+                .push(cosmic::widget::icon::from_name(icon_name).size(48))
+                .push(
+                    text(status_label)
+                        .size(11)
+                        .align_x(cosmic::iced::Alignment::Center),
+                ),
+        )
+        .width(cosmic::iced::Length::Fixed(ILLUSTRATION_WIDTH))
+        .height(cosmic::iced::Length::Fixed(ILLUSTRATION_HEIGHT))
+        .align_x(cosmic::iced::alignment::Horizontal::Center)
+        .align_y(cosmic::iced::alignment::Vertical::Center)
+        .style(|_theme| cosmic::widget::container::Style {
+            background: None,
+            ..Default::default()
+        })
+        .padding(12)
+        .into()
+    }
+
+    /// Whether the list is split in two groups: official and AUR updates,
+    /// or Flatpak apps and runtimes
+    fn splits_package_list(&self) -> bool {
+        self.config
+            .package_manager
+            .is_some_and(|pm| pm.supports_aur() || pm == PackageManager::Flatpak)
+    }
+
+    /// Height of the scrollable package list, growing with the number of rows
+    fn package_list_height(&self) -> f32 {
+        // Grouped lists add an "Official:"/"AUR:" (or "Apps:"/"Runtimes:")
+        // header row per group
+        let header_rows = if self.splits_package_list() { 2 } else { 0 };
+        let shown = self.shown_packages().count();
+        let rendered = shown.min(self.display_limit());
+        // The "Show more" button takes about two rows
+        let button_rows = if rendered < shown { 2 } else { 0 };
+        let rows = rendered + header_rows + button_rows;
+
+        (rows as f32 * PACKAGE_ROW_HEIGHT).clamp(PACKAGE_LIST_HEIGHT, PACKAGE_LIST_MAX_HEIGHT)
+    }
+
+    /// Size limits for the popup, honouring the compact layout setting.
+    ///
+    /// With no updates the popup shrinks to the status area; with updates the
+    /// minimum height grows with the package list so short lists stay short and
+    /// long lists get more room. Both heights are capped to the smallest known
+    /// output, as the popup may open on any of them; `POPUP_HEIGHT_LIMIT` only
+    /// guards against runaway sizes before outputs are known.
+    fn popup_limits(&self) -> Limits {
+        let (min_width, base_min_height) = if self.config.compact_layout {
+            (COMPACT_POPUP_MIN_WIDTH, COMPACT_POPUP_MIN_HEIGHT)
+        } else {
+            (POPUP_MIN_WIDTH, POPUP_MIN_HEIGHT)
+        };
+
+        let min_height = if self.update_info.has_updates() {
+            base_min_height
+                .max(POPUP_LIST_CHROME_HEIGHT + self.package_list_height())
+                .min(POPUP_HEIGHT_LIMIT)
+        } else {
+            base_min_height.min(POPUP_EMPTY_MIN_HEIGHT)
+        };
+        let max_height = POPUP_MAX_HEIGHT.max(min_height);
+        let (min_height, max_height) = match self.output_height_limit() {
+            Some(limit) => (min_height.min(limit), max_height.min(limit)),
+            None => (min_height, max_height),
+        };
+
+        Limits::NONE
+            .min_height(min_height)
+            .max_height(max_height)
+            .min_width(min_width)
+            .max_width(POPUP_MAX_WIDTH)
+    }
+
+    /// Tallest popup that fits the smallest known output
+    fn output_height_limit(&self) -> Option<f32> {
+        self.output_heights
+            .iter()
+            .map(|(_, height)| (height - POPUP_OUTPUT_MARGIN).max(POPUP_EMPTY_MIN_HEIGHT))
+            .reduce(f32::min)
+    }
+
+    /// Drop an error that is older than one check interval.
+    ///
+    /// The condition behind an error (network, lock contention) is usually
+    /// transient, so without a newer error the UI returns to its last known
+    /// good state instead of showing a stale message indefinitely.
+    fn clear_stale_error(&mut self) {
+        if let CheckState::Error { at, since, .. } = self.check_state {
+            let interval = Duration::from_secs(self.config.check_interval_minutes as u64 * 60);
+            if since.elapsed() >= interval {
+                self.check_state = match at {
+                    Some(at) => CheckState::Completed { at },
+                    None => CheckState::Idle,
+                };
+            }
+        }
+    }
+
+    /// Notify about the result of a terminal update, if notifications are
+    /// enabled and not snoozed
+    fn notify_update_applied(&self, before: usize) -> Task<Message> {
+        if !self.config.show_notifications || self.is_snoozed() {
+            return Task::none();
+        }
+
+        let Some((summary, body)) =
+            crate::notifications::post_update_message(before, self.update_info.total_updates)
+        else {
+            return Task::none();
+        };
+        let urgency = self.config.notification_urgency;
+
+        Task::perform(
+            async move {
+                if let Err(e) = crate::notifications::send(&summary, &body, urgency).await {
+                    crate::check_log::warn(format!("Warning: {}", e));
+                }
+            },
+            |()| cosmic::Action::App(Message::NotificationSent),
+        )
+    }
+
+    /// Announce available updates with an "Update now" action, if
+    /// notifications are enabled and not snoozed
+    fn notify_updates_available(&self, appeared: usize) -> Task<Message> {
+        if !self.config.show_notifications || self.is_snoozed() {
+            return Task::none();
+        }
+
+        let (summary, body) = crate::notifications::updates_available_message(
+            self.update_info.total_updates,
+            appeared,
+        );
+        let urgency = crate::notifications::updates_available_urgency(
+            self.config.notification_urgency,
+            self.update_info.security_updates,
+            self.update_info.total_updates,
+            self.config.badge_warning_threshold as usize,
+        );
+        Task::perform(
+            async move {
+                crate::notifications::send_with_action(
+                    &summary,
+                    &body,
+                    crate::notifications::ACTION_UPDATE,
+                    "Update now",
+                    urgency,
+                )
+                .await
+                .unwrap_or_else(|e| {
+                    crate::check_log::warn(format!("Warning: {}", e));
+                    false
+                })
+            },
+            |invoked| cosmic::Action::App(Message::NotificationActionFinished(invoked)),
+        )
+    }
+
+    /// User-facing description of a failed check
+    fn check_error_message(error: &CheckError) -> String {
+        match error {
+            CheckError::LockBusy => {
+                "Another instance is checking for updates. Try again in a moment.".to_string()
+            }
+            CheckError::CommandNotFound(cmd) => format!("{} is not installed or not in PATH", cmd),
+            CheckError::PermissionDenied(details) => format!("Permission denied. {}", details),
+            CheckError::Timeout => "The package manager did not respond in time.".to_string(),
+            CheckError::ParseError(details) => {
+                format!("Unexpected package manager output: {}", details)
+            }
+            // Handle specific Wayland errors that might occur after system updates
+            CheckError::Other(message)
+                if message.contains("Protocol error") || message.contains("wl_surface") =>
+            {
+                "Display system updated. Please restart the applet if issues persist.".to_string()
+            }
+            CheckError::Other(message) => message.clone(),
+        }
+    }
+
+    /// Record which incoming updates were not present in the previous result,
+    /// returning how many appeared with this result (none for the first)
+    fn track_new_packages(&mut self, incoming: &UpdateInfo) -> usize {
+        let key =
+            |p: &crate::package_manager::PackageUpdate| (p.name.clone(), p.new_version.clone());
+        let incoming_keys: HashSet<_> = incoming.packages.iter().map(key).collect();
+
+        // Updates that were installed meanwhile are no longer new
+        self.new_packages.retain(|k| incoming_keys.contains(k));
+
+        // The first result is compared with the previous session's, once
+        // `save_pending_versions` has read it
+        self.previous_packages = self
+            .has_checked
+            .then(|| crate::change_summary::pending_versions(&self.update_info.packages));
+
+        let mut appeared = 0;
+        if self.has_checked {
+            let previous: HashSet<_> = self.update_info.packages.iter().map(key).collect();
+            let added: Vec<_> = incoming_keys.difference(&previous).cloned().collect();
+            appeared = added.len();
+            self.new_packages.extend(added);
+        }
+        self.has_checked = true;
+        appeared
+    }
+
+    /// Save the current result for the next session off the UI thread,
+    /// reading the previous session's first when `load_previous`
+    fn save_pending_versions(&self, load_previous: bool) -> Task<Message> {
+        let pending = crate::change_summary::pending_versions(&self.update_info.packages);
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    let path = crate::paths::previous_updates_path();
+                    let previous = if load_previous {
+                        crate::change_summary::load(&path)
+                    } else {
+                        None
+                    };
+                    if let Err(e) = crate::change_summary::save(&path, &pending) {
+                        crate::check_log::warn(format!("Failed to save the update list: {:#}", e));
+                    }
+                    previous
+                })
+                .await
+                .unwrap_or_default()
+            },
+            |previous| cosmic::Action::App(Message::PreviousUpdatesLoaded(previous)),
+        )
+    }
+
+    /// "3 new, 1 resolved since last check", comparing with the previous
+    /// result; `None` without one or without changes
+    fn change_summary(&self) -> Option<String> {
+        let previous = self.previous_packages.as_ref()?;
+        let current = crate::change_summary::pending_versions(&self.update_info.packages);
+        crate::change_summary::summarize(previous, &current)
+    }
+
+    /// Manager-specific check options derived from the current config
+    fn check_options(&self) -> CheckOptions {
+        CheckOptions {
+            refresh_apk_index: self.config.apk_refresh_index,
+            refresh_zypper_repos: self.config.zypper_refresh_repos,
+            aur_single_query: self.config.aur_single_query,
+            apt_dist_upgrade_simulation: self.config.apt_use_dist_upgrade_simulation,
+            apt_origins: self.config.apt_origins.clone(),
+            lock_timeout: Duration::from_secs(self.config.lock_timeout_secs as u64),
+            count_foreign_packages: self.config.show_foreign_package_count,
+            aur_rebuild_hints: self.config.aur_rebuild_hints,
+            download_sizes: self.config.show_download_size,
+            sudo_askpass: self.config.sudo_askpass,
+            aur_backend: self.config.aur_check_backend,
+            custom_check_command: self
+                .config
+                .package_manager
+                .and_then(|pm| self.config.custom_check_commands.get(&pm).cloned()),
+            command_timeout: Some(Duration::from_secs(CHECK_COMMAND_TIMEOUT_SECS)),
+        }
+    }
+
+    fn get_sync_path() -> PathBuf {
         crate::paths::sync_path()
     }
 
@@ -602,74 +1853,335 @@ impl CosmicAppletPackageUpdater {
         async_stream::stream! {
             let sync_path = Self::get_sync_path();
 
-            // Ensure the parent directory exists
-            if let Some(parent) = sync_path.parent() {
-                let _ = std::fs::create_dir_all(parent);
-            }
+            // Set up again after a failure, e.g. once a missing runtime
+            // directory is back or after the sync file was deleted
+            loop {
+                // Ensure the parent directory exists
+                if let Some(parent) = sync_path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+
+                // Create the sync file if it doesn't exist
+                if !sync_path.exists() {
+                    let _ = std::fs::File::create(&sync_path);
+                }
+
+                // `true` for a change, `false` once the watch stopped working
+                let (tx, mut rx) = mpsc::unbounded();
+
+                let watcher = notify::recommended_watcher(move |res: Result<Event, _>| match res {
+                    Ok(event) if event.kind.is_remove() => {
+                        let _ = tx.unbounded_send(false);
+                    }
+                    Ok(event) if event.kind.is_modify() || event.kind.is_create() => {
+                        let _ = tx.unbounded_send(true);
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        let _ = tx.unbounded_send(false);
+                    }
+                })
+                .map_err(|e| format!("Failed to create file watcher: {}", e))
+                .and_then(|mut watcher| {
+                    watcher
+                        .watch(&sync_path, RecursiveMode::NonRecursive)
+                        .map(|()| watcher)
+                        .map_err(|e| format!("Failed to watch sync file: {}", e))
+                });
+
+                match watcher {
+                    // Kept alive while events are read
+                    Ok(_watcher) => {
+                        yield Message::SyncWatcherStatus(true);
+                        while let Some(true) = rx.next().await {
+                            // Small delay to avoid rapid fire events
+                            tokio::time::sleep(tokio::time::Duration::from_millis(FILE_WATCHER_DEBOUNCE_MS)).await;
+                            yield Message::SyncFileChanged;
+                        }
+                        crate::check_log::warn("Sync file watch stopped, setting it up again");
+                    }
+                    Err(e) => crate::check_log::warn(e),
+                }
 
-            // Create the sync file if it doesn't exist
-            if !sync_path.exists() {
-                let _ = std::fs::File::create(&sync_path);
+                yield Message::SyncWatcherStatus(false);
+                tokio::time::sleep(tokio::time::Duration::from_secs(SYNC_WATCHER_RETRY_SECS)).await;
             }
+        }
+    }
+
+    /// Watch a package database and emit once changes have settled.
+    ///
+    /// Files are replaced by rename (dpkg writes `status-new`), so a file is
+    /// watched through its parent directory. An update touches the database
+    /// many times; the event fires after `PACKAGE_DB_SETTLE_SECS` of quiet.
+    fn watch_package_database(path: PathBuf) -> impl futures::Stream<Item = Message> {
+        use futures::channel::mpsc;
+        use futures::StreamExt;
+        use notify::{Event, RecursiveMode, Watcher};
+
+        async_stream::stream! {
+            let (watch_path, file_name) = if path.is_dir() {
+                (path.clone(), None)
+            } else {
+                match path.parent() {
+                    Some(parent) => (parent.to_path_buf(), path.file_name().map(|n| n.to_os_string())),
+                    None => return,
+                }
+            };
 
             let (tx, mut rx) = mpsc::unbounded();
 
             let mut watcher = match notify::recommended_watcher(move |res: Result<Event, _>| {
                 if let Ok(event) = res {
-                    if event.kind.is_modify() || event.kind.is_create() {
+                    let relevant = file_name.as_ref().map_or(true, |name| {
+                        event.paths.iter().any(|p| p.file_name() == Some(name.as_os_str()))
+                    });
+                    if relevant
+                        && (event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove())
+                    {
                         let _ = tx.unbounded_send(());
                     }
                 }
             }) {
                 Ok(w) => w,
                 Err(e) => {
-                    eprintln!("Failed to create file watcher: {}", e);
+                    crate::check_log::warn(format!("Failed to create package database watcher: {}", e));
                     return;
                 }
             };
 
-            if let Err(e) = watcher.watch(&sync_path, RecursiveMode::NonRecursive) {
-                eprintln!("Failed to watch sync file: {}", e);
+            if let Err(e) = watcher.watch(&watch_path, RecursiveMode::NonRecursive) {
+                crate::check_log::warn(format!("Failed to watch {}: {}", watch_path.display(), e));
                 return;
             }
 
-            while (rx.next().await).is_some() {
-                // Small delay to avoid rapid fire events
-                tokio::time::sleep(tokio::time::Duration::from_millis(FILE_WATCHER_DEBOUNCE_MS)).await;
-                yield Message::SyncFileChanged;
+            let settle = Duration::from_secs(PACKAGE_DB_SETTLE_SECS);
+            while rx.next().await.is_some() {
+                // Wait until the database has been quiet for a while
+                loop {
+                    match tokio::time::timeout(settle, rx.next()).await {
+                        Ok(Some(())) => continue,
+                        Ok(None) => return,
+                        Err(_) => break,
+                    }
+                }
+                yield Message::PackageDatabaseChanged;
             }
         }
     }
 
+    /// Attach the right-click menu, the middle-click quick update and the
+    /// status tooltip to a panel button
+    fn panel_mouse_area<'a>(&self, button: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+        let area = cosmic::widget::mouse_area(button).on_right_press(Message::ToggleContextMenu);
+
+        let middle_click = self.config.enable_middle_click_update && self.update_info.has_updates();
+        let area: Element<'a, Message> = if middle_click {
+            area.on_middle_press(Message::LaunchTerminalUpdate).into()
+        } else {
+            area.into()
+        };
+
+        cosmic::widget::tooltip(
+            area,
+            text(self.panel_tooltip_text()),
+            cosmic::widget::tooltip::Position::Bottom,
+        )
+        .into()
+    }
+
+    /// Summary of the current status shown when hovering the panel button
+    fn panel_tooltip_text(&self) -> String {
+        let status = match &self.check_state {
+            CheckState::Checking => return "Checking for updates...".to_string(),
+            CheckState::Error { message, .. } => format!("Update check failed: {}", message),
+            _ if self.update_info.has_updates() => {
+                let count = self.update_info.total_updates;
+                let plural = if count == 1 { "" } else { "s" };
+                if self.update_info.aur_updates > 0 {
+                    format!(
+                        "{} update{} available ({} AUR)",
+                        count, plural, self.update_info.aur_updates
+                    )
+                } else {
+                    format!("{} update{} available", count, plural)
+                }
+            }
+            CheckState::Completed { .. } => "Up to date".to_string(),
+            CheckState::Idle => return "Package Updater".to_string(),
+        };
+
+        match self.overall_last_check() {
+            Some(at) => format!("{}\n{}", status, Self::format_last_check_time(at)),
+            None => status,
+        }
+    }
+
+    /// Build the right-click menu of the panel button
+    fn view_context_menu(&self) -> Element<'_, Message> {
+        let checking = matches!(self.check_state, CheckState::Checking);
+
+        let content = column()
+            .padding([8, 0])
+            .push(
+                cosmic::applet::menu_button(text("Check for updates"))
+                    .on_press_maybe((!checking).then_some(Message::MenuCheckForUpdates)),
+            )
+            .push(cosmic::applet::menu_button(text("Open settings")).on_press(Message::OpenSettings))
+            .push(divider::horizontal::default());
+        let content = if self.is_snoozed() {
+            content.push(
+                cosmic::applet::menu_button(text("Resume notifications"))
+                    .on_press(Message::Snooze(Snooze::Resume)),
+            )
+        } else {
+            content
+                .push(
+                    cosmic::applet::menu_button(text("Snooze 4 hours"))
+                        .on_press(Message::Snooze(Snooze::Hours(4))),
+                )
+                .push(
+                    cosmic::applet::menu_button(text("Snooze 8 hours"))
+                        .on_press(Message::Snooze(Snooze::Hours(8))),
+                )
+                .push(
+                    cosmic::applet::menu_button(text("Snooze until tomorrow"))
+                        .on_press(Message::Snooze(Snooze::Tomorrow)),
+                )
+        };
+
+        self.core.applet.popup_container(content).into()
+    }
+
+    fn close_context_menu(&mut self) -> Task<Message> {
+        match self.context_menu.take() {
+            Some(id) => destroy_popup(id),
+            None => Task::none(),
+        }
+    }
+
+    fn handle_toggle_context_menu(&mut self) -> Task<Message> {
+        if let Some(id) = self.context_menu.take() {
+            return destroy_popup(id);
+        }
+
+        // Only one popup is shown at a time
+        let mut tasks = vec![];
+        if let Some(p) = self.popup.take() {
+            tasks.push(destroy_popup(p));
+        }
+
+        if let Some(main_window_id) = self.core.main_window_id() {
+            let new_id = Id::unique();
+            self.context_menu = Some(new_id);
+            let mut popup_settings =
+                self.core
+                    .applet
+                    .get_popup_settings(main_window_id, new_id, None, None, None);
+            popup_settings.positioner.size_limits = Limits::NONE
+                .min_width(1.0)
+                .min_height(1.0)
+                .max_width(CONTEXT_MENU_WIDTH);
+            tasks.push(get_popup(popup_settings));
+        } else {
+            crate::check_log::warn("Failed to get main window ID for context menu");
+        }
+
+        Task::batch(tasks)
+    }
+
     fn handle_toggle_popup(&mut self) -> Task<Message> {
         if let Some(p) = self.popup.take() {
             destroy_popup(p)
         } else {
             // Add error handling for popup creation
             if let Some(main_window_id) = self.core.main_window_id() {
+                let close_menu = self.close_context_menu();
                 let new_id = Id::unique();
                 self.popup.replace(new_id);
                 let mut popup_settings =
                     self.core
                         .applet
                         .get_popup_settings(main_window_id, new_id, None, None, None);
-                popup_settings.positioner.size_limits = Limits::NONE
-                    .max_width(POPUP_MAX_WIDTH)
-                    .min_width(POPUP_MIN_WIDTH)
-                    .min_height(POPUP_MIN_HEIGHT)
-                    .max_height(POPUP_MAX_HEIGHT);
+                popup_settings.positioner.size_limits = self.popup_limits();
 
-                Task::batch(vec![get_popup(popup_settings), window::gain_focus(new_id)])
+                Task::batch(vec![
+                    close_menu,
+                    get_popup(popup_settings),
+                    window::gain_focus(new_id),
+                ])
             } else {
-                eprintln!("Failed to get main window ID for popup");
-                let at = match &self.check_state {
-                    CheckState::Completed { at } => Some(*at),
-                    CheckState::Error { at, .. } => *at,
-                    _ => None,
-                };
+                crate::check_log::warn("Failed to get main window ID for popup");
                 self.check_state = CheckState::Error {
                     message: "Unable to open popup window".to_string(),
-                    at,
+                    at: self.check_state.last_check_at(),
+                    since: Instant::now(),
+                };
+                Task::none()
+            }
+        }
+    }
+
+    /// Apply the result of a finished check
+    fn handle_updates_checked(&mut self, result: Result<UpdateInfo, CheckError>) -> Task<Message> {
+        self.cancel_check = None;
+        self.live_packages.clear();
+        let started = self.check_started.take();
+        let state_before = self.state_before_check.take();
+        let mirrored = std::mem::take(&mut self.sync_triggered_check);
+        match result {
+            Ok(mut update_info) => {
+                if let Some(started) = started {
+                    self.record_check_duration(started.elapsed());
+                }
+                // An undo pending from before the check puts back
+                // this result's updates, not the replaced ones
+                if let Some(undo) = &mut self.ignore_undo {
+                    if self.config.ignored_packages.contains(&undo.name) {
+                        undo.removed =
+                            update_info.remove_packages(std::slice::from_ref(&undo.name));
+                    }
+                    undo.live_removed.clear();
+                }
+                update_info.remove_packages(&self.config.ignored_packages);
+                // A queued check after an install compares its own result
+                let count_before_update = if self.check_requested {
+                    None
+                } else {
+                    self.pre_update_count.take()
+                };
+                let first_result = !self.has_checked;
+                let appeared = self.track_new_packages(&update_info);
+                self.update_info = update_info;
+                self.check_state = CheckState::Completed { at: Instant::now() };
+                let saved = self.save_pending_versions(first_result);
+
+                let announce = if first_result {
+                    self.update_info.has_updates()
+                } else {
+                    appeared > 0
+                };
+                let notify = if let Some(count) = count_before_update {
+                    self.notify_update_applied(count)
+                } else if announce && !mirrored {
+                    self.notify_updates_available(appeared)
+                } else {
+                    Task::none()
+                };
+                Task::batch([saved, notify])
+            }
+            Err(error) => {
+                if !self.check_requested {
+                    self.pre_update_count = None;
+                }
+                let last_check = state_before
+                    .as_ref()
+                    .unwrap_or(&self.check_state)
+                    .last_check_at();
+                self.check_state = CheckState::Error {
+                    message: Self::check_error_message(&error),
+                    at: last_check,
+                    since: Instant::now(),
                 };
                 Task::none()
             }
@@ -677,37 +2189,292 @@ impl CosmicAppletPackageUpdater {
     }
 
     fn handle_popup_closed(&mut self, id: Id) -> Task<Message> {
+        // The active tab is kept so reopening the popup returns to it
         if self.popup.as_ref() == Some(&id) {
             self.popup = None;
-            self.active_tab = PopupTab::Updates;
+            self.new_packages.clear();
+            self.show_all_packages = false;
+            self.export_status = None;
+        }
+        if self.context_menu == Some(id) {
+            self.context_menu = None;
         }
         Task::none()
     }
 
     fn handle_switch_tab(&mut self, tab: PopupTab) -> Task<Message> {
         self.active_tab = tab;
+
+        // Refresh the package manager list so newly installed ones show up
+        let discovery_due = self
+            .last_discovery
+            .map_or(true, |at| at.elapsed().as_secs() >= DISCOVERY_DEBOUNCE_SECS);
+        if tab == PopupTab::Settings && discovery_due {
+            return Task::done(cosmic::Action::App(Message::DiscoverPackageManagers));
+        }
+
         Task::none()
     }
 
+    /// Package manager whose updates `install_without_terminal` installs in
+    /// the background: it needs a non-interactive update command, and hooks
+    /// keep using the terminal
+    fn background_install_manager(&self) -> Option<PackageManager> {
+        let has_hooks = [&self.config.pre_update_hook, &self.config.post_update_hook]
+            .into_iter()
+            .any(|hook| hook.as_deref().is_some_and(|hook| !hook.trim().is_empty()));
+        self.config.package_manager.filter(|pm| {
+            self.config.install_without_terminal
+                && !has_hooks
+                && pm.noninteractive_update_command().is_some()
+        })
+    }
+
+    /// Open the terminal running the system update wrapped in the hooks,
+    /// and check again once it is done
+    fn launch_terminal_update(&mut self) -> Task<Message> {
+        let Some(pm) = self.config.package_manager else {
+            return Task::none();
+        };
+        let terminal = if self.config.preferred_terminal.trim().is_empty() {
+            crate::terminal::default_terminal()
+        } else {
+            self.config.preferred_terminal.clone()
+        };
+        let nixos_config = self.config.nixos_config.clone();
+        let command = wrap_with_hooks(
+            &pm.system_update_command(Some(&nixos_config))
+                .replace("\"", "\\\""),
+            self.config.pre_update_hook.as_deref(),
+            self.config.post_update_hook.as_deref(),
+        );
+
+        // A terminal that returns right away is tracked with a marker file
+        // its shell removes when done; any other terminal is awaited until
+        // its window closes
+        let marker_file = crate::terminal::daemonizes(&terminal).then(|| {
+            crate::paths::runtime_dir()
+                .join(format!(
+                    "cosmic-package-updater-terminal-{}.marker",
+                    std::process::id()
+                ))
+                .to_string_lossy()
+                .into_owned()
+        });
+
+        let mut wrapped_command = format!(
+            "{} && echo 'Update completed. Press Enter to exit...' && read",
+            command
+        );
+        if let Some(marker_file) = &marker_file {
+            // Use shell-escape for proper escaping
+            let escaped_marker = shell_escape::escape(marker_file.clone().into());
+            wrapped_command = format!("{}; rm -f {}", wrapped_command, escaped_marker);
+        }
+
+        // The command line exactly as spawned below
+        self.last_update_command = Some(format!(
+            "{} -e sh -c {}",
+            shell_escape::escape(terminal.as_str().into()),
+            shell_escape::escape(wrapped_command.as_str().into())
+        ));
+
+        Task::perform(
+            async move {
+                if let Some(marker_file) = &marker_file {
+                    if let Err(e) = std::fs::File::create(marker_file) {
+                        crate::check_log::warn(format!(
+                            "Warning: Failed to create marker file: {}",
+                            e
+                        ));
+                    }
+                }
+
+                match tokio::process::Command::new(&terminal)
+                    .arg("-e")
+                    .arg("sh")
+                    .arg("-c")
+                    .arg(&wrapped_command)
+                    .spawn()
+                {
+                    Ok(mut child) => {
+                        match &marker_file {
+                            // Poll for marker file deletion (update script done)
+                            Some(marker_file) => {
+                                while std::path::Path::new(marker_file).exists() {
+                                    tokio::time::sleep(tokio::time::Duration::from_millis(
+                                        MARKER_FILE_POLL_INTERVAL_MS,
+                                    ))
+                                    .await;
+                                }
+                            }
+                            None => {
+                                if let Err(e) = child.wait().await {
+                                    crate::check_log::warn(format!(
+                                        "Failed to wait for terminal: {}",
+                                        e
+                                    ));
+                                }
+                            }
+                        }
+
+                        // Add a delay to allow system to stabilize after update
+                        tokio::time::sleep(tokio::time::Duration::from_secs(
+                            POST_UPDATE_STABILIZATION_SECS,
+                        ))
+                        .await;
+                    }
+                    Err(e) => {
+                        crate::check_log::warn(format!("Failed to spawn terminal: {}", e));
+                        // Clean up marker file on error
+                        if let Some(marker_file) = &marker_file {
+                            if let Err(e) = std::fs::remove_file(marker_file) {
+                                crate::check_log::warn(format!(
+                                    "Warning: Failed to remove marker file: {}",
+                                    e
+                                ));
+                            }
+                        }
+                    }
+                }
+            },
+            |()| cosmic::Action::App(Message::TerminalFinished),
+        )
+    }
+
+    /// Run `custom_update_launcher` with `sh -c` and wait for it to exit
+    fn run_update_launcher(launcher: String) -> Task<Message> {
+        Task::perform(
+            async move {
+                match tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(&launcher)
+                    .status()
+                    .await
+                {
+                    Ok(status) => {
+                        if !status.success() {
+                            crate::check_log::warn(format!(
+                                "Update launcher exited with {}",
+                                status
+                            ));
+                        }
+                        // Add a delay to allow system to stabilize after update
+                        tokio::time::sleep(tokio::time::Duration::from_secs(
+                            POST_UPDATE_STABILIZATION_SECS,
+                        ))
+                        .await;
+                    }
+                    Err(e) => {
+                        crate::check_log::warn(format!("Failed to run update launcher: {}", e))
+                    }
+                }
+            },
+            |()| cosmic::Action::App(Message::TerminalFinished),
+        )
+    }
+
+    /// Automatic checks are paused by `check_only_on_ac`
+    fn on_battery(&self) -> bool {
+        self.config.check_only_on_ac && !crate::power::on_ac_power()
+    }
+
+    /// Whether a snooze is active; checks keep running, only notifications
+    /// and the panel highlight are muted
+    fn is_snoozed(&self) -> bool {
+        self.config
+            .snooze_until
+            .is_some_and(|until| until > crate::check_log::unix_timestamp())
+    }
+
+    /// End of a snooze started now, `None` to resume
+    fn snooze_until(snooze: Snooze) -> Option<u64> {
+        match snooze {
+            Snooze::Hours(hours) => Some(crate::check_log::unix_timestamp() + hours * 3600),
+            Snooze::Tomorrow => {
+                let tomorrow = chrono::Local::now().date_naive() + chrono::Days::new(1);
+                tomorrow
+                    .and_hms_opt(SNOOZE_TOMORROW_HOUR, 0, 0)
+                    .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+                    .map(|t| t.timestamp().max(0) as u64)
+                    // A time zone without that local time: a day from now
+                    .or(Some(crate::check_log::unix_timestamp() + 24 * 3600))
+            }
+            Snooze::Resume => None,
+        }
+    }
+
+    /// "Snoozed until Tue 08:00", while a snooze is active
+    fn snooze_status(&self) -> Option<String> {
+        use chrono::TimeZone;
+
+        let until = self.config.snooze_until.filter(|_| self.is_snoozed())?;
+        let local = chrono::Local.timestamp_opt(until as i64, 0).earliest()?;
+        Some(format!("Snoozed until {}", local.format("%a %H:%M")))
+    }
+
+    /// Panel icon, shared by the count and the plain icon layouts so both
+    /// tell the states apart the same way: animated refresh while checking,
+    /// error after a failed check, muted while snoozed, then updates
+    /// available or up to date
     fn get_icon_name(&self) -> &'static str {
-        match &self.check_state {
-            CheckState::Checking => "view-refresh-symbolic",
+        let symbolic = match &self.check_state {
+            CheckState::Checking => {
+                CHECKING_ICON_FRAMES[self.animation_frame % CHECKING_ICON_FRAMES.len()]
+            }
             CheckState::Error { .. } => "dialog-error-symbolic",
+            _ if self.is_snoozed() => "notifications-disabled-symbolic",
             _ if self.update_info.has_updates() => "software-update-available-symbolic",
             _ => "package-x-generic-symbolic",
-        }
+        };
+        self.config.icon_theme.icon_name(symbolic)
     }
 
     fn view_updates_tab(&self) -> Element<'_, Message> {
         let mut widgets = vec![];
 
+        if let Some(IgnoreUndo { name, .. }) = &self.ignore_undo {
+            widgets.push(
+                row()
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+                    .push(
+                        text(format!("Ignored {}", name))
+                            .size(12)
+                            .width(cosmic::iced::Length::Fill),
+                    )
+                    .push(button::text("Undo").on_press(Message::UndoIgnorePackage))
+                    .into(),
+            );
+        }
+
+        if !matches!(self.check_state, CheckState::Checking) {
+            if let Some(summary) = self.change_summary() {
+                widgets.push(text(summary).size(12).into());
+            }
+        }
+
         widgets.extend(self.build_status_section());
         widgets.extend(self.build_action_buttons());
 
-        if self.update_info.has_updates() {
+        if matches!(self.check_state, CheckState::Checking) {
+            if !self.live_packages.is_empty() {
+                widgets.extend(self.build_live_package_list());
+            }
+        } else if self.update_info.has_updates() {
             widgets.extend(self.build_package_list());
         }
 
+        if !matches!(self.check_state, CheckState::Checking)
+            && self.update_info.packages.iter().any(|p| p.phased)
+        {
+            widgets.extend(self.build_phased_list());
+        }
+
+        if !self.update_info.held_back.is_empty() {
+            widgets.extend(self.build_held_back_list());
+        }
+
         column().spacing(8).extend(widgets).into()
     }
 
@@ -733,6 +2500,16 @@ impl CosmicAppletPackageUpdater {
                     .into(),
                 );
 
+                if self.update_info.security_updates > 0 {
+                    widgets.push(
+                        text(format!(
+                            "Security updates: {}",
+                            self.update_info.security_updates
+                        ))
+                        .into(),
+                    );
+                }
+
                 // Only show package breakdown if package manager supports AUR
                 if let Some(pm) = self.config.package_manager {
                     if pm.supports_aur() {
@@ -754,15 +2531,89 @@ impl CosmicAppletPackageUpdater {
             }
         }
 
-        // Last check time
-        let last_check_at = match &self.check_state {
-            CheckState::Completed { at } => Some(*at),
-            CheckState::Error { at, .. } => *at,
-            _ => None,
-        };
-        if let Some(at) = last_check_at {
+        // Pending reboot, outdated running kernel and partial failures, e.g.
+        // unreachable Flatpak remotes
+        if matches!(self.check_state, CheckState::Completed { .. }) {
+            if self.update_info.reboot_required {
+                widgets.push(
+                    text("Reboot required to apply the installed updates")
+                        .size(12)
+                        .into(),
+                );
+            }
+            if let Some(mismatch) = &self.update_info.kernel_mismatch {
+                widgets.push(
+                    text(format!(
+                        "Running kernel is outdated — reboot to apply {}",
+                        mismatch.installed
+                    ))
+                    .size(12)
+                    .into(),
+                );
+            }
+            if let Some(bytes) = self.update_info.total_download_size {
+                widgets.push(
+                    text(format!("Download size: {}", Self::format_size(bytes)))
+                        .size(12)
+                        .into(),
+                );
+            }
+            if let Some(foreign) = self.update_info.foreign_packages {
+                widgets.push(
+                    text(format!("{} foreign packages installed", foreign))
+                        .size(12)
+                        .into(),
+                );
+            }
+            for warning in &self.update_info.warnings {
+                widgets.push(text(format!("Warning: {}", warning)).size(10).into());
+            }
+        }
+
+        // One line per remote host, labeled with its SSH target
+        for check in &self.remote_results {
+            let target = &check.target;
+            let count = match &check.result {
+                Ok(info) => self.remote_shown_count(target, info),
+                Err(_) => 0,
+            };
+            let line = match &check.result {
+                Ok(_) if count > 0 => format!(
+                    "{}: {} updates available ({})",
+                    target,
+                    count,
+                    Self::format_elapsed(check.at)
+                ),
+                Ok(_) => format!("{}: up to date ({})", target, Self::format_elapsed(check.at)),
+                Err(e) => match check.last_success {
+                    Some(success) => format!(
+                        "{}: check failed {}: {} (last succeeded {})",
+                        target,
+                        Self::format_elapsed(check.at),
+                        e,
+                        Self::format_elapsed(success)
+                    ),
+                    None => format!(
+                        "{}: check failed {}: {}",
+                        target,
+                        Self::format_elapsed(check.at),
+                        e
+                    ),
+                },
+            };
+            widgets.push(text(line).size(12).into());
+        }
+
+        // Last check time, the oldest successful one across hosts, and
+        // whether this machine's count reflects freshly refreshed metadata
+        if let Some(at) = self.overall_last_check() {
+            let source = if self.update_info.cached_metadata {
+                "cached metadata"
+            } else {
+                "live"
+            };
             widgets.push(
-                text(Self::format_last_check_time(at))
+                text(format!("{} · {}", Self::format_last_check_time(at), source))
                     .size(12)
                     .into(),
             );
@@ -771,47 +2622,119 @@ impl CosmicAppletPackageUpdater {
         widgets
     }
 
-    /// Format the last check time in a human-readable format
-    fn format_last_check_time(last_check: Instant) -> String {
-        let elapsed = last_check.elapsed();
-        if elapsed.as_secs() < 60 {
-            "Last checked: just now".to_string()
-        } else if elapsed.as_secs() < 3600 {
-            format!("Last checked: {} minutes ago", elapsed.as_secs() / 60)
+    /// Human-readable byte count, e.g. "12.3 MiB"
+    fn format_size(bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+        let mut size = bytes as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} B", bytes)
         } else {
-            format!("Last checked: {} hours ago", elapsed.as_secs() / 3600)
+            format!("{:.1} {}", size, UNITS[unit])
         }
     }
 
+    /// Format the last check time in a human-readable format
+    fn format_last_check_time(last_check: Instant) -> String {
+        format!("Last checked: {}", Self::format_elapsed(last_check))
+    }
+
+    /// "just now", "5 minutes ago", "2 days ago" or the date
+    fn format_elapsed(at: Instant) -> String {
+        crate::time_ago::format_elapsed(at.elapsed(), chrono::Local::now().naive_local())
+    }
+
     /// Build the action buttons section
     fn build_action_buttons(&self) -> Vec<Element<'_, Message>> {
         let mut widgets = vec![];
 
         widgets.push(Space::with_height(cosmic::iced::Length::Fixed(16.0)).into());
 
-        // Check button
-        widgets.push(
-            button::text("Check for Updates")
-                .on_press(Message::CheckForUpdates)
-                .width(cosmic::iced::Length::Fill)
-                .into(),
-        );
-
-        // Update System button right after Check for Updates if updates available
-        if self.update_info.has_updates() {
+        // Check button, replaced by a cancel button while a check is running
+        if matches!(self.check_state, CheckState::Checking) {
             widgets.push(
-                button::text("Update System")
-                    .on_press(Message::LaunchTerminalUpdate)
+                button::text("Cancel")
+                    .on_press(Message::CancelCheck)
                     .width(cosmic::iced::Length::Fill)
                     .into(),
             );
+        } else {
             widgets.push(
-                text("💡 Tip: Middle-click on the Panel icon")
-                    .size(10)
+                button::text("Check for Updates")
+                    .on_press(Message::CheckForUpdates)
+                    .width(cosmic::iced::Length::Fill)
+                    .into(),
+            );
+        }
+
+        // Update System button right after Check for Updates if updates available
+        if self.update_info.has_updates() {
+            let update_button = if self.installing {
+                button::text("Installing updates…")
+            } else {
+                button::text("Update System").on_press(Message::LaunchTerminalUpdate)
+            };
+            widgets.push(update_button.width(cosmic::iced::Length::Fill).into());
+            if self.config.enable_middle_click_update {
+                widgets.push(
+                    text("💡 Tip: Middle-click on the Panel icon")
+                        .size(10)
+                        .into(),
+                );
+            }
+        }
+
+        if matches!(self.check_state, CheckState::Completed { .. }) {
+            widgets.push(
+                button::text("Export…")
+                    .on_press(Message::ExportUpdates)
+                    .width(cosmic::iced::Length::Fill)
                     .into(),
             );
+            if let Some(status) = &self.export_status {
+                widgets.push(text(status.as_str()).size(10).into());
+            }
+        }
+
+        widgets.extend(self.build_last_commands());
+
+        widgets
+    }
+
+    /// Collapsible list of the commands the last check and update ran
+    fn build_last_commands(&self) -> Vec<Element<'_, Message>> {
+        let mut widgets = vec![];
+        if self.check_commands.is_empty() && self.last_update_command.is_none() {
+            return widgets;
+        }
+
+        widgets.push(
+            button::text(if self.show_commands {
+                "Hide last command"
+            } else {
+                "Show last command"
+            })
+            .on_press(Message::ToggleShowCommands)
+            .into(),
+        );
+        if !self.show_commands {
+            return widgets;
         }
 
+        if !self.check_commands.is_empty() {
+            widgets.push(text("Check:").size(10).into());
+            for command in &self.check_commands {
+                widgets.push(text(command.as_str()).size(10).into());
+            }
+        }
+        if let Some(command) = &self.last_update_command {
+            widgets.push(text("Update:").size(10).into());
+            widgets.push(text(command.as_str()).size(10).into());
+        }
         widgets
     }
 
@@ -823,23 +2746,24 @@ impl CosmicAppletPackageUpdater {
         widgets.push(text("Packages to update:").size(14).into());
         widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
 
-        let supports_aur = self
-            .config
-            .package_manager
-            .map(|pm| pm.supports_aur())
-            .unwrap_or(false);
-
-        let package_list = if supports_aur {
+        let mut package_list = if self.splits_package_list() || !self.custom_groups.is_empty() {
             self.build_grouped_package_list()
         } else {
             self.build_simple_package_list()
         };
 
+        let hidden = self.hidden_package_count();
+        if hidden > 0 {
+            package_list = package_list.push(
+                button::text(format!("Show {} more…", hidden)).on_press(Message::ShowAllPackages),
+            );
+        }
+
         widgets.push(
             cosmic::widget::container(
                 scrollable(package_list)
                     .width(cosmic::iced::Length::Fill)
-                    .height(cosmic::iced::Length::Fixed(PACKAGE_LIST_HEIGHT)),
+                    .height(cosmic::iced::Length::Fixed(self.package_list_height())),
             )
             .class(cosmic::theme::Container::List)
             .padding(12)
@@ -850,38 +2774,148 @@ impl CosmicAppletPackageUpdater {
         widgets
     }
 
-    /// Build package list grouped by official and AUR packages
-    fn build_grouped_package_list(&self) -> cosmic::widget::Column<'_, Message> {
+    /// Build the list of updates found so far while a check is running
+    fn build_live_package_list(&self) -> Vec<Element<'_, Message>> {
         let mut package_list = column().spacing(4);
+        let rendered = self.live_packages.len().min(self.display_limit());
+        for package in &self.live_packages[..rendered] {
+            package_list = package_list.push(self.package_row(package));
+        }
+        let height = (rendered as f32 * PACKAGE_ROW_HEIGHT)
+            .clamp(PACKAGE_LIST_HEIGHT, PACKAGE_LIST_MAX_HEIGHT);
 
-        let official_packages: Vec<_> = self
-            .update_info
-            .packages
-            .iter()
-            .filter(|p| !p.is_aur)
-            .collect();
-        let aur_packages: Vec<_> = self
-            .update_info
-            .packages
-            .iter()
-            .filter(|p| p.is_aur)
-            .collect();
+        vec![
+            Space::with_height(cosmic::iced::Length::Fixed(16.0)).into(),
+            text(format!("Found so far: {}", self.live_packages.len()))
+                .size(14)
+                .into(),
+            Space::with_height(cosmic::iced::Length::Fixed(8.0)).into(),
+            cosmic::widget::container(
+                scrollable(package_list)
+                    .width(cosmic::iced::Length::Fill)
+                    .height(cosmic::iced::Length::Fixed(height)),
+            )
+            .class(cosmic::theme::Container::List)
+            .padding(12)
+            .width(cosmic::iced::Length::Fill)
+            .into(),
+        ]
+    }
+
+    /// Build the section of apt updates kept back by `apt upgrade`
+    fn build_held_back_list(&self) -> Vec<Element<'_, Message>> {
+        let mut package_list = column().spacing(4);
+        for package in &self.update_info.held_back {
+            package_list = package_list.push(self.package_row(package));
+        }
 
-        if !official_packages.is_empty() {
-            package_list = package_list.push(text("Official:").size(12));
-            for package in official_packages.iter() {
-                package_list = package_list.push(text(self.format_package_text(package)).size(10));
+        vec![
+            Space::with_height(cosmic::iced::Length::Fixed(16.0)).into(),
+            text("Held back — require dist-upgrade").size(14).into(),
+            Space::with_height(cosmic::iced::Length::Fixed(8.0)).into(),
+            cosmic::widget::container(package_list)
+                .class(cosmic::theme::Container::List)
+                .padding(12)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        ]
+    }
+
+    /// Build the list of updates deferred by Ubuntu's phased rollout
+    fn build_phased_list(&self) -> Vec<Element<'_, Message>> {
+        let mut package_list = column().spacing(4);
+        for package in self.update_info.packages.iter().filter(|p| p.phased) {
+            package_list = package_list.push(self.package_row(package));
+        }
+
+        vec![
+            Space::with_height(cosmic::iced::Length::Fixed(16.0)).into(),
+            text("Phased (may install later)").size(14).into(),
+            Space::with_height(cosmic::iced::Length::Fixed(8.0)).into(),
+            cosmic::widget::container(package_list)
+                .class(cosmic::theme::Container::List)
+                .padding(12)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        ]
+    }
+
+    /// Build package list grouped by official, AUR and custom repository
+    /// packages; Flatpak updates are split into apps and runtimes instead
+    fn build_grouped_package_list(&self) -> cosmic::widget::Column<'_, Message> {
+        let mut package_list = column().spacing(4);
+
+        let flatpak = self.config.package_manager == Some(PackageManager::Flatpak);
+        // Official and AUR packages, or Flatpak apps and runtimes
+        let mut primary_packages = Vec::new();
+        let mut secondary_packages = Vec::new();
+        let mut custom_packages: Vec<Vec<&PackageUpdate>> =
+            vec![Vec::new(); self.custom_groups.names().len()];
+        for package in self.shown_packages() {
+            let secondary = package.is_aur
+                || (flatpak && crate::package_manager::is_flatpak_runtime(&package.name));
+            match self.custom_groups.group_of(&package.name) {
+                Some(group) => custom_packages[group].push(package),
+                None if secondary => secondary_packages.push(package),
+                None => primary_packages.push(package),
             }
         }
 
-        if !aur_packages.is_empty() {
-            if !official_packages.is_empty() {
+        let (primary_header, secondary_header) = if flatpak {
+            ("Apps:", "Runtimes:")
+        } else {
+            ("Official:", "AUR:")
+        };
+        let mut groups = vec![
+            (primary_header.to_string(), primary_packages),
+            (secondary_header.to_string(), secondary_packages),
+        ];
+        if self.config.aur_first && !flatpak {
+            groups.swap(0, 1);
+        }
+        groups.extend(
+            self.custom_groups
+                .names()
+                .iter()
+                .map(|name| format!("{}:", name))
+                .zip(custom_packages),
+        );
+
+        // The display limit is spent in display order, so the second group
+        // is the one cut short
+        let mut remaining = self.display_limit();
+        let mut rendered_group = false;
+        for (header, packages) in &groups {
+            let shown = remaining.min(packages.len());
+            if shown == 0 {
+                continue;
+            }
+            remaining -= shown;
+
+            if rendered_group {
                 package_list =
                     package_list.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)));
             }
-            package_list = package_list.push(text("AUR:").size(12));
-            for package in aur_packages.iter() {
-                package_list = package_list.push(text(self.format_package_text(package)).size(10));
+            package_list = package_list.push(text(header.as_str()).size(12));
+            for package in packages.iter().take(shown) {
+                package_list = package_list.push(self.package_row(package));
+            }
+            rendered_group = true;
+        }
+
+        if !self.update_info.rebuild_hints.is_empty() {
+            package_list = package_list
+                .push(Space::with_height(cosmic::iced::Length::Fixed(8.0)))
+                .push(text("Rebuild recommended:").size(12));
+            for hint in &self.update_info.rebuild_hints {
+                package_list = package_list.push(
+                    text(format!(
+                        "  {} (depends on {})",
+                        hint.package,
+                        hint.changed_dependencies.join(", ")
+                    ))
+                    .size(10),
+                );
             }
         }
 
@@ -891,22 +2925,163 @@ impl CosmicAppletPackageUpdater {
     /// Build simple package list without grouping
     fn build_simple_package_list(&self) -> cosmic::widget::Column<'_, Message> {
         let mut package_list = column().spacing(4);
-        for package in self.update_info.packages.iter() {
-            package_list = package_list.push(text(self.format_package_text(package)).size(10));
+        for package in self.shown_packages().take(self.display_limit()) {
+            package_list = package_list.push(self.package_row(package));
+        }
+        package_list
+    }
+
+    /// Number of rows rendered before the list is cut off
+    fn display_limit(&self) -> usize {
+        match self.config.max_displayed_packages {
+            0 => usize::MAX,
+            _ if self.show_all_packages => usize::MAX,
+            max => max,
+        }
+    }
+
+    /// Number of packages left out of the rendered list
+    fn hidden_package_count(&self) -> usize {
+        self.shown_packages().count().saturating_sub(self.display_limit())
+    }
+
+    /// Build one package list row: name, versions, download size and an
+    /// action slot. Everything but the name has a fixed width so the columns
+    /// line up down the list. Updates that just appeared get a "NEW" marker,
+    /// and for the Arch family the action opens the package page.
+    fn package_row(&self, package: &crate::package_manager::PackageUpdate) -> Element<'_, Message> {
+        let key = (package.name.clone(), package.new_version.clone());
+        let mut name = row()
+            .spacing(6)
+            .align_y(cosmic::iced::Alignment::Center)
+            .width(cosmic::iced::Length::Fill)
+            .push(Self::package_name(package));
+        if self.new_packages.contains(&key) {
+            name = name.push(text("NEW").size(9).class(cosmic::theme::Text::Accent));
+        }
+
+        let versions = if package.current_version != "unknown" {
+            format!("{} → {}", package.current_version, package.new_version)
+        } else {
+            format!("→ {}", package.new_version)
+        };
+        let mut columns = row()
+            .spacing(6)
+            .align_y(cosmic::iced::Alignment::Center)
+            .push(name)
+            .push(
+                text(versions)
+                    .size(10)
+                    .width(cosmic::iced::Length::Fixed(PACKAGE_VERSION_COLUMN_WIDTH)),
+            );
+
+        if self.config.show_download_size {
+            let size = package
+                .download_size
+                .map(Self::format_size)
+                .unwrap_or_default();
+            columns = columns.push(
+                text(size)
+                    .size(10)
+                    .width(cosmic::iced::Length::Fixed(PACKAGE_SIZE_COLUMN_WIDTH)),
+            );
+        }
+
+        let url = self
+            .config
+            .package_manager
+            .and_then(|pm| pm.package_page_url(&package.name, package.is_aur));
+        let action: Element<'_, Message> = match url {
+            Some(url) => {
+                let tip = if package.is_aur {
+                    "Open on the AUR"
+                } else {
+                    "Open on archlinux.org"
+                };
+                cosmic::widget::tooltip(
+                    button::icon(cosmic::widget::icon::from_name("web-browser-symbolic"))
+                        .extra_small()
+                        .on_press(Message::OpenPackagePage(url)),
+                    text(tip).size(10),
+                    cosmic::widget::tooltip::Position::Top,
+                )
+                .into()
+            }
+            None => Space::with_width(cosmic::iced::Length::Fill).into(),
+        };
+
+        let ignore = cosmic::widget::tooltip(
+            button::icon(cosmic::widget::icon::from_name("list-remove-symbolic"))
+                .extra_small()
+                .on_press(Message::IgnorePackage(package.name.clone())),
+            text("Ignore this package").size(10),
+            cosmic::widget::tooltip::Position::Top,
+        );
+
+        columns
+            .push(
+                cosmic::widget::container(action)
+                    .width(cosmic::iced::Length::Fixed(PACKAGE_ACTION_COLUMN_WIDTH)),
+            )
+            .push(
+                cosmic::widget::container(ignore)
+                    .width(cosmic::iced::Length::Fixed(PACKAGE_ACTION_COLUMN_WIDTH)),
+            )
+            .into()
+    }
+
+    /// Name column of a package row, filling the remaining width so the
+    /// versions stay visible; long names are shortened with an ellipsis and
+    /// shown in full on hover.
+    fn package_name<'a>(package: &crate::package_manager::PackageUpdate) -> Element<'a, Message> {
+        let shortened = Self::truncate_name(&package.name, MAX_PACKAGE_NAME_CHARS);
+        let is_shortened = matches!(shortened, std::borrow::Cow::Owned(_));
+        // Flatpak updates name their remote, for setups mixing several
+        let label = match &package.origin {
+            Some(origin) => format!("  {} ({})", shortened, origin),
+            None => format!("  {}", shortened),
+        };
+        let name = text(label).size(10).width(cosmic::iced::Length::Fill);
+        if is_shortened {
+            cosmic::widget::tooltip(
+                name,
+                text(package.name.clone()).size(10),
+                cosmic::widget::tooltip::Position::Top,
+            )
+            .into()
+        } else {
+            name.into()
         }
-        package_list
     }
 
-    /// Format package update text with version information
-    fn format_package_text(&self, package: &crate::package_manager::PackageUpdate) -> String {
-        if package.current_version != "unknown" {
-            format!(
-                "  {} {} → {}",
-                package.name, package.current_version, package.new_version
-            )
-        } else {
-            format!("  {} → {}", package.name, package.new_version)
+    /// Shorten `name` to at most `max_chars` characters, ending in an ellipsis
+    fn truncate_name(name: &str, max_chars: usize) -> std::borrow::Cow<'_, str> {
+        if name.chars().count() <= max_chars {
+            return std::borrow::Cow::Borrowed(name);
         }
+        let mut shortened: String = name.chars().take(max_chars.saturating_sub(1)).collect();
+        shortened.push('…');
+        std::borrow::Cow::Owned(shortened)
+    }
+
+    /// Build a labelled settings toggle with an explanatory tooltip on hover
+    fn settings_toggle<'a>(
+        label: &'a str,
+        description: &'a str,
+        value: bool,
+        on_toggle: impl Fn(bool) -> Message + 'a,
+    ) -> Element<'a, Message> {
+        cosmic::widget::tooltip(
+            row()
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center)
+                .push(text(label))
+                .push(Space::with_width(cosmic::iced::Length::Fill))
+                .push(toggler(value).on_toggle(on_toggle)),
+            text(description).size(12),
+            cosmic::widget::tooltip::Position::Top,
+        )
+        .into()
     }
 
     fn view_settings_tab(&self) -> Element<'_, Message> {
@@ -1019,14 +3194,75 @@ impl CosmicAppletPackageUpdater {
                     .into(),
             );
 
+            widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
+
+            // What the update count covers
+            widgets.push(text("Count as Updates").size(14).into());
+            let selected_count = Some(self.config.nixos_config.update_count);
+            let mut counts = row().spacing(8);
+            for (label, count) in [
+                ("Builds + fetches", NixOSUpdateCount::BuildsAndFetches),
+                ("Builds", NixOSUpdateCount::Builds),
+                ("Fetches", NixOSUpdateCount::Fetches),
+            ] {
+                counts = counts.push(
+                    radio(text(label), count, selected_count, Message::SetNixOSUpdateCount)
+                        .width(cosmic::iced::Length::Fill),
+                );
+            }
+            widgets.push(counts.into());
+            widgets.push(
+                text("Which store paths of the dry-build are listed and counted")
+                    .size(10)
+                    .into(),
+            );
+
+            if self.config.nixos_config.mode == NixOSMode::Channels {
+                widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
+                widgets.push(Self::settings_toggle(
+                    "Ask for the sudo password",
+                    "Without PolicyKit or passwordless sudo, prompt with a graphical askpass helper (sudo -A). Scheduled checks prompt too.",
+                    self.config.sudo_askpass,
+                    Message::ToggleSudoAskpass,
+                ));
+            }
+
             // Auto-detection button
             widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
             widgets.push(
-                button::text("Auto-detect Mode")
-                    .on_press(Message::AutoDetectNixOSMode)
+                row()
+                    .spacing(8)
+                    .push(
+                        button::text("Auto-detect Mode")
+                            .on_press(Message::AutoDetectNixOSMode)
+                            .width(cosmic::iced::Length::Fill),
+                    )
+                    .push(
+                        button::text("Detect config path")
+                            .on_press(Message::DetectNixOSConfigPath)
+                            .width(cosmic::iced::Length::Fill),
+                    )
+                    .into(),
+            );
+
+            widgets.push(Space::with_height(cosmic::iced::Length::Fixed(16.0)).into());
+        }
+
+        // home-manager settings (only show if home-manager is selected)
+        if self.config.package_manager == Some(PackageManager::HomeManager) {
+            widgets.push(text("Home Manager Configuration").size(16).into());
+            let hm_path = self.config.nixos_config.home_manager_path.clone().unwrap_or_default();
+            widgets.push(
+                text_input("~/.config/home-manager (default)", hm_path)
+                    .on_input(Message::SetHomeManagerPath)
                     .width(cosmic::iced::Length::Fill)
                     .into(),
             );
+            widgets.push(
+                text("Directory with your home.nix or home-manager flake. Leave empty for the default.")
+                    .size(10)
+                    .into(),
+            );
 
             widgets.push(Space::with_height(cosmic::iced::Length::Fixed(16.0)).into());
         }
@@ -1042,82 +3278,689 @@ impl CosmicAppletPackageUpdater {
                 .width(cosmic::iced::Length::Fill)
                 .into(),
         );
+        if let Some(warning) = self.check_interval_warning() {
+            widgets.push(
+                text(warning)
+                    .size(10)
+                    .class(cosmic::theme::Text::Custom(|theme| {
+                        cosmic::iced::widget::text::Style {
+                            color: Some(theme.cosmic().warning_color().into()),
+                        }
+                    }))
+                    .into(),
+            );
+        }
 
         widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
 
-        // Toggles
+        // Badge warning threshold
+        widgets.push(text("Badge Warning Threshold").size(14).into());
+        let threshold_value = self.config.badge_warning_threshold.to_string();
         widgets.push(
-            row()
-                .spacing(8)
-                .align_y(cosmic::iced::Alignment::Center)
-                .push(text("Auto-check on startup"))
-                .push(Space::with_width(cosmic::iced::Length::Fill))
-                .push(
-                    toggler(self.config.auto_check_on_startup).on_toggle(Message::ToggleAutoCheck),
-                )
+            text_input("20", threshold_value)
+                .on_input(|s| Message::SetBadgeWarningThreshold(s.parse::<u32>().unwrap_or(0)))
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        );
+        widgets.push(
+            text("The update count turns to the warning color from this many updates (0 disables). Security updates always use the error color.")
+                .size(10)
                 .into(),
         );
 
+        widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
+
+        // Toggles
+        widgets.push(Self::settings_toggle(
+            "Auto-check on startup",
+            "Check for updates shortly after the applet starts.",
+            self.config.auto_check_on_startup,
+            Message::ToggleAutoCheck,
+        ));
+
+        widgets.push(Self::settings_toggle(
+            "Check only on AC power",
+            "Skip automatic checks while running on battery. Checking manually always works.",
+            self.config.check_only_on_ac,
+            Message::ToggleCheckOnlyOnAc,
+        ));
+
+        if self
+            .available_package_managers
+            .iter()
+            .any(|pm| pm.supports_aur())
+        {
+            widgets.push(Self::settings_toggle(
+                "Prefer system package manager",
+                "When detecting the package manager automatically, pick pacman over paru/yay.",
+                self.config.prefer_system_manager,
+                Message::TogglePreferSystemManager,
+            ));
+        }
+
+        if self
+            .available_package_managers
+            .contains(&PackageManager::Nala)
+        {
+            widgets.push(Self::settings_toggle(
+                "Prefer nala",
+                "When detecting the package manager automatically, pick nala over apt. Applies even with \"Prefer system package manager\" on.",
+                self.config.prefer_nala,
+                Message::TogglePreferNala,
+            ));
+        }
+
+        if self
+            .config
+            .package_manager
+            .is_some_and(|pm| pm.database_path().is_some())
+        {
+            widgets.push(Self::settings_toggle(
+                "Watch package database",
+                "Check again shortly after packages are installed or updated outside the applet.",
+                self.config.watch_package_database,
+                Message::ToggleWatchPackageDatabase,
+            ));
+        }
+
+        if matches!(
+            self.config.package_manager,
+            Some(PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay)
+        ) {
+            widgets.push(Self::settings_toggle(
+                "Show download size",
+                "Look up the total download size of repository updates after each check with an extra pacman query.",
+                self.config.show_download_size,
+                Message::ToggleDownloadSize,
+            ));
+        }
+
         // Only show AUR toggle if package manager supports it
         if let Some(pm) = self.config.package_manager {
             if pm.supports_aur() {
+                widgets.push(Self::settings_toggle(
+                    "Include AUR updates",
+                    "Also check AUR packages with the AUR helper, counted separately from official packages.",
+                    self.config.include_aur_updates,
+                    Message::ToggleIncludeAur,
+                ));
+                if self.config.include_aur_updates {
+                    widgets.push(Self::settings_toggle(
+                        "Single helper query",
+                        "Get repo and AUR updates from one helper call instead of checkupdates plus an AUR query. Repo results use the local sync database.",
+                        self.config.aur_single_query,
+                        Message::ToggleAurSingleQuery,
+                    ));
+                    if !self.config.aur_single_query {
+                        widgets.push(text("AUR lookup").size(14).into());
+                        let selected_backend = Some(self.config.aur_check_backend);
+                        let mut backends = row().spacing(8);
+                        for (label, backend) in [
+                            ("AUR helper", AurCheckBackend::Helper),
+                            ("AUR RPC", AurCheckBackend::Rpc),
+                        ] {
+                            backends = backends.push(
+                                radio(
+                                    text(label),
+                                    backend,
+                                    selected_backend,
+                                    Message::SetAurCheckBackend,
+                                )
+                                .width(cosmic::iced::Length::Fill),
+                            );
+                        }
+                        widgets.push(backends.into());
+                        widgets.push(
+                            text("AUR RPC looks up all foreign packages in a few batched requests with curl, which is faster with many AUR packages. Falls back to the helper on failure.")
+                                .size(10)
+                                .into(),
+                        );
+                    }
+                    widgets.push(Self::settings_toggle(
+                        "Count foreign packages",
+                        "Show how many installed packages are not in any repository (AUR or local builds), regardless of updates.",
+                        self.config.show_foreign_package_count,
+                        Message::ToggleForeignPackageCount,
+                    ));
+                    widgets.push(Self::settings_toggle(
+                        "AUR updates first",
+                        "List AUR updates above official ones in the popup.",
+                        self.config.aur_first,
+                        Message::ToggleAurFirst,
+                    ));
+                    widgets.push(Self::settings_toggle(
+                        "AUR rebuild hints",
+                        "Mark installed AUR packages that depend on an official package updated to a new major or minor version as needing a rebuild.",
+                        self.config.aur_rebuild_hints,
+                        Message::ToggleAurRebuildHints,
+                    ));
+                }
+            }
+        }
+
+        if self.config.package_manager == Some(PackageManager::Apt) {
+            widgets.push(Self::settings_toggle(
+                "Simulate dist-upgrade",
+                "Count what 'apt-get dist-upgrade' would install, including new and held-back packages, instead of 'apt list --upgradable'.",
+                self.config.apt_use_dist_upgrade_simulation,
+                Message::ToggleAptDistUpgradeSimulation,
+            ));
+
+            widgets.push(text("Counted apt suites").size(14).into());
+            widgets.push(
+                text_input("All suites", self.apt_origins_input.as_str())
+                    .on_input(Message::SetAptOrigins)
+                    .width(cosmic::iced::Length::Fill)
+                    .into(),
+            );
+            widgets.push(
+                text("Comma-separated, e.g. bookworm, bookworm-security. Leave empty to count all.")
+                    .size(10)
+                    .into(),
+            );
+        }
+
+        widgets.push(text("Ignored packages").size(14).into());
+        widgets.push(
+            text_input("None", self.ignored_packages_input.as_str())
+                .on_input(Message::SetIgnoredPackages)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        );
+        widgets.push(
+            text("Comma-separated package names left out of the list and counts. Use the button on a package row to add one.")
+                .size(10)
+                .into(),
+        );
+
+        widgets.push(text("Custom repositories").size(14).into());
+        widgets.push(
+            text_input("None", self.custom_patterns_input.as_str())
+                .on_input(Message::SetCustomRepoPatterns)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        );
+        widgets.push(
+            text("Semicolon-separated 'Group=regex' patterns matched against package names, e.g. Corp=^corp-. Matches get their own section.")
+                .size(10)
+                .into(),
+        );
+        for error in self.custom_groups.errors() {
+            widgets.push(text(error.as_str()).size(10).into());
+        }
+
+        widgets.push(text("Remote hosts").size(14).into());
+        widgets.push(
+            text_input("None", self.remote_hosts_input.as_str())
+                .on_input(Message::SetRemoteHosts)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        );
+        widgets.push(
+            text("Comma-separated 'target manager' pairs checked over SSH, e.g. admin@nas apt, pi dnf. Needs key-based login.")
+                .size(10)
+                .into(),
+        );
+
+        if matches!(
+            self.config.package_manager,
+            Some(PackageManager::Apt | PackageManager::Dnf)
+        ) {
+            widgets.push(Self::settings_toggle(
+                "Security updates only",
+                "List and count only updates that fix security issues.",
+                self.config.security_only,
+                Message::ToggleSecurityOnly,
+            ));
+        }
+
+        if self.config.package_manager == Some(PackageManager::Apk) {
+            widgets.push(Self::settings_toggle(
+                "Refresh apk index before checking",
+                "Run 'apk update' with administrator rights before each check so results are current.",
+                self.config.apk_refresh_index,
+                Message::ToggleApkRefreshIndex,
+            ));
+        }
+
+        if matches!(
+            self.config.package_manager,
+            Some(PackageManager::Zypper | PackageManager::TransactionalUpdate)
+        ) {
+            widgets.push(Self::settings_toggle(
+                "Refresh repositories before checking",
+                "Run 'zypper refresh' with administrator rights before each check; otherwise updates are listed from cached metadata.",
+                self.config.zypper_refresh_repos,
+                Message::ToggleZypperRefreshRepos,
+            ));
+        }
+
+        widgets.push(Self::settings_toggle(
+            "Show notifications",
+            "Show a desktop notification when new updates are found.",
+            self.config.show_notifications,
+            Message::ToggleShowNotifications,
+        ));
+
+        if self.config.show_notifications {
+            widgets.push(text("Notification Urgency").size(14).into());
+            let selected_urgency = Some(self.config.notification_urgency);
+            let mut urgencies = row().spacing(8);
+            for (label, urgency) in [
+                ("Low", NotificationUrgency::Low),
+                ("Normal", NotificationUrgency::Normal),
+                ("Critical", NotificationUrgency::Critical),
+            ] {
+                urgencies = urgencies.push(
+                    radio(
+                        text(label),
+                        urgency,
+                        selected_urgency,
+                        Message::SetNotificationUrgency,
+                    )
+                    .width(cosmic::iced::Length::Fill),
+                );
+            }
+            widgets.push(urgencies.into());
+            widgets.push(
+                text("Security updates are always critical; from the badge warning threshold updates are at least normal.")
+                    .size(10)
+                    .into(),
+            );
+        }
+
+        widgets.push(text("Snooze").size(14).into());
+        match self.snooze_status() {
+            Some(status) => {
+                widgets.push(text(status).size(12).into());
+                widgets.push(
+                    button::text("Resume")
+                        .on_press(Message::Snooze(Snooze::Resume))
+                        .into(),
+                );
+            }
+            None => {
                 widgets.push(
                     row()
                         .spacing(8)
-                        .align_y(cosmic::iced::Alignment::Center)
-                        .push(text("Include AUR updates"))
-                        .push(Space::with_width(cosmic::iced::Length::Fill))
-                        .push(
-                            toggler(self.config.include_aur_updates)
-                                .on_toggle(Message::ToggleIncludeAur),
-                        )
+                        .push(button::text("4 hours").on_press(Message::Snooze(Snooze::Hours(4))))
+                        .push(button::text("8 hours").on_press(Message::Snooze(Snooze::Hours(8))))
+                        .push(button::text("Tomorrow").on_press(Message::Snooze(Snooze::Tomorrow)))
                         .into(),
                 );
             }
         }
+        widgets.push(
+            text("Mutes notifications and the panel highlight. Checks keep running.")
+                .size(10)
+                .into(),
+        );
+
+        widgets.push(Self::settings_toggle(
+            "Show update count",
+            "Show the number of available updates next to the panel icon.",
+            self.config.show_update_count,
+            Message::ToggleShowUpdateCount,
+        ));
+        if self.config.show_update_count {
+            widgets.push(Self::settings_toggle(
+                "Large count text",
+                "Draw the panel count bigger so it is easier to read.",
+                self.config.large_badge_text,
+                Message::ToggleLargeBadgeText,
+            ));
+        }
+
+        if self.config.show_update_count {
+            widgets.push(text("When Up to Date").size(14).into());
+            let selected_behavior = Some(self.config.zero_badge_behavior);
+            let mut behaviors = row().spacing(8);
+            for (label, behavior) in [
+                ("Hide", ZeroBadgeBehavior::Hidden),
+                ("Check mark", ZeroBadgeBehavior::Checkmark),
+                ("Show 0", ZeroBadgeBehavior::ShowZero),
+            ] {
+                behaviors = behaviors.push(
+                    radio(
+                        text(label),
+                        behavior,
+                        selected_behavior,
+                        Message::SetZeroBadgeBehavior,
+                    )
+                    .width(cosmic::iced::Length::Fill),
+                );
+            }
+            widgets.push(behaviors.into());
+        }
+
+        widgets.push(Self::settings_toggle(
+            "Middle-click to update",
+            "Launch the system update when the panel icon is middle-clicked.",
+            self.config.enable_middle_click_update,
+            Message::ToggleMiddleClickUpdate,
+        ));
+
+        widgets.push(Self::settings_toggle(
+            "Compact layout",
+            "Use a smaller popup without the status illustration.",
+            self.config.compact_layout,
+            Message::ToggleCompactLayout,
+        ));
+
+        if !self.config.compact_layout {
+            widgets.push(Self::settings_toggle(
+                "Show illustration",
+                "Show the status illustration beside the popup content.",
+                self.config.show_illustration,
+                Message::ToggleShowIllustration,
+            ));
+        }
 
+        widgets.push(text("Icon Style").size(14).into());
+        let selected_style = Some(self.config.icon_theme);
         widgets.push(
             row()
                 .spacing(8)
-                .align_y(cosmic::iced::Alignment::Center)
-                .push(text("Show notifications"))
-                .push(Space::with_width(cosmic::iced::Length::Fill))
                 .push(
-                    toggler(self.config.show_notifications)
-                        .on_toggle(Message::ToggleShowNotifications),
+                    radio(
+                        text("Symbolic"),
+                        IconStyle::Symbolic,
+                        selected_style,
+                        Message::SetIconStyle,
+                    )
+                    .width(cosmic::iced::Length::Fill),
+                )
+                .push(
+                    radio(
+                        text("Colorful"),
+                        IconStyle::Colorful,
+                        selected_style,
+                        Message::SetIconStyle,
+                    )
+                    .width(cosmic::iced::Length::Fill),
                 )
                 .into(),
         );
 
+        widgets.push(Self::settings_toggle(
+            "Debug logging",
+            "Record every update check in a log file for troubleshooting.",
+            self.config.debug_logging,
+            Message::ToggleDebugLogging,
+        ));
+        if self.config.debug_logging {
+            widgets.push(
+                text(format!(
+                    "Logging checks to {}",
+                    crate::paths::check_log_path().display()
+                ))
+                .size(10)
+                .into(),
+            );
+        }
+        if let Some(active) = self.sync_watcher_active {
+            let status = if active {
+                "Cross-instance sync: active"
+            } else {
+                "Cross-instance sync: unavailable, retrying every minute"
+            };
+            widgets.push(text(status).size(10).into());
+        }
+
+        let checking = matches!(self.check_state, CheckState::Checking);
         widgets.push(
-            row()
-                .spacing(8)
-                .align_y(cosmic::iced::Alignment::Center)
-                .push(text("Show update count"))
-                .push(Space::with_width(cosmic::iced::Length::Fill))
-                .push(
-                    toggler(self.config.show_update_count)
-                        .on_toggle(Message::ToggleShowUpdateCount),
-                )
+            button::text("Reset update lock")
+                .on_press_maybe((!checking).then_some(Message::ResetLock))
+                .into(),
+        );
+        widgets.push(
+            text("Removes a stuck check lock left by a crashed instance. A running instance keeps its lock.")
+                .size(10)
                 .into(),
         );
+        if let Some(status) = &self.lock_reset_status {
+            widgets.push(text(status.as_str()).size(10).into());
+        }
 
         widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
 
         // Terminal setting
         widgets.push(text("Preferred Terminal").size(14).into());
-        let terminal_value = if self.config.preferred_terminal.is_empty() {
-            "cosmic-term".to_string()
-        } else {
-            self.config.preferred_terminal.clone()
-        };
         widgets.push(
-            text_input("cosmic-term", terminal_value)
+            text_input(
+                self.default_terminal.as_str(),
+                self.config.preferred_terminal.as_str(),
+            )
                 .on_input(Message::SetPreferredTerminal)
                 .width(cosmic::iced::Length::Fill)
                 .into(),
         );
 
+        widgets.push(text("Custom Update Launcher").size(14).into());
+        let launcher_value = self
+            .config
+            .custom_update_launcher
+            .clone()
+            .unwrap_or_default();
+        widgets.push(
+            text_input("e.g. alacritty -e topgrade", launcher_value)
+                .on_input(Message::SetCustomUpdateLauncher)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        );
+        widgets.push(
+            text("Runs instead of the terminal and the hooks below. The applet checks again once it exits, so it must wait for the update to finish.")
+                .size(10)
+                .into(),
+        );
+
+        if let Some(pm) = self
+            .config
+            .package_manager
+            .filter(|pm| pm.supports_custom_check_command())
+        {
+            widgets.push(text("Custom Check Command").size(14).into());
+            let check_command_value = self
+                .config
+                .custom_check_commands
+                .get(&pm)
+                .cloned()
+                .unwrap_or_default();
+            widgets.push(
+                text_input("e.g. checkupdates-with-aur", check_command_value)
+                    .on_input(Message::SetCustomCheckCommand)
+                    .width(cosmic::iced::Length::Fill)
+                    .into(),
+            );
+            widgets.push(
+                text("Lists updates instead of the built-in check, with output in the same format. Leave empty for the default.")
+                    .size(10)
+                    .into(),
+            );
+            if let Some(warning) = &self.custom_check_warning {
+                widgets.push(text(warning.as_str()).size(10).into());
+            }
+        }
+
+        widgets.push(Space::with_height(cosmic::iced::Length::Fixed(8.0)).into());
+
+        widgets.push(Self::settings_toggle(
+            "Install without a terminal",
+            "Authenticate with PolicyKit and install in the background where the package manager allows it. Hooks and a custom launcher still use the terminal.",
+            self.config.install_without_terminal,
+            Message::ToggleInstallWithoutTerminal,
+        ));
+
+        // Update hooks
+        widgets.push(text("Pre-update Hook").size(14).into());
+        let pre_hook_value = self.config.pre_update_hook.clone().unwrap_or_default();
+        widgets.push(
+            text_input("e.g. timeshift --create", pre_hook_value)
+                .on_input(Message::SetPreUpdateHook)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        );
+        widgets.push(
+            text("Runs before updating. A non-zero exit aborts the update.")
+                .size(10)
+                .into(),
+        );
+
+        widgets.push(text("Post-update Hook").size(14).into());
+        let post_hook_value = self.config.post_update_hook.clone().unwrap_or_default();
+        widgets.push(
+            text_input("e.g. notify-send 'Updated'", post_hook_value)
+                .on_input(Message::SetPostUpdateHook)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+        );
+        widgets.push(
+            text("Runs after the package manager finished successfully.")
+                .size(10)
+                .into(),
+        );
+
         column().spacing(8).extend(widgets).into()
     }
 }
+
+/// Wrap the system update command with the optional user hooks.
+///
+/// Each hook is passed to its own `sh -c` with shell escaping, so it is run as
+/// the user wrote it. A failing pre-update hook aborts the update; the
+/// post-update hook only runs once the package manager succeeded.
+fn wrap_with_hooks(command: &str, pre_hook: Option<&str>, post_hook: Option<&str>) -> String {
+    let mut wrapped = command.to_string();
+
+    if let Some(pre) = pre_hook.filter(|h| !h.trim().is_empty()) {
+        wrapped = format!(
+            "{{ sh -c {} || {{ echo 'Pre-update hook failed, update aborted. Press Enter to exit...'; read _; false; }}; }} && {}",
+            shell_escape::escape(pre.to_string().into()),
+            wrapped
+        );
+    }
+
+    if let Some(post) = post_hook.filter(|h| !h.trim().is_empty()) {
+        wrapped = format!(
+            "{} && sh -c {}",
+            wrapped,
+            shell_escape::escape(post.to_string().into())
+        );
+    }
+
+    wrapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic::Application;
+
+    /// Applet as `init` builds it, with the default settings
+    fn test_applet(name: &str, test: impl FnOnce(CosmicAppletPackageUpdater)) {
+        crate::config::tests::with_config_home(name, || {
+            let (applet, _) = CosmicAppletPackageUpdater::init(Core::default(), ());
+            test(applet);
+        });
+    }
+
+    /// Run a wrapped command line in `dir`, returning whether it succeeded
+    fn run_wrapped(dir: &std::path::Path, command: &str) -> bool {
+        std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(dir)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap()
+            .success()
+    }
+
+    fn hooks_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hooks-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_wrap_with_hooks_failing_pre_hook_aborts() {
+        let dir = hooks_dir("pre");
+        let command = wrap_with_hooks("touch updated", Some("exit 1"), Some("touch post"));
+        assert!(!run_wrapped(&dir, &command));
+        assert!(!dir.join("updated").exists());
+        assert!(!dir.join("post").exists());
+
+        let command = wrap_with_hooks("touch updated", Some("touch pre"), None);
+        assert!(run_wrapped(&dir, &command));
+        assert!(dir.join("pre").exists());
+        assert!(dir.join("updated").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_wrap_with_hooks_post_hook_after_success() {
+        let dir = hooks_dir("post");
+        let failed = wrap_with_hooks("false", None, Some("touch post"));
+        assert!(!run_wrapped(&dir, &failed));
+        assert!(!dir.join("post").exists());
+
+        let succeeded = wrap_with_hooks("true", None, Some("touch post"));
+        assert!(run_wrapped(&dir, &succeeded));
+        assert!(dir.join("post").exists());
+
+        // Blank hooks leave the command alone
+        let unchanged = wrap_with_hooks("paru -Syu", Some("  "), Some(""));
+        assert_eq!(unchanged, "paru -Syu");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_wrap_with_hooks_escapes_quotes_and_spaces() {
+        assert_eq!(
+            wrap_with_hooks("paru -Syu", None, Some("echo 'a b'")),
+            r"paru -Syu && sh -c 'echo '\''a b'\'''"
+        );
+
+        let dir = hooks_dir("escape");
+        let command = wrap_with_hooks(
+            "true",
+            Some(r#"printf '%s' "it's done" > "pre out.txt""#),
+            Some(r#"printf '%s' 'a "quoted" word' > "post out.txt""#),
+        );
+        assert!(run_wrapped(&dir, &command));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("pre out.txt")).unwrap(),
+            "it's done"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("post out.txt")).unwrap(),
+            r#"a "quoted" word"#
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_check_requested_while_checking() {
+        test_applet("app-check-requested", |mut applet| {
+            applet.config.package_manager = Some(PackageManager::Pacman);
+            applet.check_state = CheckState::Checking;
+            let (cancel_tx, mut cancel_rx) = tokio::sync::oneshot::channel();
+            applet.cancel_check = Some(cancel_tx);
+
+            let _ = applet.update(Message::CheckForUpdates);
+            // The running check keeps its cancel handle
+            assert!(applet.check_requested);
+            assert!(applet.cancel_check.is_some());
+            assert_eq!(
+                cancel_rx.try_recv(),
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty)
+            );
+
+            let _ = applet.update(Message::CancelCheck);
+            assert_eq!(cancel_rx.try_recv(), Ok(()));
+            assert!(!applet.check_requested);
+        });
+    }
+}
@@ -0,0 +1,129 @@
+//! The "3 new, 1 resolved since last check" line at the top of the Updates
+//! tab.
+//!
+//! The pending versions of the last result are kept in
+//! [`crate::paths::previous_updates_path`], so the first check after a
+//! restart is compared with the last one of the previous session.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::package_manager::PackageUpdate;
+
+/// Pending new version per package name
+pub type PendingVersions = BTreeMap<String, String>;
+
+/// Pending versions of `packages`
+pub fn pending_versions(packages: &[PackageUpdate]) -> PendingVersions {
+    packages
+        .iter()
+        .map(|p| (p.name.clone(), p.new_version.clone()))
+        .collect()
+}
+
+/// "2 new, 1 newer version, 1 resolved since last check"; `None` without
+/// changes.
+///
+/// A package still pending with a different new version than before counts
+/// as a newer version rather than as new.
+pub fn summarize(previous: &PendingVersions, current: &PendingVersions) -> Option<String> {
+    let added = current
+        .keys()
+        .filter(|name| !previous.contains_key(*name))
+        .count();
+    let upgraded = current
+        .iter()
+        .filter(|(name, version)| previous.get(*name).is_some_and(|old| old != *version))
+        .count();
+    let resolved = previous
+        .keys()
+        .filter(|name| !current.contains_key(*name))
+        .count();
+
+    let upgraded_label = if upgraded == 1 {
+        "newer version"
+    } else {
+        "newer versions"
+    };
+    let parts: Vec<String> = [
+        (added, "new"),
+        (upgraded, upgraded_label),
+        (resolved, "resolved"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, label)| format!("{} {}", count, label))
+    .collect();
+    (!parts.is_empty()).then(|| format!("{} since last check", parts.join(", ")))
+}
+
+/// Pending versions saved by the previous check; `None` when nothing was
+/// saved yet or the file is unreadable
+pub fn load(path: &Path) -> Option<PendingVersions> {
+    let json = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Save `versions` for the next comparison
+pub fn save(path: &Path, versions: &PendingVersions) -> Result<()> {
+    crate::paths::write_atomic(path, serde_json::to_string(versions)?.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(entries: &[(&str, &str)]) -> PendingVersions {
+        entries
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_summarize_added_upgraded_resolved() {
+        let previous = versions(&[("curl", "8.5.0-1"), ("linux", "6.7.1"), ("vim", "9.1")]);
+        let current = versions(&[
+            ("curl", "8.5.0-1"),
+            ("linux", "6.7.2"),
+            ("git", "2.44.0"),
+            ("mesa", "24.0.1"),
+        ]);
+
+        assert_eq!(
+            summarize(&previous, &current).as_deref(),
+            Some("2 new, 1 newer version, 1 resolved since last check")
+        );
+        assert_eq!(
+            summarize(&current, &previous).as_deref(),
+            Some("1 new, 1 newer version, 2 resolved since last check")
+        );
+    }
+
+    #[test]
+    fn test_summarize_without_changes() {
+        let same = versions(&[("curl", "8.5.0-1")]);
+        assert_eq!(summarize(&same, &same), None);
+        assert_eq!(
+            summarize(&PendingVersions::new(), &PendingVersions::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("change-summary-test-{}", std::process::id()));
+        let path = dir.join("previous-updates.json");
+        assert_eq!(load(&path), None);
+
+        let saved = versions(&[("curl", "8.5.0-1"), ("git", "2.44.0")]);
+        save(&path, &saved).unwrap();
+        assert_eq!(load(&path), Some(saved));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(load(&path), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
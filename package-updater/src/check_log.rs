@@ -0,0 +1,169 @@
+//! Optional persistent log of update checks.
+//!
+//! When `debug_logging` is enabled, every check appends one `key=value` line to
+//! [`crate::paths::check_log_path`] recording when it ran, which package manager
+//! was used, the resulting counts and how long it took. Warnings raised during
+//! a check go to the same file, so count changes can be traced after the fact.
+//! The file is rotated to `checks.log.1` once it grows past [`MAX_LOG_SIZE`].
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::package_manager::{CheckError, PackageManager, UpdateInfo};
+
+/// Size after which the log is rotated
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable writing to the log file (mirrors the `debug_logging` setting)
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Seconds since the Unix epoch, shared by everything that stamps a file
+pub fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Record the outcome of a finished update check
+pub fn record_check(
+    package_manager: PackageManager,
+    result: &Result<UpdateInfo, CheckError>,
+    duration: Duration,
+) {
+    if !is_enabled() {
+        return;
+    }
+    let line = format_check_line(unix_timestamp(), package_manager, result, duration);
+    append_line(&crate::paths::check_log_path(), &line);
+}
+
+/// Print a warning to stderr and, when enabled, persist it to the log
+pub fn warn(message: impl AsRef<str>) {
+    let message = message.as_ref();
+    eprintln!("{}", message);
+    if is_enabled() {
+        let line = format!(
+            "timestamp={} level=warn message={:?}",
+            unix_timestamp(),
+            message
+        );
+        append_line(&crate::paths::check_log_path(), &line);
+    }
+}
+
+fn format_check_line(
+    timestamp: u64,
+    package_manager: PackageManager,
+    result: &Result<UpdateInfo, CheckError>,
+    duration: Duration,
+) -> String {
+    match result {
+        Ok(info) => format!(
+            "timestamp={} level=info manager={} result=ok total={} official={} aur={} duration_ms={}",
+            timestamp,
+            package_manager.name(),
+            info.total_updates,
+            info.official_updates,
+            info.aur_updates,
+            duration.as_millis()
+        ),
+        Err(error) => format!(
+            "timestamp={} level=error manager={} result=error duration_ms={} error={:?}",
+            timestamp,
+            package_manager.name(),
+            duration.as_millis(),
+            error.to_string()
+        ),
+    }
+}
+
+/// Append a line to the log, rotating it first if it grew past the cap
+fn append_line(path: &Path, line: &str) {
+    if let Some(parent) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            eprintln!("Warning: Failed to create log directory: {}", e);
+            return;
+        }
+    }
+
+    if std::fs::metadata(path).is_ok_and(|m| m.len() >= MAX_LOG_SIZE) {
+        let rotated = path.with_extension("log.1");
+        if let Err(e) = std::fs::rename(path, &rotated) {
+            eprintln!("Warning: Failed to rotate check log: {}", e);
+        }
+    }
+
+    match OpenOptions::new().create(true).append(true).open(path) {
+        Ok(mut file) => {
+            if let Err(e) = writeln!(file, "{}", line) {
+                eprintln!("Warning: Failed to write check log: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Warning: Failed to open check log: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_successful_check() {
+        let mut info = UpdateInfo::new();
+        info.total_updates = 3;
+        info.official_updates = 2;
+        info.aur_updates = 1;
+
+        let line = format_check_line(
+            1700000000,
+            PackageManager::Paru,
+            &Ok(info),
+            Duration::from_millis(1500),
+        );
+
+        assert_eq!(
+            line,
+            "timestamp=1700000000 level=info manager=paru result=ok total=3 official=2 aur=1 duration_ms=1500"
+        );
+    }
+
+    #[test]
+    fn test_format_failed_check() {
+        let line = format_check_line(
+            1700000000,
+            PackageManager::Apt,
+            &Err(CheckError::Other("exit 100".to_string())),
+            Duration::from_millis(20),
+        );
+
+        assert!(line.contains("result=error"));
+        assert!(line.ends_with("error=\"exit 100\""));
+    }
+
+    #[test]
+    fn test_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("check-log-test-{}", std::process::id()));
+        let path = dir.join("checks.log");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(&path, vec![b'x'; MAX_LOG_SIZE as usize]).unwrap();
+        append_line(&path, "fresh");
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fresh\n");
+        assert!(dir.join("checks.log.1").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
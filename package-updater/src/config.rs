@@ -1,34 +1,214 @@
 use cosmic_config::{Config, ConfigGet, ConfigSet};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::package_manager::PackageManager;
 
+/// cosmic-config application ID the settings are stored under
+pub const CONFIG_ID: &str = "com.github.cosmic_ext.PackageUpdater";
+
+/// Bump only for changes `#[serde(default)]` cannot absorb (a field changing
+/// type or meaning), adding the previous version to `OLDER_CONFIG_VERSIONS`
 pub const CONFIG_VERSION: u64 = 1;
 
+/// Earlier versions whose settings are migrated, newest first
+const OLDER_CONFIG_VERSIONS: &[u64] = &[];
+
+/// Cleared when stored settings could not be read nor copied aside, so
+/// saving would destroy them
+static ENTRY_WRITABLE: AtomicBool = AtomicBool::new(true);
+
+/// Where cosmic-config keeps this applet's settings of `version`
+pub fn config_dir(version: u64) -> PathBuf {
+    let base = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".config"))
+        })
+        .unwrap_or_default();
+    base.join("cosmic")
+        .join(CONFIG_ID)
+        .join(format!("v{}", version))
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum NixOSMode {
     Channels,
     Flakes,
 }
 
+/// Which parts of a NixOS dry-build are reported as updates
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum NixOSUpdateCount {
+    /// Derivations to build plus paths to fetch
+    #[default]
+    BuildsAndFetches,
+    /// Only the derivations that will be built
+    Builds,
+    /// Only the store paths that will be fetched
+    Fetches,
+}
+
+impl NixOSUpdateCount {
+    pub fn includes_builds(self) -> bool {
+        matches!(self, NixOSUpdateCount::BuildsAndFetches | NixOSUpdateCount::Builds)
+    }
+
+    pub fn includes_fetches(self) -> bool {
+        matches!(self, NixOSUpdateCount::BuildsAndFetches | NixOSUpdateCount::Fetches)
+    }
+}
+
+/// Icon names used for the panel icon and the popup illustration
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum IconStyle {
+    /// Monochrome `-symbolic` icons, matching the rest of the panel
+    #[default]
+    Symbolic,
+    /// Full-color variants of the same icons
+    Colorful,
+}
+
+impl IconStyle {
+    /// Map a symbolic icon name to this style
+    pub fn icon_name(self, symbolic: &'static str) -> &'static str {
+        match self {
+            IconStyle::Symbolic => symbolic,
+            IconStyle::Colorful => symbolic.strip_suffix("-symbolic").unwrap_or(symbolic),
+        }
+    }
+}
+
+/// What the panel count shows when there are no updates
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ZeroBadgeBehavior {
+    /// Only the icon
+    Hidden,
+    /// A check mark once a check confirmed the system is up to date
+    #[default]
+    Checkmark,
+    /// The number 0
+    ShowZero,
+}
+
+/// Freedesktop notification urgency, ordered from least to most urgent
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum NotificationUrgency {
+    Low,
+    #[default]
+    Normal,
+    /// Stays on screen until dismissed
+    Critical,
+}
+
+/// How AUR updates are looked up
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AurCheckBackend {
+    /// `paru -Qu --aur` / `yay -Qu --aur`
+    #[default]
+    Helper,
+    /// Batched AUR RPC `info` requests for the foreign packages, compared
+    /// locally; falls back to the helper on failure
+    Rpc,
+}
+
+/// A machine whose updates are checked over SSH
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RemoteHost {
+    /// `ssh` destination, e.g. `admin@nas` or a `~/.ssh/config` alias
+    pub target: String,
+    /// Package manager on that machine
+    pub package_manager: PackageManager,
+}
+
+impl RemoteHost {
+    /// Parse comma-separated `target manager` entries, e.g.
+    /// `admin@nas apt, pi@pihole apt`. Entries with an unknown or
+    /// unsupported manager are skipped, as are targets starting with `-`,
+    /// which ssh would read as an option such as `-oProxyCommand=...`.
+    pub fn parse_list(input: &str) -> Vec<RemoteHost> {
+        input
+            .split(',')
+            .filter_map(|entry| {
+                let mut words = entry.split_whitespace();
+                let target = words.next().filter(|target| !target.starts_with('-'))?;
+                let package_manager = PackageManager::from_name(words.next()?)
+                    .filter(|pm| pm.supports_remote_check())?;
+                words.next().is_none().then(|| RemoteHost {
+                    target: target.to_string(),
+                    package_manager,
+                })
+            })
+            .collect()
+    }
+
+    /// Inverse of [`parse_list`](Self::parse_list)
+    pub fn format_list(hosts: &[RemoteHost]) -> String {
+        hosts
+            .iter()
+            .map(|host| format!("{} {}", host.target, host.package_manager.name()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Where NixOS keeps its configuration unless told otherwise
+pub const DEFAULT_NIXOS_CONFIG_PATH: &str = "/etc/nixos";
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct NixOSConfig {
     pub mode: NixOSMode,
     pub config_path: String,
     #[serde(default)]
     pub hostname: Option<String>,
+    /// home-manager configuration directory; `~/.config/home-manager` when unset
+    #[serde(default)]
+    pub home_manager_path: Option<String>,
+    /// What the NixOS update count covers
+    #[serde(default)]
+    pub update_count: NixOSUpdateCount,
 }
 
 impl Default for NixOSConfig {
     fn default() -> Self {
         Self {
             mode: NixOSMode::Flakes,
-            config_path: "/etc/nixos".to_string(),
+            config_path: DEFAULT_NIXOS_CONFIG_PATH.to_string(),
             hostname: None,
+            home_manager_path: None,
+            update_count: NixOSUpdateCount::BuildsAndFetches,
         }
     }
 }
 
+impl NixOSConfig {
+    /// The home-manager configuration directory, falling back to the default
+    /// `$XDG_CONFIG_HOME/home-manager`.
+    pub fn home_manager_dir(&self) -> String {
+        if let Some(path) = self.home_manager_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            return path.to_string();
+        }
+        let config_home = std::env::var("XDG_CONFIG_HOME")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or_else(|| format!("{}/.config", std::env::var("HOME").unwrap_or_default()));
+        format!("{}/home-manager", config_home)
+    }
+
+    /// Whether the home-manager configuration is a flake
+    pub fn home_manager_uses_flake(&self) -> bool {
+        std::path::Path::new(&self.home_manager_dir())
+            .join("flake.nix")
+            .exists()
+    }
+}
+
 /// Auto-detect the system hostname from /etc/hostname.
 pub fn detect_hostname() -> Option<String> {
     std::fs::read_to_string("/etc/hostname")
@@ -38,6 +218,7 @@ pub fn detect_hostname() -> Option<String> {
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
 pub struct PackageUpdaterConfig {
     pub package_manager: Option<PackageManager>,
     pub check_interval_minutes: u32,
@@ -47,6 +228,86 @@ pub struct PackageUpdaterConfig {
     pub show_update_count: bool,
     pub preferred_terminal: String,
     pub nixos_config: NixOSConfig,
+    /// Shell command run before the system update; a non-zero exit aborts the update
+    pub pre_update_hook: Option<String>,
+    /// Shell command run after the package manager finished successfully
+    pub post_update_hook: Option<String>,
+    /// Shell command run instead of the terminal and hooks; the update counts
+    /// as finished when it exits
+    pub custom_update_launcher: Option<String>,
+    /// Install updates in the background after PolicyKit authentication
+    /// instead of in a terminal, for managers with a non-interactive update
+    pub install_without_terminal: bool,
+    /// Shell command listing updates in place of the built-in one, per
+    /// package manager; its output goes through that manager's parser
+    pub custom_check_commands: HashMap<PackageManager, String>,
+    /// Append a record of every update check to the check log
+    pub debug_logging: bool,
+    /// Refresh the apk index (privileged `apk update`) before checking
+    pub apk_refresh_index: bool,
+    /// Refresh the zypper repositories (privileged `zypper refresh`) before checking
+    pub zypper_refresh_repos: bool,
+    /// Smaller popup without the status illustration
+    pub compact_layout: bool,
+    /// Query repo and AUR updates with a single AUR helper call
+    pub aur_single_query: bool,
+    /// Check apt updates by simulating `apt-get dist-upgrade` instead of `apt list --upgradable`
+    pub apt_use_dist_upgrade_simulation: bool,
+    /// List and count only security updates (apt/dnf)
+    pub security_only: bool,
+    /// Auto-detection picks the system manager (pacman) over AUR helpers
+    pub prefer_system_manager: bool,
+    /// Update count from which the panel badge uses the warning color (0 disables)
+    pub badge_warning_threshold: u32,
+    /// Packages rendered before the list offers "Show more" (0 shows all)
+    pub max_displayed_packages: usize,
+    /// apt suites (e.g. `bookworm-backports`) whose updates are counted; all when empty
+    pub apt_origins: Vec<String>,
+    /// Middle-clicking the panel icon launches the system update
+    pub enable_middle_click_update: bool,
+    /// Seconds to keep retrying while another instance is checking (0 tries once)
+    pub lock_timeout_secs: u32,
+    /// Count installed foreign (AUR) packages with `-Qm` on every check
+    pub show_foreign_package_count: bool,
+    /// Symbolic or full-color status icons
+    pub icon_theme: IconStyle,
+    /// Auto-detection picks nala over apt when both are installed, regardless
+    /// of `prefer_system_manager`
+    pub prefer_nala: bool,
+    /// Flag installed AUR packages that depend on official packages updated to a
+    /// new major or minor version
+    pub aur_rebuild_hints: bool,
+    /// Panel count shown at zero updates
+    pub zero_badge_behavior: ZeroBadgeBehavior,
+    /// Look up the total download size of repo updates (pacman-based)
+    pub show_download_size: bool,
+    /// Re-check once the package database stops changing, e.g. after an
+    /// update run outside the applet
+    pub watch_package_database: bool,
+    /// Machines checked over SSH alongside this one
+    pub remote_hosts: Vec<RemoteHost>,
+    /// List AUR updates above official ones in the grouped list
+    pub aur_first: bool,
+    /// `Name=regex` entries grouping matching packages in their own section
+    pub custom_repo_patterns: Vec<String>,
+    /// Unix time until which notifications and the panel highlight are muted
+    pub snooze_until: Option<u64>,
+    /// Without PolicyKit or passwordless sudo, ask for the sudo password with
+    /// a graphical askpass helper (`sudo -A`); off by default since every
+    /// scheduled check would prompt
+    pub sudo_askpass: bool,
+    /// Where the separate AUR check gets its results
+    pub aur_check_backend: AurCheckBackend,
+    /// Status illustration beside the popup content (regular layout only)
+    pub show_illustration: bool,
+    /// Skip automatic checks while running on battery
+    pub check_only_on_ac: bool,
+    /// Larger panel count text for legibility
+    pub large_badge_text: bool,
+    /// Urgency of routine notifications; security updates are always critical
+    pub notification_urgency: NotificationUrgency,
+    /// Package names left out of the list and the counts
+    pub ignored_packages: Vec<String>,
 }
 
 impl Default for PackageUpdaterConfig {
@@ -58,24 +319,272 @@ impl Default for PackageUpdaterConfig {
             include_aur_updates: true,
             show_notifications: true,
             show_update_count: true,
-            preferred_terminal: "cosmic-term".to_string(),
+            preferred_terminal: String::new(),
             nixos_config: NixOSConfig::default(),
+            pre_update_hook: None,
+            post_update_hook: None,
+            custom_update_launcher: None,
+            install_without_terminal: false,
+            custom_check_commands: HashMap::new(),
+            debug_logging: false,
+            apk_refresh_index: false,
+            zypper_refresh_repos: false,
+            compact_layout: false,
+            aur_single_query: false,
+            apt_use_dist_upgrade_simulation: false,
+            security_only: false,
+            prefer_system_manager: false,
+            badge_warning_threshold: 20,
+            max_displayed_packages: 200,
+            apt_origins: Vec::new(),
+            enable_middle_click_update: true,
+            lock_timeout_secs: 10,
+            show_foreign_package_count: false,
+            icon_theme: IconStyle::Symbolic,
+            prefer_nala: false,
+            aur_rebuild_hints: false,
+            zero_badge_behavior: ZeroBadgeBehavior::Checkmark,
+            show_download_size: false,
+            watch_package_database: false,
+            remote_hosts: Vec::new(),
+            custom_repo_patterns: Vec::new(),
+            aur_first: false,
+            snooze_until: None,
+            sudo_askpass: false,
+            aur_check_backend: AurCheckBackend::Helper,
+            show_illustration: true,
+            check_only_on_ac: false,
+            large_badge_text: false,
+            notification_urgency: NotificationUrgency::Normal,
+            ignored_packages: Vec::new(),
         }
     }
 }
 
 impl PackageUpdaterConfig {
+    /// Load the settings of `CONFIG_VERSION`, or migrate those of the newest
+    /// older version that has any. Migrated settings are stored at the
+    /// current version by the next `set_entry`.
+    ///
+    /// A stored entry that does not parse is copied aside first, so saving
+    /// changed settings never destroys it; without that copy nothing is saved.
     pub fn load() -> (Config, Self) {
-        let config = Config::new("com.github.cosmic_ext.PackageUpdater", CONFIG_VERSION).unwrap();
-        let config_helper = Self::get_entry(&config).unwrap_or_default();
+        let config = Config::new(CONFIG_ID, CONFIG_VERSION).unwrap();
+        let config_helper = match config.get::<Self>("config") {
+            Ok(entry) => entry,
+            Err(e) => {
+                let entry = config_dir(CONFIG_VERSION).join("config");
+                if entry.is_file() {
+                    Self::preserve_unreadable(&entry, &e);
+                }
+                Self::migrate_older(OLDER_CONFIG_VERSIONS).unwrap_or_else(|| {
+                    eprintln!(
+                        "No usable settings at config version {} ({}), using defaults",
+                        CONFIG_VERSION, e
+                    );
+                    Self::default()
+                })
+            }
+        };
         (config, config_helper)
     }
 
+    /// Copy the unreadable `entry` to `config.unreadable-<unix time>` next to
+    /// it, or stop `set_entry` from writing over it when that fails
+    fn preserve_unreadable(entry: &Path, error: &cosmic_config::Error) {
+        let timestamp = crate::check_log::unix_timestamp();
+        let backup = entry.with_file_name(format!("config.unreadable-{}", timestamp));
+        match std::fs::copy(entry, &backup) {
+            Ok(_) => eprintln!(
+                "Settings in {} could not be read ({}), kept a copy in {}",
+                entry.display(),
+                error,
+                backup.display()
+            ),
+            Err(e) => {
+                eprintln!(
+                    "Settings in {} could not be read ({}) nor copied ({}), not saving changes",
+                    entry.display(),
+                    error,
+                    e
+                );
+                ENTRY_WRITABLE.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Settings of the newest of `versions` that has any, migrated
+    fn migrate_older(versions: &[u64]) -> Option<Self> {
+        versions.iter().find_map(|&version| {
+            let older = Config::new(CONFIG_ID, version).ok()?;
+            let migrated = Self::migrate(version, &older)?;
+            eprintln!("Migrated settings from config version {}", version);
+            Some(migrated)
+        })
+    }
+
+    /// Convert the entry stored by an older `version` to the current shape.
+    ///
+    /// Every field is `#[serde(default)]` and unknown fields are ignored, so
+    /// a version that only added or removed fields loads as is. For a
+    /// breaking change, keep the old shape as its own struct, read that here
+    /// and convert it field by field.
+    fn migrate(_version: u64, config: &Config) -> Option<Self> {
+        Self::get_entry(config)
+    }
+
     pub fn get_entry(config: &Config) -> Option<Self> {
         config.get("config").ok()
     }
 
     pub fn set_entry(config: &Config, config_helper: &Self) {
+        if !ENTRY_WRITABLE.load(Ordering::Relaxed) {
+            return;
+        }
         let _ = config.set("config", config_helper);
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_older_shape_loads_without_data_loss() {
+        let saved = PackageUpdaterConfig {
+            package_manager: Some(PackageManager::Dnf),
+            check_interval_minutes: 15,
+            preferred_terminal: "kitty".to_string(),
+            pre_update_hook: Some("snapper create".to_string()),
+            ..Default::default()
+        };
+        let mut stored = serde_json::to_value(&saved).unwrap();
+        let fields = stored.as_object_mut().unwrap();
+        // Written before these fields existed, with one that was dropped since
+        fields.remove("notification_urgency");
+        fields.remove("zypper_refresh_repos");
+        fields.insert("removed_setting".to_string(), serde_json::json!(true));
+
+        let loaded: PackageUpdaterConfig = serde_json::from_value(stored).unwrap();
+        assert_eq!(loaded, saved);
+    }
+
+    /// Serializes the tests that point `XDG_CONFIG_HOME` at a temp directory
+    static CONFIG_HOME: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Run `test` with a fresh, empty `XDG_CONFIG_HOME`
+    pub(crate) fn with_config_home(name: &str, test: impl FnOnce()) {
+        let _guard = CONFIG_HOME.lock().unwrap_or_else(|e| e.into_inner());
+        let home =
+            std::env::temp_dir().join(format!("config-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::env::set_var("XDG_CONFIG_HOME", &home);
+        test();
+        std::env::remove_var("XDG_CONFIG_HOME");
+        let _ = std::fs::remove_dir_all(home);
+    }
+
+    fn store_entry(version: u64, ron: &str) -> PathBuf {
+        let dir = config_dir(version);
+        std::fs::create_dir_all(&dir).unwrap();
+        let entry = dir.join("config");
+        std::fs::write(&entry, ron).unwrap();
+        entry
+    }
+
+    /// The shape the applet first stored, before any field had a default
+    const V1_INITIAL: &str = include_str!("../tests/config/v1-initial.ron");
+
+    fn v1_initial_settings() -> PackageUpdaterConfig {
+        PackageUpdaterConfig {
+            package_manager: Some(PackageManager::Dnf),
+            check_interval_minutes: 15,
+            auto_check_on_startup: false,
+            show_notifications: false,
+            preferred_terminal: "kitty".to_string(),
+            nixos_config: NixOSConfig {
+                mode: NixOSMode::Channels,
+                config_path: "/home/user/nixos-config".to_string(),
+                hostname: Some("desk".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_load_initial_shape() {
+        with_config_home("initial", || {
+            store_entry(CONFIG_VERSION, V1_INITIAL);
+            let (_, loaded) = PackageUpdaterConfig::load();
+            assert_eq!(loaded, v1_initial_settings());
+        });
+    }
+
+    #[test]
+    fn test_migrate_older_version() {
+        // As after a bump: the current version holds nothing yet
+        with_config_home("migrate", || {
+            store_entry(1, V1_INITIAL);
+            assert_eq!(
+                PackageUpdaterConfig::migrate_older(&[3, 2, 1]),
+                Some(v1_initial_settings())
+            );
+            assert_eq!(PackageUpdaterConfig::migrate_older(&[3, 2]), None);
+        });
+    }
+
+    #[test]
+    fn test_unreadable_entry_is_kept() {
+        with_config_home("unreadable", || {
+            let unreadable = "(check_interval_minutes: \"hourly\")";
+            let entry = store_entry(CONFIG_VERSION, unreadable);
+
+            let (config, loaded) = PackageUpdaterConfig::load();
+            assert_eq!(loaded, PackageUpdaterConfig::default());
+
+            let backups: Vec<_> = std::fs::read_dir(entry.parent().unwrap())
+                .unwrap()
+                .filter_map(|dir_entry| dir_entry.ok())
+                .filter(|dir_entry| {
+                    dir_entry
+                        .file_name()
+                        .to_string_lossy()
+                        .starts_with("config.unreadable-")
+                })
+                .collect();
+            assert_eq!(backups.len(), 1);
+            let backup = || std::fs::read_to_string(backups[0].path()).unwrap();
+            assert_eq!(backup(), unreadable);
+
+            // Saving may now replace the entry, the copy stays
+            PackageUpdaterConfig::set_entry(&config, &loaded);
+            assert_eq!(backup(), unreadable);
+        });
+    }
+
+    #[test]
+    fn test_remote_host_list_round_trip() {
+        let hosts = RemoteHost::parse_list(" admin@nas apt, pi dnf ,, bad, box nixos, x emerge");
+        assert_eq!(
+            hosts,
+            vec![
+                RemoteHost {
+                    target: "admin@nas".to_string(),
+                    package_manager: PackageManager::Apt,
+                },
+                RemoteHost {
+                    target: "pi".to_string(),
+                    package_manager: PackageManager::Dnf,
+                },
+            ]
+        );
+        assert_eq!(RemoteHost::format_list(&hosts), "admin@nas apt, pi dnf");
+    }
+
+    #[test]
+    fn test_remote_host_list_rejects_options() {
+        assert!(RemoteHost::parse_list("-oProxyCommand=touch apt").is_empty());
+        assert!(RemoteHost::parse_list("-p apt, -- dnf").is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! User-defined package groups, e.g. for packages from a private repository,
+//! listed in their own section like the Official/AUR split.
+
+use regex::Regex;
+
+/// Group of patterns given without a name
+const DEFAULT_GROUP: &str = "Custom";
+
+/// Compiled `custom_repo_patterns`.
+///
+/// Each entry is `Name=regex`, or a bare regex for the "Custom" group. The
+/// regex matches anywhere in the package name, so anchor it (`^corp-`) to
+/// match a prefix only.
+#[derive(Debug, Clone, Default)]
+pub struct CustomGroups {
+    /// Group names in first-use order
+    names: Vec<String>,
+    /// Index into `names` and its pattern, in config order
+    patterns: Vec<(usize, Regex)>,
+    /// One message per entry that failed to compile
+    errors: Vec<String>,
+}
+
+impl CustomGroups {
+    pub fn compile(entries: &[String]) -> Self {
+        let mut groups = Self::default();
+        for entry in entries {
+            let (name, pattern) = split_entry(entry);
+            match Regex::new(pattern) {
+                Ok(regex) => {
+                    let index = match groups.names.iter().position(|n| n == name) {
+                        Some(index) => index,
+                        None => {
+                            groups.names.push(name.to_string());
+                            groups.names.len() - 1
+                        }
+                    };
+                    groups.patterns.push((index, regex));
+                }
+                Err(e) => groups
+                    .errors
+                    .push(format!("Invalid pattern '{}': {}", pattern, e)),
+            }
+        }
+        groups
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Group names; [`group_of`](Self::group_of) indexes into these
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Group of the first pattern matching `package`
+    pub fn group_of(&self, package: &str) -> Option<usize> {
+        self.patterns
+            .iter()
+            .find(|(_, regex)| regex.is_match(package))
+            .map(|(index, _)| *index)
+    }
+
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+}
+
+/// Split `Name=regex` into its parts; anything else is a bare regex
+fn split_entry(entry: &str) -> (&str, &str) {
+    match entry.split_once('=') {
+        Some((name, pattern))
+            if !name.trim().is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_')) =>
+        {
+            (name.trim(), pattern.trim())
+        }
+        _ => (DEFAULT_GROUP, entry.trim()),
+    }
+}
+
+/// Parse the settings input: entries separated by `;`
+pub fn parse_list(input: &str) -> Vec<String> {
+    input
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Format entries for the settings input
+pub fn format_list(entries: &[String]) -> String {
+    entries.join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_and_default_groups() {
+        let groups = CustomGroups::compile(&parse_list(
+            "Corp=^corp-; ^internal-; Corp=-corp$; Other=^zz",
+        ));
+
+        assert_eq!(groups.names(), ["Corp", "Custom", "Other"]);
+        assert_eq!(groups.group_of("corp-vpn"), Some(0));
+        assert_eq!(groups.group_of("tools-corp"), Some(0));
+        assert_eq!(groups.group_of("internal-ca"), Some(1));
+        assert_eq!(groups.group_of("linux"), None);
+        assert!(groups.errors().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_reported() {
+        let groups = CustomGroups::compile(&["Broken=(".to_string(), "^ok-".to_string()]);
+
+        assert_eq!(groups.errors().len(), 1);
+        assert!(groups.errors()[0].contains("'('"));
+        assert_eq!(groups.group_of("ok-pkg"), Some(0));
+    }
+
+    #[test]
+    fn test_regex_with_equals_is_bare() {
+        // A "name" with regex syntax in it is part of the pattern
+        let groups = CustomGroups::compile(&["^a(b=c)".to_string()]);
+        assert_eq!(groups.names(), ["Custom"]);
+        assert_eq!(groups.group_of("ab=c"), Some(0));
+    }
+
+    #[test]
+    fn test_list_round_trip() {
+        let entries = parse_list(" Corp=^corp- ;; ^x ");
+        assert_eq!(entries, ["Corp=^corp-", "^x"]);
+        assert_eq!(format_list(&entries), "Corp=^corp-; ^x");
+    }
+}
@@ -0,0 +1,195 @@
+//! `--doctor`: print a diagnostic report for triaging detection and
+//! privilege problems.
+//!
+//! Everything here only reads: nothing is written or refreshed. The
+//! exceptions are what the applet itself does on startup: resolving the
+//! runtime directory may create its private fallback under /tmp, and
+//! settings that fail to load are copied aside.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::{PackageUpdaterConfig, CONFIG_VERSION};
+use crate::package_manager::{PackageManager, PackageManagerDetector};
+
+/// Print the report to stdout, for `manager` instead of the configured
+/// or detected one when given
+pub fn run(manager: Option<PackageManager>) {
+    println!(
+        "cosmic-applet-package-updater {} doctor",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    println!();
+    println!("Package managers:");
+    let available = PackageManagerDetector::detect_available();
+    if available.is_empty() {
+        println!("  none detected");
+    }
+    for pm in &available {
+        let binary = binary_for(*pm);
+        let path = which(binary).unwrap_or_else(|| "not on PATH".to_string());
+        println!("  {:<14} {} ({})", pm.name(), path, binary);
+    }
+
+    let config_dir = crate::config::config_dir(CONFIG_VERSION);
+    let config = if config_dir.is_dir() {
+        let (_, config) = PackageUpdaterConfig::load();
+        Some(config)
+    } else {
+        None
+    };
+    let effective = config.clone().unwrap_or_default();
+    if let Some(pm) = manager {
+        let path = which(binary_for(pm)).unwrap_or_else(|| "not on PATH".to_string());
+        println!("  selected: {} (--manager, {})", pm.name(), path);
+    }
+    match effective.package_manager {
+        Some(pm) => println!("  configured: {}", pm.name()),
+        None => match PackageManagerDetector::get_preferred(
+            &available,
+            effective.prefer_system_manager,
+            effective.prefer_nala,
+        ) {
+            Some(pm) => println!("  configured: none, auto-detection picks {}", pm.name()),
+            None => println!("  configured: none"),
+        },
+    }
+
+    println!();
+    println!("Privileges:");
+    let polkit = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map(|runtime| runtime.block_on(crate::polkit::PolkitAuth::is_available()));
+    match polkit {
+        Ok(available) => println!("  PolicyKit: {}", yes_no(available)),
+        Err(e) => println!("  PolicyKit: unknown ({})", e),
+    }
+    let sudo = Command::new("sudo")
+        .args(["-n", "true"])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    println!("  passwordless sudo: {}", yes_no(sudo));
+    match crate::package_manager::find_askpass() {
+        Some(askpass) => println!("  sudo askpass helper: {}", askpass),
+        None => println!("  sudo askpass helper: none found"),
+    }
+
+    println!();
+    println!("Runtime directory:");
+    match std::env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => {
+            let writable = is_writable(Path::new(&dir));
+            println!("  XDG_RUNTIME_DIR={} (writable: {})", dir, yes_no(writable));
+        }
+        _ => println!("  XDG_RUNTIME_DIR is not set"),
+    }
+    println!(
+        "  lock and sync files: {}",
+        crate::paths::runtime_dir().display()
+    );
+
+    println!();
+    match config {
+        Some(config) => {
+            println!("Config ({}):", config_dir.display());
+            println!("{:#?}", config);
+        }
+        None => println!(
+            "Config: nothing saved in {}, defaults apply",
+            config_dir.display()
+        ),
+    }
+}
+
+/// The package manager named by `--manager <name>`, if given
+pub fn manager_arg(args: impl Iterator<Item = String>) -> Result<Option<PackageManager>, String> {
+    let mut args = args.skip_while(|arg| arg != "--manager");
+    if args.next().is_none() {
+        return Ok(None);
+    }
+    let names = || {
+        PackageManager::ALL
+            .iter()
+            .map(|pm| pm.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let name = args
+        .next()
+        .ok_or_else(|| format!("--manager needs a name, one of: {}", names()))?;
+    PackageManager::from_name(&name).map(Some).ok_or_else(|| {
+        format!(
+            "Unknown package manager '{}', expected one of: {}",
+            name,
+            names()
+        )
+    })
+}
+
+/// Executable whose presence makes a package manager available
+fn binary_for(pm: PackageManager) -> &'static str {
+    match pm {
+        PackageManager::NixOS => "nixos-rebuild",
+        PackageManager::NixProfile => "nix",
+        pm => pm.name(),
+    }
+}
+
+fn which(binary: &str) -> Option<String> {
+    Command::new("which")
+        .arg(binary)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Write permission check without creating a probe file
+fn is_writable(dir: &Path) -> bool {
+    nix::unistd::access(dir, nix::unistd::AccessFlags::W_OK).is_ok()
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manager_arg() {
+        let args = |line: &str| {
+            line.split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(manager_arg(args("--doctor").into_iter()), Ok(None));
+        assert_eq!(
+            manager_arg(args("--doctor --manager pacman").into_iter()),
+            Ok(Some(PackageManager::Pacman))
+        );
+        assert!(manager_arg(args("--manager emerge --doctor").into_iter())
+            .unwrap_err()
+            .contains("'emerge'"));
+        assert!(manager_arg(args("--doctor --manager").into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_binary_for() {
+        assert_eq!(binary_for(PackageManager::Paru), "paru");
+        assert_eq!(binary_for(PackageManager::NixOS), "nixos-rebuild");
+        assert_eq!(binary_for(PackageManager::NixProfile), "nix");
+        assert_eq!(
+            binary_for(PackageManager::TransactionalUpdate),
+            "transactional-update"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Export of the current update list for inventory and reporting.
+//!
+//! The applet has no file dialog, so the list is always written to
+//! [`crate::paths::export_path`] as pretty-printed JSON.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::package_manager::{PackageManager, UpdateInfo};
+
+/// Top-level structure of the exported file
+#[derive(Serialize)]
+struct UpdateExport<'a> {
+    /// Unix timestamp of the export
+    exported_at: u64,
+    package_manager: &'a str,
+    #[serde(flatten)]
+    updates: &'a UpdateInfo,
+}
+
+/// Write the update list to the export path, returning where it was written
+pub fn export_updates(package_manager: PackageManager, info: &UpdateInfo) -> Result<PathBuf> {
+    let timestamp = crate::check_log::unix_timestamp();
+    let path = crate::paths::export_path();
+    let json = export_json(timestamp, package_manager, info)?;
+    crate::paths::write_atomic(&path, json.as_bytes())?;
+    Ok(path)
+}
+
+fn export_json(
+    timestamp: u64,
+    package_manager: PackageManager,
+    info: &UpdateInfo,
+) -> Result<String> {
+    let export = UpdateExport {
+        exported_at: timestamp,
+        package_manager: package_manager.name(),
+        updates: info,
+    };
+    Ok(serde_json::to_string_pretty(&export)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package_manager::PackageUpdate;
+
+    #[test]
+    fn test_export_json_structure() {
+        let mut info = UpdateInfo::new();
+        info.packages.push(PackageUpdate {
+            name: "curl".to_string(),
+            current_version: "8.4.0-1".to_string(),
+            new_version: "8.5.0-1".to_string(),
+            is_aur: false,
+            is_security: true,
+            ..Default::default()
+        });
+        info.total_updates = 1;
+        info.official_updates = 1;
+
+        let json = export_json(1_700_000_000, PackageManager::Pacman, &info).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["exported_at"], 1_700_000_000);
+        assert_eq!(value["package_manager"], "pacman");
+        assert_eq!(value["total_updates"], 1);
+        assert_eq!(value["packages"][0]["name"], "curl");
+        assert_eq!(value["packages"][0]["new_version"], "8.5.0-1");
+        assert_eq!(value["packages"][0]["is_security"], true);
+    }
+}
@@ -0,0 +1,169 @@
+//! Parser regression corpus: package manager output in each tool's format,
+//! replayed through the full check pipeline.
+//!
+//! Each directory under `tests/fixtures` is one case. Its `case.json` names
+//! the package manager, the command lines it answers and the expected result;
+//! the output lives next to it and is referenced by file name, so adding
+//! a case needs no code. See `tests/fixtures/README.md`.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::config::{NixOSConfig, NixOSMode};
+use crate::package_manager::{PackageManager, UpdateChecker, UpdateInfo};
+use crate::runner::MockRunner;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    manager: PackageManager,
+    responses: Vec<Response>,
+    expect: Expect,
+}
+
+/// Canned result of one command line
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Response {
+    command: String,
+    #[serde(default)]
+    exit: i32,
+    /// File holding the stdout
+    stdout: Option<String>,
+    /// File holding the stderr
+    stderr: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expect {
+    total: usize,
+    #[serde(default)]
+    aur: usize,
+    #[serde(default)]
+    security: usize,
+    #[serde(default)]
+    held_back: usize,
+    #[serde(default)]
+    phased: usize,
+    /// Entries that must be listed, by name
+    #[serde(default)]
+    packages: Vec<ExpectedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectedPackage {
+    name: String,
+    current: String,
+    new: String,
+}
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+fn read_capture(dir: &Path, file: Option<&str>) -> Result<String, String> {
+    match file {
+        Some(file) => std::fs::read_to_string(dir.join(file))
+            .map_err(|e| format!("cannot read {}: {}", file, e)),
+        None => Ok(String::new()),
+    }
+}
+
+/// Run one case, describing the first mismatch
+async fn run_case(dir: &Path) -> Result<(), String> {
+    let json = std::fs::read_to_string(dir.join("case.json"))
+        .map_err(|e| format!("cannot read case.json: {}", e))?;
+    let case: Case =
+        serde_json::from_str(&json).map_err(|e| format!("invalid case.json: {}", e))?;
+
+    let mut runner = MockRunner::new();
+    for response in &case.responses {
+        runner = runner.respond_full(
+            &response.command,
+            response.exit,
+            &read_capture(dir, response.stdout.as_deref())?,
+            &read_capture(dir, response.stderr.as_deref())?,
+        );
+    }
+
+    let nixos_config = NixOSConfig {
+        mode: NixOSMode::Channels,
+        home_manager_path: Some("/nonexistent/home-manager".to_string()),
+        ..Default::default()
+    };
+    let info = UpdateChecker::new(case.manager)
+        .with_runner(runner)
+        .check_updates(true, &nixos_config)
+        .await
+        .map_err(|e| format!("check failed: {}", e))?;
+
+    check_expectations(&case.expect, &info)
+}
+
+fn check_expectations(expect: &Expect, info: &UpdateInfo) -> Result<(), String> {
+    let counts = [
+        ("total", expect.total, info.total_updates),
+        ("aur", expect.aur, info.aur_updates),
+        ("security", expect.security, info.security_updates),
+        ("held_back", expect.held_back, info.held_back.len()),
+        (
+            "phased",
+            expect.phased,
+            info.packages.iter().filter(|p| p.phased).count(),
+        ),
+    ];
+    for (what, expected, actual) in counts {
+        if expected != actual {
+            return Err(format!("{}: expected {}, got {}", what, expected, actual));
+        }
+    }
+
+    for expected in &expect.packages {
+        let package = info
+            .packages
+            .iter()
+            .find(|p| p.name == expected.name)
+            .ok_or_else(|| format!("{} is not listed", expected.name))?;
+        if package.current_version != expected.current || package.new_version != expected.new {
+            return Err(format!(
+                "{}: expected {} -> {}, got {} -> {}",
+                expected.name,
+                expected.current,
+                expected.new,
+                package.current_version,
+                package.new_version
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fixture_corpus() {
+    let mut cases: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .expect("tests/fixtures is missing")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no fixtures found");
+
+    let mut failures = Vec::new();
+    for dir in &cases {
+        if let Err(e) = run_case(dir).await {
+            let name = dir.file_name().unwrap_or_default().to_string_lossy();
+            failures.push(format!("{}: {}", name, e));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "fixture failures:\n{}",
+        failures.join("\n")
+    );
+}
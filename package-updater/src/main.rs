@@ -1,11 +1,40 @@
 mod app;
+mod change_summary;
+mod check_log;
 mod config;
+mod custom_groups;
+mod doctor;
+mod export;
+#[cfg(test)]
+mod fixture_tests;
+mod notifications;
 mod package_manager;
 mod paths;
 mod polkit;
+mod power;
+mod runner;
+mod terminal;
+mod time_ago;
+mod version;
 
 use app::CosmicAppletPackageUpdater;
 
 fn main() -> cosmic::iced::Result {
+    if std::env::args().skip(1).any(|arg| arg == "--doctor") {
+        let manager = match doctor::manager_arg(std::env::args().skip(1)) {
+            Ok(manager) => manager,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        };
+        doctor::run(manager);
+        return Ok(());
+    }
+    if std::env::args().skip(1).any(|arg| arg == "--print-polkit-policy") {
+        print!("{}", polkit::POLICY_FILE);
+        return Ok(());
+    }
+
     cosmic::applet::run::<CosmicAppletPackageUpdater>(())
 }
@@ -0,0 +1,264 @@
+/// Desktop notifications through the freedesktop notification service
+///
+/// Notifications are sent over the session D-Bus to
+/// `org.freedesktop.Notifications`, which the COSMIC notification daemon
+/// implements. Failures are never fatal; callers only log them.
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::time::Duration;
+use zbus::{zvariant, Connection};
+
+use crate::config::NotificationUrgency;
+
+/// Application name reported to the notification daemon
+const APP_NAME: &str = "Package Updater";
+
+/// Icon shown next to notifications
+const APP_ICON: &str = "software-update-available-symbolic";
+
+/// Let the notification daemon pick the expiration timeout
+const DEFAULT_EXPIRE_TIMEOUT: i32 = -1;
+
+/// Stop waiting for an action on notifications the daemon never closes
+const ACTION_WAIT_TIMEOUT_SECS: u64 = 30 * 60;
+
+/// Action key of the "Update now" button
+pub const ACTION_UPDATE: &str = "update";
+
+/// Send a desktop notification
+///
+/// # Returns
+///
+/// The notification ID assigned by the daemon
+///
+/// # Errors
+///
+/// Returns an error if the session bus or the notification service is unavailable
+pub async fn send(summary: &str, body: &str, urgency: NotificationUrgency) -> Result<u32> {
+    let proxy = proxy().await?;
+    notify(&proxy, summary, body, Vec::new(), urgency).await
+}
+
+/// Send a notification with one action button and wait until it is used
+///
+/// # Returns
+///
+/// `true` when the user invoked the action. `false` when the notification
+/// was closed or expired, or when the daemon does not support actions, in
+/// which case the notification is sent without the button.
+///
+/// # Errors
+///
+/// Returns an error if the session bus or the notification service is unavailable
+pub async fn send_with_action(
+    summary: &str,
+    body: &str,
+    action_key: &str,
+    action_label: &str,
+    urgency: NotificationUrgency,
+) -> Result<bool> {
+    let proxy = proxy().await?;
+
+    let capabilities: Vec<String> = proxy.call("GetCapabilities", &()).await.unwrap_or_default();
+    if !capabilities.iter().any(|c| c == "actions") {
+        notify(&proxy, summary, body, Vec::new(), urgency).await?;
+        return Ok(false);
+    }
+
+    // Subscribe before sending so a quick click is not missed
+    let mut invoked = proxy.receive_signal("ActionInvoked").await?;
+    let mut closed = proxy.receive_signal("NotificationClosed").await?;
+    let id = notify(
+        &proxy,
+        summary,
+        body,
+        vec![action_key, action_label],
+        urgency,
+    )
+    .await?;
+
+    let wait = async {
+        loop {
+            tokio::select! {
+                Some(message) = invoked.next() => {
+                    if let Ok((signal_id, key)) = message.body().deserialize::<(u32, String)>() {
+                        if signal_id == id && key == action_key {
+                            return true;
+                        }
+                    }
+                }
+                Some(message) = closed.next() => {
+                    if let Ok((signal_id, _reason)) = message.body().deserialize::<(u32, u32)>() {
+                        if signal_id == id {
+                            return false;
+                        }
+                    }
+                }
+                else => return false,
+            }
+        }
+    };
+
+    Ok(tokio::time::timeout(Duration::from_secs(ACTION_WAIT_TIMEOUT_SECS), wait)
+        .await
+        .unwrap_or(false))
+}
+
+async fn proxy() -> Result<zbus::Proxy<'static>> {
+    let connection = Connection::session()
+        .await
+        .map_err(|e| anyhow!("Failed to connect to session D-Bus: {}", e))?;
+
+    Ok(zbus::Proxy::new(
+        &connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )
+    .await?)
+}
+
+/// Call `Notify`; `actions` alternates action keys and labels
+async fn notify(
+    proxy: &zbus::Proxy<'_>,
+    summary: &str,
+    body: &str,
+    actions: Vec<&str>,
+    urgency: NotificationUrgency,
+) -> Result<u32> {
+    let mut hints: HashMap<&str, zvariant::Value> = HashMap::new();
+    hints.insert("urgency", zvariant::Value::from(urgency_hint(urgency)));
+
+    let id: u32 = proxy
+        .call(
+            "Notify",
+            &(
+                APP_NAME,
+                0u32,
+                APP_ICON,
+                summary,
+                body,
+                actions,
+                hints,
+                DEFAULT_EXPIRE_TIMEOUT,
+            ),
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to send notification: {}", e))?;
+
+    Ok(id)
+}
+
+/// Value of the `urgency` hint (0 low, 1 normal, 2 critical)
+fn urgency_hint(urgency: NotificationUrgency) -> u8 {
+    match urgency {
+        NotificationUrgency::Low => 0,
+        NotificationUrgency::Normal => 1,
+        NotificationUrgency::Critical => 2,
+    }
+}
+
+/// Urgency of the notification about available updates: critical while
+/// security updates are pending, at least normal from `threshold` updates
+/// (0 disables), otherwise the configured default
+pub fn updates_available_urgency(
+    default: NotificationUrgency,
+    security: usize,
+    total: usize,
+    threshold: usize,
+) -> NotificationUrgency {
+    if security > 0 {
+        NotificationUrgency::Critical
+    } else if threshold > 0 && total >= threshold {
+        default.max(NotificationUrgency::Normal)
+    } else {
+        default
+    }
+}
+
+/// Build the summary and body of the notification about available updates
+pub fn updates_available_message(total: usize, new: usize) -> (String, String) {
+    let plural = if total == 1 { "" } else { "s" };
+    let summary = format!("{} update{} available", total, plural);
+    let body = if new > 0 && new < total {
+        format!("{} new since the last check", new)
+    } else {
+        "Select \"Update now\" to install them in a terminal".to_string()
+    };
+    (summary, body)
+}
+
+/// Build the summary and body of the notification shown after a terminal update
+///
+/// Returns `None` when the update did not reduce the number of pending updates.
+pub fn post_update_message(before: usize, after: usize) -> Option<(String, String)> {
+    if after >= before {
+        return None;
+    }
+
+    let applied = before - after;
+    let plural = if applied == 1 { "" } else { "s" };
+
+    if after == 0 {
+        Some((
+            "System updated — now up to date".to_string(),
+            format!("Applied {} update{}", applied, plural),
+        ))
+    } else {
+        Some((
+            "System updated".to_string(),
+            format!("Applied {} update{}, {} remaining", applied, plural, after),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_available_message() {
+        let (summary, body) = updates_available_message(1, 1);
+        assert_eq!(summary, "1 update available");
+        assert!(body.contains("Update now"));
+
+        let (summary, body) = updates_available_message(7, 2);
+        assert_eq!(summary, "7 updates available");
+        assert_eq!(body, "2 new since the last check");
+    }
+
+    #[test]
+    fn test_updates_available_urgency() {
+        use NotificationUrgency::*;
+
+        assert_eq!(updates_available_urgency(Low, 0, 3, 20), Low);
+        assert_eq!(updates_available_urgency(Low, 0, 20, 20), Normal);
+        assert_eq!(updates_available_urgency(Low, 1, 3, 20), Critical);
+        assert_eq!(updates_available_urgency(Critical, 0, 3, 20), Critical);
+        // A zero threshold never escalates
+        assert_eq!(updates_available_urgency(Low, 0, 500, 0), Low);
+        assert_eq!(urgency_hint(Critical), 2);
+    }
+
+    #[test]
+    fn test_post_update_message_up_to_date() {
+        let (summary, body) = post_update_message(12, 0).unwrap();
+        assert_eq!(summary, "System updated — now up to date");
+        assert_eq!(body, "Applied 12 updates");
+    }
+
+    #[test]
+    fn test_post_update_message_partial() {
+        let (summary, body) = post_update_message(5, 4).unwrap();
+        assert_eq!(summary, "System updated");
+        assert_eq!(body, "Applied 1 update, 4 remaining");
+    }
+
+    #[test]
+    fn test_post_update_message_no_change() {
+        assert!(post_update_message(3, 3).is_none());
+        assert!(post_update_message(0, 0).is_none());
+        assert!(post_update_message(2, 5).is_none());
+    }
+}
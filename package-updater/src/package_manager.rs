@@ -1,31 +1,56 @@
 use anyhow::{anyhow, Result};
+use futures::{Stream, StreamExt};
 #[allow(deprecated)]
 use nix::fcntl::{flock, FlockArg};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::LazyLock;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 use std::process::Command;
+use std::time::{Duration, Instant};
 use tokio::process::Command as TokioCommand;
 
+use crate::config::{AurCheckBackend, NixOSUpdateCount};
+use crate::runner::{CommandRunner, ProcessRunner};
+use crate::version::{self, VersionScheme};
+
 // Retry and timing constants
-const LOCK_RETRY_DELAY_SECS: u64 = 2;
+const LOCK_BACKOFF_INITIAL_MS: u64 = 250;
+const LOCK_BACKOFF_MAX_MS: u64 = 2000;
 const UPDATE_RETRY_DELAY_SECS: u64 = 1;
 
+/// AUR RPC `info` endpoint; packages are appended as `arg[]` parameters
+const AUR_RPC_INFO_URL: &str = "https://aur.archlinux.org/rpc/v5/info?";
+/// Longest RPC request URL; the AUR rejects request URIs over 4443 bytes
+const AUR_RPC_MAX_URL_LEN: usize = 4000;
+
 // Compiled regex patterns for NixOS flake parsing
 static FLAKE_UPDATE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r#"(?:Updated|updated|updating|Will update)\s+(?:input\s+)?['"]?([^\s':]+)['"]?:?\s+['"]?([^'"]+)['"]?\s+(?:->|→|to)\s+['"]?([^'"]+)['"]?"#).unwrap()
 });
 
+// `nix profile upgrade` entry: upgrading '<attr path>' from flake '<old>' to '<new>'
+static NIX_PROFILE_UPGRADE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"upgrading '([^']+)' from flake '([^']+)' to '([^']+)'").unwrap()
+});
+
+// Terminal escape sequences: CSI (colors, cursor movement), OSC (titles,
+// hyperlinks) and two-byte escapes
+static ANSI_ESCAPE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[@-Z\\-_])").unwrap()
+});
+
 /// Package manager types supported by the updater applet.
 ///
 /// Each variant represents a different Linux package manager or distribution
 /// package management system. The enum is marked `#[non_exhaustive]` to allow
 /// adding new package managers without breaking existing code.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum PackageManager {
     // Arch Linux
@@ -34,16 +59,27 @@ pub enum PackageManager {
     Yay,
     // Debian/Ubuntu
     Apt,
+    Nala,
     // Fedora/RHEL
     Dnf,
     // openSUSE/SUSE
     Zypper,
+    // openSUSE MicroOS / Aeon (atomic zypper)
+    TransactionalUpdate,
     // Alpine Linux
     Apk,
+    // Solus
+    Eopkg,
     // Universal
     Flatpak,
     // NixOS
     NixOS,
+    // Nix user profile on a non-NixOS distribution
+    NixProfile,
+    // home-manager user environment
+    HomeManager,
+    // GNU Guix user profile, on Guix System or another distribution
+    Guix,
 }
 
 impl PackageManager {
@@ -53,11 +89,17 @@ impl PackageManager {
             PackageManager::Paru => "paru",
             PackageManager::Yay => "yay",
             PackageManager::Apt => "apt",
+            PackageManager::Nala => "nala",
             PackageManager::Dnf => "dnf",
             PackageManager::Zypper => "zypper",
+            PackageManager::TransactionalUpdate => "transactional-update",
             PackageManager::Apk => "apk",
+            PackageManager::Eopkg => "eopkg",
             PackageManager::Flatpak => "flatpak",
             PackageManager::NixOS => "nixos",
+            PackageManager::NixProfile => "nix-profile",
+            PackageManager::HomeManager => "home-manager",
+            PackageManager::Guix => "guix",
         }
     }
 
@@ -65,6 +107,106 @@ impl PackageManager {
         matches!(self, PackageManager::Paru | PackageManager::Yay)
     }
 
+    /// Whether the update listing is plain text that a custom check command
+    /// can stand in for (not Flatpak, the Nix family or Guix)
+    pub fn supports_custom_check_command(&self) -> bool {
+        !matches!(
+            self,
+            PackageManager::Flatpak
+                | PackageManager::NixOS
+                | PackageManager::NixProfile
+                | PackageManager::HomeManager
+                | PackageManager::Guix
+        )
+    }
+
+    /// Web page of a package on archlinux.org, or on the AUR for AUR rows.
+    /// `None` outside the Arch family.
+    pub fn package_page_url(&self, name: &str, is_aur: bool) -> Option<String> {
+        match self {
+            PackageManager::Paru | PackageManager::Yay if is_aur => {
+                Some(format!("https://aur.archlinux.org/packages/{}", name))
+            }
+            PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay => Some(format!(
+                "https://archlinux.org/packages/?q={}",
+                // `+` would read as a space in the query
+                name.replace('+', "%2B")
+            )),
+            _ => None,
+        }
+    }
+
+    /// Every variant, in declaration order
+    pub const ALL: &'static [PackageManager] = &[
+        PackageManager::Pacman,
+        PackageManager::Paru,
+        PackageManager::Yay,
+        PackageManager::Apt,
+        PackageManager::Nala,
+        PackageManager::Dnf,
+        PackageManager::Zypper,
+        PackageManager::TransactionalUpdate,
+        PackageManager::Apk,
+        PackageManager::Eopkg,
+        PackageManager::Flatpak,
+        PackageManager::NixOS,
+        PackageManager::NixProfile,
+        PackageManager::HomeManager,
+        PackageManager::Guix,
+    ];
+
+    /// Look up a package manager by its [`name`](Self::name)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|pm| pm.name() == name)
+    }
+
+    /// Whether a check can run on a remote host over SSH; NixOS and
+    /// home-manager checks read configuration paths on this machine
+    pub fn supports_remote_check(&self) -> bool {
+        !matches!(self, PackageManager::NixOS | PackageManager::HomeManager)
+    }
+
+    /// Exit code of this manager's update listing when nothing needs
+    /// updating: `checkupdates` exits 2, `paru`/`yay -Qu` exit 1
+    pub fn no_updates_exit_code(&self) -> Option<i32> {
+        match self {
+            PackageManager::Pacman => Some(2),
+            PackageManager::Paru | PackageManager::Yay => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Non-zero exit code of this manager's update listing that means
+    /// updates were listed: `dnf check-update` exits 100
+    pub fn updates_available_exit_code(&self) -> Option<i32> {
+        match self {
+            PackageManager::Dnf => Some(100),
+            _ => None,
+        }
+    }
+
+    /// Database of installed packages that changes whenever packages are
+    /// installed or upgraded, if there is a single one to watch
+    pub fn database_path(&self) -> Option<&'static str> {
+        match self {
+            PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay => {
+                Some("/var/lib/pacman/local")
+            }
+            PackageManager::Apt | PackageManager::Nala => Some("/var/lib/dpkg/status"),
+            PackageManager::Dnf | PackageManager::Zypper => Some("/var/lib/rpm"),
+            PackageManager::Apk => Some("/lib/apk/db/installed"),
+            // One metadata directory per installed package
+            PackageManager::Eopkg => Some("/var/lib/eopkg/package"),
+            // Updates land in a new snapshot or store path, not a shared database
+            PackageManager::TransactionalUpdate
+            | PackageManager::Flatpak
+            | PackageManager::NixOS
+            | PackageManager::NixProfile
+            | PackageManager::HomeManager
+            | PackageManager::Guix => None,
+        }
+    }
+
     /// Get the system update command for this package manager.
     ///
     /// # Arguments
@@ -79,10 +221,34 @@ impl PackageManager {
             PackageManager::Paru => "paru -Syu".to_string(),
             PackageManager::Yay => "yay -Syu".to_string(),
             PackageManager::Apt => "sudo apt update && sudo apt upgrade".to_string(),
+            PackageManager::Nala => "sudo nala upgrade".to_string(),
             PackageManager::Dnf => "sudo dnf upgrade".to_string(),
             PackageManager::Zypper => "sudo zypper update".to_string(),
+            PackageManager::TransactionalUpdate => "sudo transactional-update dup".to_string(),
             PackageManager::Apk => "sudo apk upgrade".to_string(),
+            PackageManager::Eopkg => "sudo eopkg upgrade".to_string(),
             PackageManager::Flatpak => "flatpak update".to_string(),
+            PackageManager::NixProfile => "nix profile upgrade '.*'".to_string(),
+            // The check covers the user profile; Guix System also reconfigures
+            PackageManager::Guix => {
+                if is_guix_system() {
+                    "guix pull && guix package -u && sudo guix system reconfigure /etc/config.scm"
+                        .to_string()
+                } else {
+                    "guix pull && guix package -u".to_string()
+                }
+            }
+            PackageManager::HomeManager => {
+                let config = nixos_config.cloned().unwrap_or_default();
+                if config.home_manager_uses_flake() {
+                    format!(
+                        "cd {} && nix flake update && home-manager switch --flake .",
+                        config.home_manager_dir()
+                    )
+                } else {
+                    "nix-channel --update && home-manager switch".to_string()
+                }
+            }
             PackageManager::NixOS => {
                 if let Some(config) = nixos_config {
                     match config.mode {
@@ -112,6 +278,32 @@ impl PackageManager {
             }
         }
     }
+
+    /// Command that installs all updates without prompting, run as root.
+    ///
+    /// `None` for managers that must not run as root (AUR helpers, Flatpak,
+    /// Nix user environments) or that need the interactive terminal flow (NixOS).
+    pub fn noninteractive_update_command(&self) -> Option<(&'static str, &'static [&'static str])> {
+        match self {
+            PackageManager::Pacman => Some(("pacman", &["-Syu", "--noconfirm"])),
+            PackageManager::Apt => Some(("apt-get", &["-y", "upgrade"])),
+            PackageManager::Nala => Some(("nala", &["upgrade", "-y"])),
+            PackageManager::Dnf => Some(("dnf", &["upgrade", "-y"])),
+            PackageManager::Zypper => Some(("zypper", &["--non-interactive", "update"])),
+            PackageManager::TransactionalUpdate => {
+                Some(("transactional-update", &["--non-interactive", "dup"]))
+            }
+            PackageManager::Apk => Some(("apk", &["upgrade"])),
+            PackageManager::Eopkg => Some(("eopkg", &["upgrade", "-y"])),
+            PackageManager::Paru
+            | PackageManager::Yay
+            | PackageManager::Flatpak
+            | PackageManager::NixOS
+            | PackageManager::NixProfile
+            | PackageManager::HomeManager
+            | PackageManager::Guix => None,
+        }
+    }
 }
 
 impl std::fmt::Display for PackageManager {
@@ -123,7 +315,7 @@ impl std::fmt::Display for PackageManager {
 /// Summary of available package updates.
 ///
 /// Contains counts of updates by type and a list of individual package updates.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct UpdateInfo {
     /// Total number of updates available
     pub total_updates: usize,
@@ -131,15 +323,230 @@ pub struct UpdateInfo {
     pub official_updates: usize,
     /// Number of AUR (Arch User Repository) updates
     pub aur_updates: usize,
+    /// Number of security updates (apt/dnf only)
+    pub security_updates: usize,
     /// Detailed list of package updates
     pub packages: Vec<PackageUpdate>,
+    /// Non-fatal problems, e.g. a Flatpak remote that could not be reached
+    pub warnings: Vec<String>,
+    /// Installed updates only take effect after a reboot (transactional-update)
+    pub reboot_required: bool,
+    /// Updates `apt upgrade` keeps back because they need `dist-upgrade`;
+    /// not part of `packages` or the counts
+    pub held_back: Vec<PackageUpdate>,
+    /// Installed packages not found in any repository (AUR helpers, when enabled)
+    pub foreign_packages: Option<usize>,
+    /// Installed AUR packages that depend on an updated official package
+    pub rebuild_hints: Vec<RebuildHint>,
+    /// Sum of the known package download sizes in bytes
+    pub total_download_size: Option<u64>,
+    /// The running kernel is older than the newest installed one
+    pub kernel_mismatch: Option<KernelMismatch>,
+    /// Updates were listed from cached repository metadata rather than
+    /// metadata refreshed for this check, e.g. apt's lists from the last
+    /// `apt update`
+    pub cached_metadata: bool,
+}
+
+/// An installed AUR package that should be rebuilt after the pending updates,
+/// e.g. because a library it links against changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RebuildHint {
+    /// The AUR package
+    pub package: String,
+    /// Its dependencies among the pending official updates
+    pub changed_dependencies: Vec<String>,
+}
+
+/// A kernel update installed since boot, which needs a reboot to take effect
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KernelMismatch {
+    /// `uname -r`
+    pub running: String,
+    /// Release of the newest installed kernel of the same flavor
+    pub installed: String,
+}
+
+/// Kernel packages checked on Arch; each is installed in one version only
+const PACMAN_KERNELS: &[&str] = &["linux", "linux-lts", "linux-zen", "linux-hardened"];
+
+/// Reason an update check failed.
+///
+/// Returned by [`UpdateChecker::check_updates`] so callers can react to the
+/// kind of failure instead of matching on error text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckError {
+    /// Another instance holds the check lock
+    LockBusy,
+    /// The package manager command (or a tool it needs) is not installed
+    CommandNotFound(String),
+    /// Missing privileges; carries instructions for fixing the setup
+    PermissionDenied(String),
+    /// The package manager did not answer in time
+    Timeout,
+    /// The command failed and its output could not be understood
+    ParseError(String),
+    /// Any other failure
+    Other(String),
+}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckError::LockBusy => write!(f, "Another update check is already in progress"),
+            CheckError::CommandNotFound(what) => write!(f, "Command not found: {}", what),
+            CheckError::PermissionDenied(details) => write!(f, "Permission denied. {}", details),
+            CheckError::Timeout => write!(f, "The update check timed out"),
+            CheckError::ParseError(details) => {
+                write!(f, "Could not read package manager output: {}", details)
+            }
+            CheckError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+impl From<anyhow::Error> for CheckError {
+    fn from(error: anyhow::Error) -> Self {
+        CheckError::of(&error)
+    }
+}
+
+impl CheckError {
+    /// Recover the failure kind from an error raised inside the checker
+    fn of(error: &anyhow::Error) -> Self {
+        if let Some(check_error) = error.downcast_ref::<CheckError>() {
+            return check_error.clone();
+        }
+
+        if let Some(io_error) = error.downcast_ref::<std::io::Error>() {
+            match io_error.kind() {
+                std::io::ErrorKind::NotFound => {
+                    return CheckError::CommandNotFound(io_error.to_string())
+                }
+                std::io::ErrorKind::PermissionDenied => {
+                    return CheckError::PermissionDenied(io_error.to_string())
+                }
+                std::io::ErrorKind::TimedOut => return CheckError::Timeout,
+                _ => {}
+            }
+        }
+
+        CheckError::Other(error.to_string())
+    }
+}
+
+/// Whether `/etc/os-release` describes Guix System
+fn is_guix_os_release(os_release: &str) -> bool {
+    os_release
+        .lines()
+        .any(|line| line.trim() == "ID=guix" || line.trim() == "ID=\"guix\"")
+}
+
+/// Whether this machine runs Guix System rather than Guix on another distribution
+fn is_guix_system() -> bool {
+    std::fs::read_to_string("/etc/os-release")
+        .is_ok_and(|os_release| is_guix_os_release(&os_release))
+}
+
+/// Whether Guix System was reconfigured since boot: `/run/booted-system` and
+/// `/run/current-system` point to different system generations
+fn guix_reboot_pending() -> bool {
+    match (
+        std::fs::canonicalize("/run/booted-system"),
+        std::fs::canonicalize("/run/current-system"),
+    ) {
+        (Ok(booted), Ok(current)) => booted != current,
+        _ => false,
+    }
+}
+
+/// Whether `/etc/os-release` describes an atomic openSUSE (MicroOS, Aeon, Kalpa)
+fn is_transactional_os_release(os_release: &str) -> bool {
+    os_release
+        .lines()
+        .filter(|line| {
+            line.starts_with("NAME=") || line.starts_with("ID=") || line.starts_with("VARIANT_ID=")
+        })
+        .any(|line| {
+            let value = line.to_ascii_lowercase();
+            ["microos", "aeon", "kalpa"]
+                .iter()
+                .any(|variant| value.contains(variant))
+        })
+}
+
+/// Marker written by transactional-update when a new snapshot awaits a reboot
+const REBOOT_NEEDED_PATH: &str = "/run/reboot-needed";
+
+/// Columns requested from `flatpak remote-ls --updates`, in this order, so the
+/// output does not depend on the flatpak version's defaults
+const FLATPAK_UPDATE_COLUMNS: &str = "--columns=application,version,branch,origin";
+
+/// Event reported while an update check runs.
+#[derive(Debug, Clone)]
+pub enum CheckProgress {
+    /// The official repositories are being checked
+    StartedOfficial,
+    /// The AUR is being checked
+    StartedAur,
+    /// An update was found
+    FoundPackage(PackageUpdate),
+    /// The check completed
+    Finished(UpdateInfo),
+    /// The check failed
+    Failed(CheckError),
+    /// A command the check ran, as a shell-style command line
+    RanCommand(String),
+}
+
+/// Wrap a failure to spawn `cmd`, naming the command when it is missing
+fn spawn_error(cmd: &str, error: std::io::Error) -> anyhow::Error {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => CheckError::CommandNotFound(cmd.to_string()).into(),
+        _ => error.into(),
+    }
+}
+
+/// Flavor suffix of a kernel release, e.g. `generic` in `6.8.0-45-generic`
+/// or `lts` in `6.6.30-1-lts`; empty for a plain build
+fn kernel_flavor(release: &str) -> &str {
+    match release.rsplit_once('-') {
+        Some((_, last))
+            if last.chars().any(|c| c.is_ascii_alphabetic())
+                && last.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            last
+        }
+        _ => "",
+    }
+}
+
+/// Strip what a terminal would interpret from command output: escape
+/// sequences are removed, a line redrawn with `\r` keeps only its final
+/// text, and other control characters and invalid UTF-8 are dropped
+fn clean_terminal_output(output: &str) -> String {
+    let stripped = ANSI_ESCAPE_REGEX.replace_all(output, "");
+    stripped
+        .split('\n')
+        .map(|line| {
+            let line = line.trim_end_matches('\r');
+            let shown = line.rsplit('\r').next().unwrap_or(line);
+            shown
+                .chars()
+                .filter(|c| (!c.is_control() || *c == '\t') && *c != char::REPLACEMENT_CHARACTER)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// Information about a single package update.
 ///
 /// Represents an available update for one package, including version information
 /// and whether it's from the AUR (for Arch-based systems).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct PackageUpdate {
     /// Package name
     pub name: String,
@@ -149,6 +556,15 @@ pub struct PackageUpdate {
     pub new_version: String,
     /// Whether this is an AUR package (Arch Linux only)
     pub is_aur: bool,
+    /// Whether this update fixes a security issue (apt/dnf only)
+    pub is_security: bool,
+    /// Download size in bytes (pacman-based only, when enabled)
+    pub download_size: Option<u64>,
+    /// Deferred by Ubuntu's phased updates, so `apt upgrade` may skip it
+    /// for now (apt only)
+    pub phased: bool,
+    /// Remote the update comes from, e.g. "flathub" (Flatpak only)
+    pub origin: Option<String>,
 }
 
 impl UpdateInfo {
@@ -157,13 +573,374 @@ impl UpdateInfo {
             total_updates: 0,
             official_updates: 0,
             aur_updates: 0,
+            security_updates: 0,
             packages: Vec::new(),
+            warnings: Vec::new(),
+            reboot_required: false,
+            held_back: Vec::new(),
+            foreign_packages: None,
+            rebuild_hints: Vec::new(),
+            total_download_size: None,
+            kernel_mismatch: None,
+            cached_metadata: false,
         }
     }
 
     pub fn has_updates(&self) -> bool {
         self.total_updates > 0
     }
+
+    /// Drop the updates of the named packages and recount, returning the
+    /// dropped updates
+    pub fn remove_packages(&mut self, names: &[String]) -> Vec<PackageUpdate> {
+        if names.is_empty() {
+            return Vec::new();
+        }
+        let (removed, kept) = std::mem::take(&mut self.packages)
+            .into_iter()
+            .partition(|p| names.contains(&p.name));
+        self.packages = kept;
+        self.recount();
+        removed
+    }
+
+    /// Look up the pending update for a package by name
+    pub fn find_package(&self, name: &str) -> Option<&PackageUpdate> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+
+    /// Whether an update is pending for the named package
+    pub fn has_update_for(&self, name: &str) -> bool {
+        self.find_package(name).is_some()
+    }
+
+    /// Put back updates dropped by `remove_packages` and recount
+    pub fn restore_packages(&mut self, packages: Vec<PackageUpdate>) {
+        self.packages.extend(packages);
+        self.recount();
+    }
+
+    fn recount(&mut self) {
+        self.total_updates = self.packages.len();
+        self.aur_updates = self.packages.iter().filter(|p| p.is_aur).count();
+        self.official_updates = self.total_updates - self.aur_updates;
+        self.security_updates = self.packages.iter().filter(|p| p.is_security).count();
+        if self.total_download_size.is_some() {
+            self.total_download_size =
+                Some(self.packages.iter().filter_map(|p| p.download_size).sum());
+        }
+    }
+}
+
+/// Directory prefixes that package manager binaries may be executed from.
+///
+/// All of these are only writable by root (Linuxbrew uses a dedicated
+/// `linuxbrew` user), so a binary dropped into `/tmp` or an arbitrary home
+/// directory is never picked up. User profiles (`~/.nix-profile`,
+/// `/nix/var/nix/profiles/per-user`, `/var/guix/profiles/per-user`) are
+/// writable by their owner and are deliberately missing: their entries are
+/// accepted through the store path they resolve to.
+const SAFE_EXECUTABLE_PREFIXES: &[&str] = &[
+    "/usr/",
+    "/bin/",
+    "/sbin/",
+    "/opt/",
+    "/nix/store/",
+    "/run/current-system/",
+    "/etc/profiles/per-user/",
+    "/home/linuxbrew/.linuxbrew/",
+    "/gnu/store/",
+];
+
+/// Check a path against the safe prefixes
+fn has_safe_prefix(path: &str) -> bool {
+    // Reject relative paths and any attempt to climb out of a safe prefix
+    if !path.starts_with('/') || path.split('/').any(|segment| segment == "..") {
+        return false;
+    }
+
+    SAFE_EXECUTABLE_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// Whether an executable found on `$PATH` lives in a trusted location.
+///
+/// A path is accepted if it is under one of [`SAFE_EXECUTABLE_PREFIXES`], or
+/// if it resolves there through symlinks, as entries of Nix and Guix
+/// profiles resolve into `/nix/store` and `/gnu/store`.
+pub fn is_safe_executable_path(path: &str) -> bool {
+    if has_safe_prefix(path) {
+        return true;
+    }
+
+    std::fs::canonicalize(path)
+        .ok()
+        .and_then(|resolved| resolved.to_str().map(has_safe_prefix))
+        .unwrap_or(false)
+}
+
+/// Usual install locations of graphical askpass helpers
+const ASKPASS_PATHS: &[&str] = &[
+    "/usr/bin/ksshaskpass",
+    "/usr/bin/lxqt-openssh-askpass",
+    "/usr/lib/ssh/ssh-askpass",
+    "/usr/libexec/openssh/gnome-ssh-askpass",
+    "/usr/libexec/openssh/ssh-askpass",
+    "/usr/lib/openssh/gnome-ssh-askpass",
+    "/usr/bin/ssh-askpass",
+    "/usr/libexec/seahorse/ssh-askpass",
+];
+
+/// Graphical password prompt for `sudo -A`: `$SUDO_ASKPASS` or
+/// `$SSH_ASKPASS` when set (NixOS sets the latter), otherwise the first
+/// helper found in the usual locations
+pub fn find_askpass() -> Option<String> {
+    let from_env = ["SUDO_ASKPASS", "SSH_ASKPASS"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok());
+    pick_askpass(from_env, |path| std::path::Path::new(path).is_file())
+}
+
+/// First candidate that exists and lives in a trusted location, since sudo
+/// hands it the password
+fn pick_askpass(
+    from_env: impl IntoIterator<Item = String>,
+    exists: impl Fn(&str) -> bool,
+) -> Option<String> {
+    from_env
+        .into_iter()
+        .filter(|path| !path.is_empty())
+        .chain(ASKPASS_PATHS.iter().map(|path| path.to_string()))
+        .find(|path| exists(path) && is_safe_executable_path(path))
+}
+
+/// Dotted app-id parts that mark a Flatpak runtime or extension rather than
+/// an app, e.g. `org.gnome.Platform` or `org.freedesktop.Platform.GL.default`
+const FLATPAK_RUNTIME_PARTS: &[&str] = &[
+    "Platform",
+    "Sdk",
+    "BaseApp",
+    "Locale",
+    "Debug",
+    "PlatformTheme",
+    "WaylandDecoration",
+    "KStyle",
+    "Gtk3theme",
+];
+
+/// Whether a Flatpak app-id names a runtime (platforms, SDKs, themes and
+/// other extensions) instead of an application
+pub fn is_flatpak_runtime(app_id: &str) -> bool {
+    app_id
+        .split('.')
+        .skip(2)
+        .any(|part| FLATPAK_RUNTIME_PARTS.contains(&part))
+}
+
+/// Whether the program a shell command line runs can be found, either as a
+/// path or on `$PATH`; leading `VAR=value` assignments are skipped
+pub async fn command_exists(command_line: &str) -> bool {
+    let Some(program) = command_line
+        .split_whitespace()
+        .find(|word| !word.contains('='))
+    else {
+        return false;
+    };
+    if program.contains('/') {
+        return tokio::fs::metadata(program)
+            .await
+            .is_ok_and(|meta| meta.is_file());
+    }
+    which_async(program).await.is_some()
+}
+
+/// PID written into the lock file by the instance that took it
+fn parse_lock_pid(contents: &str) -> Option<u32> {
+    contents.trim().parse().ok()
+}
+
+/// Whether a process with this PID is still running
+fn process_exists(pid: u32) -> bool {
+    std::path::Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+/// PID of another running process recorded in the lock file
+fn live_lock_holder(contents: &str) -> Option<u32> {
+    parse_lock_pid(contents).filter(|&pid| pid != std::process::id() && process_exists(pid))
+}
+
+/// Outcome of [`UpdateChecker::reset_lock`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockReset {
+    /// Nothing holds the lock, so there was nothing to reset
+    NotHeld,
+    /// The lock file was deleted; the next check creates a fresh one
+    Removed,
+    /// A running instance holds the lock and was left alone
+    HeldBy(u32),
+}
+
+/// Whether a failed `dnf check-update` could not download repository
+/// metadata (dnf4 and dnf5 wording), e.g. while offline
+fn is_dnf_metadata_error(message: &str) -> bool {
+    message.contains("Failed to download metadata")
+        || message.contains("Cannot download repomd.xml")
+}
+
+/// Package managers probed by detection, in order of preference
+const DETECTION_ORDER: &[PackageManager] = &[
+    // AUR helpers first (most feature-rich for Arch)
+    PackageManager::Paru,
+    PackageManager::Yay,
+    // System package managers
+    PackageManager::Pacman,
+    PackageManager::Apt,
+    // apt frontend, only picked over apt when preferred
+    PackageManager::Nala,
+    PackageManager::Dnf,
+    // Atomic openSUSE before zypper, which is present there too
+    PackageManager::TransactionalUpdate,
+    PackageManager::Zypper,
+    PackageManager::Apk,
+    PackageManager::Eopkg,
+    // NixOS
+    PackageManager::NixOS,
+    // Nix on other distributions
+    PackageManager::NixProfile,
+    PackageManager::HomeManager,
+    PackageManager::Guix,
+    // Universal package managers
+    PackageManager::Flatpak,
+];
+
+/// Path printed by `which name`, without blocking the async runtime
+async fn which_async(name: &str) -> Option<String> {
+    let output = TokioCommand::new("which").arg(name).output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Checkouts in the home directory that commonly hold a NixOS flake
+const HOME_NIXOS_CONFIG_DIRS: &[&str] = &["nixos-config", ".config/nixos", ".nixos", "nixos"];
+
+/// Directories that may hold the NixOS configuration, most specific first:
+/// the `nixos-config` entry of `NIX_PATH`, the target of a symlinked
+/// `flake.nix` in `etc_nixos`, then flakes in the usual home checkouts.
+/// `etc_nixos` itself is left to the caller's fallback, so a `NIX_PATH`
+/// entry pointing into it does not shadow the symlink target.
+///
+/// Touches the filesystem; run it off the async runtime.
+fn nixos_config_candidates(
+    nix_path: Option<&str>,
+    home: Option<&str>,
+    etc_nixos: &std::path::Path,
+) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    let nix_path_entry = nix_path
+        .into_iter()
+        .flat_map(|nix_path| nix_path.split(':'))
+        .find_map(|entry| entry.strip_prefix("nixos-config="))
+        .map(PathBuf::from)
+        .and_then(|path| {
+            if path.is_file() {
+                path.parent().map(PathBuf::from)
+            } else {
+                Some(path)
+            }
+        })
+        .filter(|dir| dir.is_dir() && !dir.starts_with(etc_nixos));
+    candidates.extend(nix_path_entry);
+
+    let linked = std::fs::canonicalize(etc_nixos.join("flake.nix"))
+        .ok()
+        .and_then(|flake| flake.parent().map(PathBuf::from))
+        .filter(|dir| !dir.starts_with(etc_nixos) && !dir.starts_with("/nix/store"));
+    candidates.extend(linked);
+
+    if let Some(home) = home.filter(|home| !home.is_empty()) {
+        candidates.extend(
+            HOME_NIXOS_CONFIG_DIRS
+                .iter()
+                .map(|dir| std::path::Path::new(home).join(dir))
+                .filter(|dir| dir.join("flake.nix").exists()),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    candidates.retain(|dir| seen.insert(dir.clone()));
+    candidates
+}
+
+/// Git revision the running system was built from, from `nixos-version
+/// --json`; only flakes that set `system.configurationRevision` record it
+fn configuration_revision(json: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let revision = value.get("configurationRevision")?.as_str()?;
+    let revision = revision.strip_suffix("-dirty").unwrap_or(revision);
+    (!revision.is_empty()).then(|| revision.to_string())
+}
+
+/// Path of `name` when it resolves to a trusted location
+async fn trusted_program(name: &str) -> Option<String> {
+    which_async(name)
+        .await
+        .filter(|path| is_safe_executable_path(path))
+}
+
+async fn git_head(git: &str, dir: &std::path::Path) -> Option<String> {
+    let output = TokioCommand::new(git)
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .await
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// What package manager detection looks at: `which` lookups and a few
+/// marker files. Blocking; the files are read from this machine unless a
+/// test stands in for it.
+trait DetectionProbe {
+    /// Path printed by `which name`
+    fn which(&self, name: &str) -> Option<String>;
+
+    fn exists(&self, path: &str) -> bool {
+        std::path::Path::new(path).exists()
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(path).ok()
+    }
+}
+
+/// Runs `which` for each lookup
+struct BlockingProbe;
+
+impl DetectionProbe for BlockingProbe {
+    fn which(&self, name: &str) -> Option<String> {
+        let output = Command::new("which").arg(name).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// `which` results looked up beforehand
+struct ResolvedProbe(HashMap<&'static str, Option<String>>);
+
+impl DetectionProbe for ResolvedProbe {
+    fn which(&self, name: &str) -> Option<String> {
+        self.0.get(name).cloned().flatten()
+    }
 }
 
 /// Detects which package managers are available on the system.
@@ -173,79 +950,99 @@ impl UpdateInfo {
 pub struct PackageManagerDetector;
 
 impl PackageManagerDetector {
+    /// Blocking detection, for the `--doctor` report
     pub fn detect_available() -> Vec<PackageManager> {
-        let mut available = Vec::new();
-
-        // Check in order of preference
-        for pm in [
-            // AUR helpers first (most feature-rich for Arch)
-            PackageManager::Paru,
-            PackageManager::Yay,
-            // System package managers
-            PackageManager::Pacman,
-            PackageManager::Apt,
-            PackageManager::Dnf,
-            PackageManager::Zypper,
-            PackageManager::Apk,
-            // NixOS
-            PackageManager::NixOS,
-            // Universal package managers
-            PackageManager::Flatpak,
-        ] {
-            if Self::is_available(pm) {
-                available.push(pm);
-            }
-        }
+        Self::detect_with(&BlockingProbe)
+    }
+
+    /// Same result as `detect_available`, with the `which` lookups run
+    /// concurrently on the async runtime and the file checks on a blocking
+    /// thread, so applet startup is not stalled
+    pub async fn detect_available_async() -> Vec<PackageManager> {
+        let names: Vec<&'static str> = DETECTION_ORDER
+            .iter()
+            .filter_map(|&pm| Self::detection_binary(pm))
+            .collect();
+        let paths = futures::future::join_all(names.iter().map(|&name| which_async(name))).await;
+        let probe = ResolvedProbe(names.into_iter().zip(paths).collect());
 
-        available
+        tokio::task::spawn_blocking(move || Self::detect_with(&probe))
+            .await
+            .unwrap_or_default()
     }
 
-    pub fn get_preferred() -> Option<PackageManager> {
-        Self::detect_available().into_iter().next()
+    /// Package managers found through `probe`, in detection order
+    fn detect_with(probe: &impl DetectionProbe) -> Vec<PackageManager> {
+        DETECTION_ORDER
+            .iter()
+            .copied()
+            .filter(|&pm| Self::is_available(probe, pm))
+            .collect()
     }
 
-    fn is_available(pm: PackageManager) -> bool {
-        match pm {
-            PackageManager::NixOS => Self::is_nixos_available(),
-            _ => {
-                if let Ok(output) = Command::new("which").arg(pm.name()).output() {
-                    if output.status.success() {
-                        let path = String::from_utf8_lossy(&output.stdout);
-                        let path = path.trim();
-
-                        // Verify it's in a system path (not in /tmp, home dir, etc.)
-                        // This prevents executing arbitrary binaries from unsafe locations
-                        path.starts_with("/usr/")
-                            || path.starts_with("/bin/")
-                            || path.starts_with("/sbin/")
-                            || path.starts_with("/nix/store/")
-                            || path.starts_with("/run/current-system/")
-                            || path.starts_with("/opt/")
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
+    /// Pick the package manager to use when none is configured from the
+    /// detected ones.
+    ///
+    /// The two preferences are applied in order and cover separate choices:
+    /// `prefer_system_manager` first picks pacman over the AUR helpers that
+    /// wrap it, then `prefer_nala` replaces apt with nala when both are
+    /// installed. `prefer_system_manager` never counts nala as a wrapper, so
+    /// it does not undo `prefer_nala`.
+    pub fn get_preferred(
+        available: &[PackageManager],
+        prefer_system_manager: bool,
+        prefer_nala: bool,
+    ) -> Option<PackageManager> {
+        let preferred = if prefer_system_manager {
+            available
+                .iter()
+                .find(|pm| !pm.supports_aur())
+                .or(available.first())
+        } else {
+            available.first()
+        };
+
+        match preferred.copied() {
+            Some(PackageManager::Apt) if prefer_nala && available.contains(&PackageManager::Nala) => {
+                Some(PackageManager::Nala)
             }
+            pm => pm,
         }
     }
 
-    fn is_nixos_available() -> bool {
-        // Check if nixos-rebuild exists
-        let nixos_rebuild = Command::new("which")
-            .arg("nixos-rebuild")
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false);
-
-        if !nixos_rebuild {
-            return false;
+    /// Binary looked up with `which` to detect `pm`, if any
+    fn detection_binary(pm: PackageManager) -> Option<&'static str> {
+        match pm {
+            PackageManager::NixOS => Some("nixos-rebuild"),
+            PackageManager::NixProfile => Some("nix"),
+            PackageManager::TransactionalUpdate => None,
+            _ => Some(pm.name()),
         }
+    }
 
-        // Check if we're actually on NixOS
-        std::path::Path::new("/etc/NIXOS").exists()
-            || std::path::Path::new("/run/current-system").exists()
+    fn is_available(probe: &impl DetectionProbe, pm: PackageManager) -> bool {
+        let binary = Self::detection_binary(pm).and_then(|name| probe.which(name));
+        match pm {
+            // Whether we're actually on NixOS
+            PackageManager::NixOS => {
+                binary.is_some()
+                    && (probe.exists("/etc/NIXOS") || probe.exists("/run/current-system"))
+            }
+            // Nix installed on a distribution other than NixOS
+            PackageManager::NixProfile => {
+                !probe.exists("/etc/NIXOS")
+                    && binary.is_some_and(|path| is_safe_executable_path(&path))
+            }
+            PackageManager::TransactionalUpdate => {
+                probe.exists("/usr/sbin/transactional-update")
+                    && probe
+                        .read("/etc/os-release")
+                        .is_some_and(|os_release| is_transactional_os_release(&os_release))
+            }
+            // Verify it's in a system path (not in /tmp, home dir, etc.)
+            // This prevents executing arbitrary binaries from unsafe locations
+            _ => binary.is_some_and(|path| is_safe_executable_path(&path)),
+        }
     }
 
     pub fn detect_nixos_mode(config_path: &str) -> crate::config::NixOSMode {
@@ -256,6 +1053,51 @@ impl PackageManagerDetector {
             crate::config::NixOSMode::Channels
         }
     }
+
+    /// Directory holding the configuration the running system was built from.
+    ///
+    /// When the system records its configuration revision, the candidate
+    /// checked out at that commit wins; otherwise the first candidate found.
+    /// Falls back to `/etc/nixos`.
+    pub async fn detect_nixos_config_path() -> String {
+        let candidates = tokio::task::spawn_blocking(|| {
+            nixos_config_candidates(
+                std::env::var("NIX_PATH").ok().as_deref(),
+                std::env::var("HOME").ok().as_deref(),
+                std::path::Path::new(crate::config::DEFAULT_NIXOS_CONFIG_PATH),
+            )
+        })
+        .await
+        .unwrap_or_default();
+
+        let revision = match trusted_program("nixos-version").await {
+            Some(nixos_version) => match TokioCommand::new(nixos_version)
+                .arg("--json")
+                .output()
+                .await
+            {
+                Ok(output) if output.status.success() => {
+                    configuration_revision(&String::from_utf8_lossy(&output.stdout))
+                }
+                _ => None,
+            },
+            None => None,
+        };
+        let mut detected = None;
+        if let (Some(revision), Some(git)) = (revision, trusted_program("git").await) {
+            for dir in &candidates {
+                if git_head(&git, dir).await.as_deref() == Some(revision.as_str()) {
+                    detected = Some(dir.clone());
+                    break;
+                }
+            }
+        }
+
+        detected
+            .or_else(|| candidates.into_iter().next())
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_else(|| crate::config::DEFAULT_NIXOS_CONFIG_PATH.to_string())
+    }
 }
 
 /// Manages package update checking across multiple Linux distributions.
@@ -270,16 +1112,129 @@ impl PackageManagerDetector {
 /// - **Fedora/RHEL**: dnf
 /// - **openSUSE**: zypper
 /// - **Alpine**: apk
+/// - **Solus**: eopkg
+/// - **GNU Guix**: user profile
 /// - **NixOS**: channels and flakes modes
 /// - **Universal**: flatpak
 pub struct UpdateChecker {
     package_manager: PackageManager,
+    options: CheckOptions,
+    runner: Box<dyn CommandRunner>,
+    /// Command lines run since the last `take_commands`
+    commands: std::sync::Mutex<Vec<String>>,
+    /// Set when the running check lists from cached repository metadata
+    cached_metadata: AtomicBool,
+}
+
+/// Optional, manager-specific behaviour for an update check.
+///
+/// Every flag defaults to off so that `UpdateChecker::new` keeps the plain,
+/// unprivileged check.
+#[derive(Debug, Clone, Default)]
+pub struct CheckOptions {
+    /// Run a privileged `apk update` before listing upgrades (Alpine)
+    pub refresh_apk_index: bool,
+    /// Run a privileged `zypper refresh` before listing updates (zypper,
+    /// transactional-update)
+    pub refresh_zypper_repos: bool,
+    /// Use one `paru -Qu`/`yay -Qu` call for repo and AUR updates (AUR helpers)
+    pub aur_single_query: bool,
+    /// List what `apt-get -s dist-upgrade` would install instead of `apt list --upgradable` (apt)
+    pub apt_dist_upgrade_simulation: bool,
+    /// Only count apt updates whose candidate comes from one of these suites (all when empty)
+    pub apt_origins: Vec<String>,
+    /// How long to keep retrying while another instance holds the check lock
+    /// (a single attempt when zero)
+    pub lock_timeout: Duration,
+    /// Count installed foreign packages with `-Qm` (AUR helpers)
+    pub count_foreign_packages: bool,
+    /// Flag AUR packages depending on updated official packages (AUR helpers)
+    pub aur_rebuild_hints: bool,
+    /// Look up download sizes of repo updates with `pacman -Sddp` (pacman-based)
+    pub download_sizes: bool,
+    /// Fall back to `sudo -A` with a graphical askpass helper when sudo needs
+    /// a password (NixOS channels)
+    pub sudo_askpass: bool,
+    /// How the separate AUR check is done (AUR helpers)
+    pub aur_backend: AurCheckBackend,
+    /// Shell command whose output replaces the built-in update listing,
+    /// parsed like it (managers with a text listing only)
+    pub custom_check_command: Option<String>,
+    /// Give up on a check command that runs longer than this, failing the
+    /// check with [`CheckError::Timeout`] (no limit when unset)
+    pub command_timeout: Option<Duration>,
 }
 
 impl UpdateChecker {
     /// Create a new update checker for the specified package manager.
     pub fn new(package_manager: PackageManager) -> Self {
-        Self { package_manager }
+        Self::with_options(package_manager, CheckOptions::default())
+    }
+
+    /// Create an update checker with manager-specific options.
+    pub fn with_options(package_manager: PackageManager, options: CheckOptions) -> Self {
+        Self {
+            package_manager,
+            options,
+            runner: Box::new(ProcessRunner::Local),
+            commands: std::sync::Mutex::new(Vec::new()),
+            cached_metadata: AtomicBool::new(false),
+        }
+    }
+
+    /// Run the check's commands through `runner`, e.g. on a remote host.
+    ///
+    /// Checks that do not run locally skip the instance lock and sync file,
+    /// which only coordinate applets on this machine.
+    pub fn with_runner(mut self, runner: impl CommandRunner + 'static) -> Self {
+        self.runner = Box::new(runner);
+        self
+    }
+
+    /// Run a check command to completion through the runner, remembering
+    /// its command line
+    async fn run<I, S>(&self, program: &str, args: I) -> std::io::Result<std::process::Output>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.run_with_env(program, args, &[]).await
+    }
+
+    /// [`run`](Self::run) with extra environment variables
+    async fn run_with_env<I, S>(
+        &self,
+        program: &str,
+        args: I,
+        env: &[(&str, &str)],
+    ) -> std::io::Result<std::process::Output>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<String> = args.into_iter().map(|arg| arg.as_ref().to_string()).collect();
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.push(self.runner.command_line(program, &args, env));
+        }
+        let output = self.runner.output(program, &args, env);
+        let Some(limit) = self.options.command_timeout else {
+            return output.await;
+        };
+        // Dropping the output future stops the command
+        tokio::time::timeout(limit, output)
+            .await
+            .unwrap_or_else(|_| {
+                let message = format!("{} did not finish within {}s", program, limit.as_secs());
+                Err(std::io::Error::new(std::io::ErrorKind::TimedOut, message))
+            })
+    }
+
+    /// Command lines run so far, clearing the record
+    fn take_commands(&self) -> Vec<String> {
+        self.commands
+            .lock()
+            .map(|mut commands| std::mem::take(&mut *commands))
+            .unwrap_or_default()
     }
 
     fn get_lock_path() -> PathBuf {
@@ -307,40 +1262,138 @@ impl UpdateChecker {
                     .unwrap_or_default()
                     .as_secs()
             ) {
-                eprintln!("Warning: Failed to write sync file: {}", e);
+                crate::check_log::warn(format!("Warning: Failed to write sync file: {}", e));
             }
         }
     }
 
-    /// Acquire an exclusive lock using flock to prevent concurrent update checks
-    #[allow(deprecated)]
-    async fn acquire_lock() -> Result<File> {
-        let lock_path = Self::get_lock_path();
+    /// Acquire an exclusive lock using flock to prevent concurrent update checks.
+    ///
+    /// While another instance holds the lock, retries with exponential backoff
+    /// (250ms, 500ms, 1s, then every 2s) until `timeout` has passed, and only
+    /// then reports [`CheckError::LockBusy`].
+    async fn acquire_lock(timeout: Duration) -> Result<File> {
+        Self::acquire_lock_at(&Self::get_lock_path(), timeout).await
+    }
+
+    /// [`acquire_lock`](Self::acquire_lock) on the lock file at `lock_path`
+    async fn acquire_lock_at(lock_path: &std::path::Path, timeout: Duration) -> Result<File> {
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_millis(LOCK_BACKOFF_INITIAL_MS);
+
+        loop {
+            match Self::try_lock(lock_path) {
+                Err(e)
+                    if matches!(e.downcast_ref::<CheckError>(), Some(CheckError::LockBusy)) =>
+                {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(e);
+                    }
+                    tokio::time::sleep(delay.min(remaining)).await;
+                    delay = (delay * 2).min(Duration::from_millis(LOCK_BACKOFF_MAX_MS));
+                }
+                result => return result,
+            }
+        }
+    }
 
-        // Open or create the lock file
+    /// Try once to take the check lock without blocking
+    #[allow(deprecated)]
+    fn try_lock(lock_path: &std::path::Path) -> Result<File> {
+        // Open or create the lock file; only truncate once we hold the lock so
+        // the holder's PID stays readable while it is busy
         let file = OpenOptions::new()
             .write(true)
             .create(true)
-            .truncate(true)
-            .open(&lock_path)?;
+            .truncate(false)
+            .open(lock_path)?;
 
         // Try to acquire an exclusive non-blocking lock
         match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
             Ok(()) => {
                 // Successfully acquired lock, write our PID
-                if let Err(e) = writeln!(&file, "{}", std::process::id()) {
-                    eprintln!("Warning: Failed to write PID to lock file: {}", e);
+                if let Err(e) = file
+                    .set_len(0)
+                    .and_then(|()| writeln!(&file, "{}", std::process::id()))
+                {
+                    crate::check_log::warn(format!(
+                        "Warning: Failed to write PID to lock file: {}",
+                        e
+                    ));
                 }
                 Ok(file)
             }
             Err(nix::errno::Errno::EWOULDBLOCK) => {
                 // Lock is held by another process
-                Err(anyhow!("Another instance is checking for updates"))
+                let contents = std::fs::read_to_string(lock_path).unwrap_or_default();
+                if let Some(pid) = parse_lock_pid(&contents) {
+                    if !process_exists(pid) {
+                        crate::check_log::warn(format!(
+                            "Previous lock holder (PID {} in {}) is gone; a process that inherited the lock still holds it",
+                            pid,
+                            lock_path.display()
+                        ));
+                    }
+                }
+                Err(CheckError::LockBusy.into())
             }
             Err(e) => Err(anyhow!("Failed to acquire lock: {}", e)),
         }
     }
 
+    /// Delete the check lock file when it is held but its recorded holder is
+    /// gone, e.g. a crashed instance whose lock was inherited by a child.
+    ///
+    /// The action is written to the check log.
+    pub fn reset_lock() -> Result<LockReset> {
+        let lock_path = Self::get_lock_path();
+        let file = match File::open(&lock_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(LockReset::NotHeld),
+            Err(e) => return Err(e.into()),
+        };
+
+        #[allow(deprecated)]
+        match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+            // Free: dropping the file releases it again
+            Ok(()) => return Ok(LockReset::NotHeld),
+            Err(nix::errno::Errno::EWOULDBLOCK) => {}
+            Err(e) => return Err(anyhow!("Failed to test lock: {}", e)),
+        }
+
+        let contents = std::fs::read_to_string(&lock_path).unwrap_or_default();
+        if let Some(pid) = live_lock_holder(&contents) {
+            return Ok(LockReset::HeldBy(pid));
+        }
+
+        std::fs::remove_file(&lock_path)?;
+        crate::check_log::warn(format!(
+            "Reset the update lock {} (recorded holder: {})",
+            lock_path.display(),
+            parse_lock_pid(&contents).map_or("none".to_string(), |pid| format!("PID {}", pid))
+        ));
+        Ok(LockReset::Removed)
+    }
+
+    /// Install all pending updates without a terminal.
+    ///
+    /// Authorized through the polkit update action, which is distinct from the
+    /// check action so administrators can grant the two separately.
+    pub async fn install_updates(&self) -> Result<()> {
+        let (command, args) = self
+            .package_manager
+            .noninteractive_update_command()
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} updates can only be installed from a terminal",
+                    self.package_manager
+                )
+            })?;
+        crate::polkit::execute_privileged_update(command, args).await?;
+        Ok(())
+    }
+
     /// Check for available updates.
     ///
     /// # Arguments
@@ -350,715 +1403,3345 @@ impl UpdateChecker {
     ///
     /// # Returns
     ///
-    /// `UpdateInfo` containing all available updates, or an error if the check failed
+    /// `UpdateInfo` containing all available updates, or the reason the check
+    /// failed. A failed official check is only an error when no AUR updates
+    /// could be listed either.
     pub async fn check_updates(
         &self,
         include_aur: bool,
         nixos_config: &crate::config::NixOSConfig,
-    ) -> Result<UpdateInfo> {
-        // Try to acquire lock first
-        let _lock = match Self::acquire_lock().await {
-            Ok(lock) => lock,
-            Err(e) => {
-                eprintln!("Could not acquire lock: {}. Waiting and retrying...", e);
-                tokio::time::sleep(tokio::time::Duration::from_secs(LOCK_RETRY_DELAY_SECS)).await;
-
-                // Retry once
-                match Self::acquire_lock().await {
-                    Ok(lock) => lock,
-                    Err(e) => return Err(anyhow!("Update check already in progress: {}", e)),
-                }
+    ) -> Result<UpdateInfo, CheckError> {
+        let progress = self.check_updates_with_progress(include_aur, nixos_config);
+        futures::pin_mut!(progress);
+
+        while let Some(event) = progress.next().await {
+            match event {
+                CheckProgress::Finished(update_info) => return Ok(update_info),
+                CheckProgress::Failed(error) => return Err(error),
+                _ => {}
             }
-        };
+        }
 
-        let mut update_info = UpdateInfo::new();
+        Err(CheckError::Other("Update check ended without a result".to_string()))
+    }
 
-        // Step 1: Check official updates first and wait for completion
-        match self.check_official_updates(nixos_config).await {
-            Ok(official_updates) => {
-                let count = official_updates.len();
-                update_info.official_updates = count;
-                update_info.packages.extend(official_updates);
-            }
-            Err(e) => {
-                eprintln!("Failed to check official updates: {}", e);
-                // Retry once after a delay
-                tokio::time::sleep(tokio::time::Duration::from_secs(UPDATE_RETRY_DELAY_SECS)).await;
-                match self.check_official_updates(nixos_config).await {
-                    Ok(official_updates) => {
-                        let count = official_updates.len();
-                        update_info.official_updates = count;
-                        update_info.packages.extend(official_updates);
+    /// Check for available updates, reporting progress as it goes.
+    ///
+    /// The stream ends with either [`CheckProgress::Finished`] or
+    /// [`CheckProgress::Failed`]; [`check_updates`](Self::check_updates) is this
+    /// stream collected into its final event.
+    pub fn check_updates_with_progress<'a>(
+        &'a self,
+        include_aur: bool,
+        nixos_config: &'a crate::config::NixOSConfig,
+    ) -> impl Stream<Item = CheckProgress> + 'a {
+        async_stream::stream! {
+            // Waits out a briefly colliding instance within the lock timeout
+            let _lock = if self.runner.is_local() {
+                match Self::acquire_lock(self.options.lock_timeout).await {
+                    Ok(lock) => Some(lock),
+                    Err(e) => {
+                        crate::check_log::warn(format!("Could not acquire lock: {}", e));
+                        yield CheckProgress::Failed(CheckError::from(e));
+                        return;
                     }
+                }
+            } else {
+                None
+            };
+
+            let mut update_info = UpdateInfo::new();
+            let check_aur = include_aur && self.package_manager.supports_aur();
+            self.cached_metadata.store(false, Ordering::Relaxed);
+
+            if check_aur && self.options.aur_single_query {
+                // Single helper query covering both repo and AUR packages
+                yield CheckProgress::StartedOfficial;
+                let packages = match Self::retry_once("combined updates", || {
+                    self.check_combined_updates()
+                })
+                .await
+                {
+                    Ok(packages) => packages,
                     Err(e) => {
-                        eprintln!("Retry failed for official updates: {}", e);
-                        // Continue with AUR check even if official fails
+                        for command in self.take_commands() {
+                            yield CheckProgress::RanCommand(command);
+                        }
+                        yield CheckProgress::Failed(CheckError::from(e));
+                        return;
+                    }
+                };
+                for package in &packages {
+                    yield CheckProgress::FoundPackage(package.clone());
+                }
+                update_info.aur_updates = packages.iter().filter(|p| p.is_aur).count();
+                update_info.official_updates = packages.len() - update_info.aur_updates;
+                update_info.packages.extend(packages);
+            } else {
+                // Step 1: Check official updates first and wait for completion
+                // (continue with the AUR check even if official fails)
+                yield CheckProgress::StartedOfficial;
+                let official = if self.package_manager == PackageManager::Flatpak {
+                    // Checked per remote so unreachable remotes become warnings
+                    self.check_flatpak_updates(&mut update_info.warnings).await
+                } else {
+                    Self::retry_once("official updates", || {
+                        self.check_official_updates(nixos_config)
+                    })
+                    .await
+                };
+
+                let official_error = match official {
+                    Ok(mut official_updates) => {
+                        if self.package_manager == PackageManager::Apt
+                            && !self.options.apt_dist_upgrade_simulation
+                        {
+                            let (kept_back, phased) = self.apt_kept_back().await;
+                            // Phasing explains a kept-back package, so it wins
+                            let (held_back, mut rest): (Vec<_>, Vec<_>) =
+                                official_updates.into_iter().partition(|p: &PackageUpdate| {
+                                    kept_back.contains(&p.name) && !phased.contains(&p.name)
+                                });
+                            for package in &mut rest {
+                                package.phased = phased.contains(&package.name);
+                            }
+                            update_info.held_back = held_back;
+                            official_updates = rest;
+                        }
+                        for package in &official_updates {
+                            yield CheckProgress::FoundPackage(package.clone());
+                        }
+                        update_info.official_updates = official_updates.len();
+                        update_info.packages.extend(official_updates);
+                        None
+                    }
+                    Err(e) => Some(CheckError::from(e)),
+                };
+
+                // Step 2: Only after official check is done, check AUR updates if enabled
+                let mut aur_ok = false;
+                if check_aur {
+                    yield CheckProgress::StartedAur;
+                    if let Ok(aur_updates) =
+                        Self::retry_once("AUR updates", || self.check_aur_updates()).await
+                    {
+                        for package in &aur_updates {
+                            yield CheckProgress::FoundPackage(package.clone());
+                        }
+                        update_info.aur_updates = aur_updates.len();
+                        update_info.packages.extend(aur_updates);
+                        aur_ok = true;
+                    }
+                }
+
+                if let Some(error) = official_error {
+                    if !aur_ok {
+                        for command in self.take_commands() {
+                            yield CheckProgress::RanCommand(command);
+                        }
+                        yield CheckProgress::Failed(error);
+                        return;
                     }
                 }
             }
-        }
 
-        // Step 2: Only after official check is done, check AUR updates if enabled
-        if include_aur && self.package_manager.supports_aur() {
-            match self.check_aur_updates().await {
-                Ok(aur_updates) => {
-                    let count = aur_updates.len();
-                    update_info.aur_updates = count;
-                    update_info.packages.extend(aur_updates);
+            // Step 3: Calculate final total only after both checks are complete
+            update_info.total_updates = update_info.packages.len();
+            update_info.security_updates =
+                update_info.packages.iter().filter(|p| p.is_security).count();
+            if check_aur && self.options.count_foreign_packages {
+                // Informational only, so a failure just leaves the count out
+                match self.foreign_packages().await {
+                    Ok(foreign) => update_info.foreign_packages = Some(foreign.len()),
+                    Err(e) => crate::check_log::warn(format!(
+                        "Failed to count foreign packages: {}",
+                        e
+                    )),
                 }
-                Err(e) => {
-                    eprintln!("Failed to check AUR updates: {}", e);
-                    // Retry once after a delay
-                    tokio::time::sleep(tokio::time::Duration::from_secs(UPDATE_RETRY_DELAY_SECS))
-                        .await;
-                    match self.check_aur_updates().await {
-                        Ok(aur_updates) => {
-                            let count = aur_updates.len();
-                            update_info.aur_updates = count;
-                            update_info.packages.extend(aur_updates);
-                        }
-                        Err(e) => {
-                            eprintln!("Retry failed for AUR updates: {}", e);
-                            // Continue even if AUR check fails
-                        }
+            }
+            if check_aur && self.options.aur_rebuild_hints {
+                // Hints only, so a failure just leaves them out
+                match self.aur_rebuild_hints(&update_info.packages).await {
+                    Ok(hints) => update_info.rebuild_hints = hints,
+                    Err(e) => crate::check_log::warn(format!(
+                        "Failed to compute AUR rebuild hints: {}",
+                        e
+                    )),
+                }
+            }
+            if self.options.download_sizes
+                && matches!(
+                    self.package_manager,
+                    PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay
+                )
+            {
+                // Sizes are cosmetic, so a failure just leaves them out
+                match self.download_sizes(&update_info.packages).await {
+                    Ok(sizes) => Self::apply_download_sizes(&mut update_info, &sizes),
+                    Err(e) => crate::check_log::warn(format!(
+                        "Failed to look up download sizes: {}",
+                        e
+                    )),
+                }
+            }
+            update_info.reboot_required = self.runner.is_local()
+                && match self.package_manager {
+                    PackageManager::TransactionalUpdate => {
+                        std::path::Path::new(REBOOT_NEEDED_PATH).exists()
                     }
+                    PackageManager::Guix => guix_reboot_pending(),
+                    _ => false,
+                };
+            if self.runner.is_local() {
+                match self.kernel_mismatch().await {
+                    Ok(mismatch) => update_info.kernel_mismatch = mismatch,
+                    Err(e) => crate::check_log::warn(format!(
+                        "Failed to compare the running kernel: {}",
+                        e
+                    )),
                 }
             }
-        }
 
-        // Step 3: Calculate final total only after both checks are complete
-        update_info.total_updates = update_info.packages.len();
+            update_info.cached_metadata = self.cached_metadata.load(Ordering::Relaxed);
+
+            // Notify other instances that we completed a check
+            if self.runner.is_local() {
+                Self::notify_check_completed();
+            }
 
-        // Notify other instances that we completed a check
-        Self::notify_check_completed();
+            for command in self.take_commands() {
+                yield CheckProgress::RanCommand(command);
+            }
+            // Lock is automatically released when _lock is dropped
+            yield CheckProgress::Finished(update_info);
+        }
+    }
 
-        // Lock is automatically released when _lock is dropped
-        Ok(update_info)
+    /// Run a check, retrying once after a short delay if it fails in a way
+    /// that may pass on a second try.
+    ///
+    /// Only unexplained failures, like an unreachable mirror or remote, are
+    /// retried; a missing command, missing privileges, a busy lock, a
+    /// timeout or unreadable output would fail the same way again. Both
+    /// failures are logged; the second error is returned to the caller.
+    async fn retry_once<T, F, Fut>(what: &str, mut check: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        match check().await {
+            Ok(result) => Ok(result),
+            Err(e) if !matches!(CheckError::of(&e), CheckError::Other(_)) => Err(e),
+            Err(e) => {
+                crate::check_log::warn(format!("Failed to check {}: {}", what, e));
+                tokio::time::sleep(tokio::time::Duration::from_secs(UPDATE_RETRY_DELAY_SECS)).await;
+                check().await.inspect_err(|e| {
+                    crate::check_log::warn(format!("Retry failed for {}: {}", what, e));
+                })
+            }
+        }
     }
 
     async fn check_official_updates(
         &self,
         nixos_config: &crate::config::NixOSConfig,
     ) -> Result<Vec<PackageUpdate>> {
+        // Whether the built-in command lists from cached metadata
+        let mut cached_metadata = false;
         let (cmd, args) = match self.package_manager {
             // Arch-based systems
             PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay => {
                 ("checkupdates", vec![])
             }
-            // Debian/Ubuntu
-            PackageManager::Apt => ("apt", vec!["list", "--upgradable"]),
+            // Debian/Ubuntu; both read the lists from the last `apt update`
+            PackageManager::Nala => {
+                cached_metadata = true;
+                ("nala", vec!["list", "--upgradable"])
+            }
+            PackageManager::Apt => {
+                cached_metadata = true;
+                if self.options.apt_dist_upgrade_simulation {
+                    ("apt-get", vec!["-s", "dist-upgrade"])
+                } else {
+                    ("apt", vec!["list", "--upgradable"])
+                }
+            }
             // Fedora/RHEL
             PackageManager::Dnf => ("dnf", vec!["check-update", "-q"]),
-            // openSUSE/SUSE
-            PackageManager::Zypper => ("zypper", vec!["list-updates"]),
+            // openSUSE/SUSE; unprivileged zypper only reads the cached
+            // metadata, and the running MicroOS snapshot is read-only but
+            // listing its updates still works
+            PackageManager::Zypper | PackageManager::TransactionalUpdate => {
+                cached_metadata = !(self.options.refresh_zypper_repos
+                    && self.runner.is_local()
+                    && Self::refresh_zypper_repos().await);
+                ("zypper", vec!["list-updates"])
+            }
             // Alpine Linux
-            PackageManager::Apk => ("apk", vec!["-u", "list"]),
+            PackageManager::Apk => {
+                // The privileged refresh goes through the local PolicyKit
+                cached_metadata = !(self.options.refresh_apk_index
+                    && self.runner.is_local()
+                    && Self::refresh_apk_index().await);
+                ("apk", vec!["-u", "list"])
+            }
+            // Solus; reads the repository index from the last `eopkg update-repo`
+            PackageManager::Eopkg => {
+                cached_metadata = true;
+                ("eopkg", vec!["list-upgrades"])
+            }
             // Flatpak
-            PackageManager::Flatpak => ("flatpak", vec!["remote-ls", "--updates"]),
+            PackageManager::Flatpak => {
+                return self.check_flatpak_updates(&mut Vec::new()).await;
+            }
             // NixOS
             PackageManager::NixOS => {
                 return self.check_nixos_updates(nixos_config).await;
             }
+            PackageManager::NixProfile => {
+                return self.check_nix_profile_updates().await;
+            }
+            PackageManager::HomeManager => {
+                return self.check_home_manager_updates(nixos_config).await;
+            }
+            PackageManager::Guix => {
+                return self.check_guix_updates().await;
+            }
+        };
+        let (cmd, args) = match self
+            .options
+            .custom_check_command
+            .as_deref()
+            .filter(|command| !command.trim().is_empty())
+        {
+            // What a custom command reads is unknown
+            Some(command) => ("sh", vec!["-c", command]),
+            None => {
+                if cached_metadata {
+                    self.cached_metadata.store(true, Ordering::Relaxed);
+                }
+                (cmd, args)
+            }
+        };
+
+        let mut packages = match self.parse_update_output(cmd, args, false).await {
+            // Unprivileged dnf refreshes expired metadata into the user
+            // cache; when that download fails, list from what is cached
+            Err(e) if cmd == "dnf" && is_dnf_metadata_error(&e.to_string()) => {
+                crate::check_log::warn(format!(
+                    "dnf could not refresh metadata, using cached metadata: {}",
+                    e
+                ));
+                self.cached_metadata.store(true, Ordering::Relaxed);
+                self.parse_update_output("dnf", vec!["check-update", "-q", "--cacheonly"], false)
+                    .await?
+            }
+            result => result?,
         };
 
-        self.parse_update_output(cmd, args, false).await
+        if self.package_manager == PackageManager::Dnf && !packages.is_empty() {
+            self.mark_dnf_security_updates(&mut packages).await;
+        }
+
+        Ok(packages)
     }
 
-    async fn check_aur_updates(&self) -> Result<Vec<PackageUpdate>> {
-        let (cmd, args) = match self.package_manager {
-            PackageManager::Pacman => return Ok(Vec::new()),
-            PackageManager::Paru => ("paru", vec!["-Qu", "--aur"]),
-            PackageManager::Yay => ("yay", vec!["-Qu", "--aur"]),
-            // Other package managers don't have AUR support
-            _ => return Ok(Vec::new()),
+    /// Flag the updates listed by `dnf updateinfo list security`.
+    ///
+    /// Failures are only logged: the updates are still reported, just unflagged.
+    async fn mark_dnf_security_updates(&self, packages: &mut [PackageUpdate]) {
+        let output = match self
+            .run("dnf", ["updateinfo", "list", "security", "-q"])
+            .await
+        {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                crate::check_log::warn(format!(
+                    "dnf updateinfo failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+                return;
+            }
+            Err(e) => {
+                crate::check_log::warn(format!("Failed to run dnf updateinfo: {}", e));
+                return;
+            }
         };
 
-        self.parse_update_output(cmd, args, true).await
+        let security = Self::parse_dnf_security_names(&String::from_utf8_lossy(&output.stdout));
+        for package in packages {
+            package.is_security = security.contains(&package.name);
+        }
     }
 
-    async fn parse_update_output(
+    /// Names of the packages `apt-get -s upgrade` would keep back, and of
+    /// those it defers due to phasing.
+    ///
+    /// Failures are only logged: the updates then simply stay in the main list.
+    async fn apt_kept_back(&self) -> (HashSet<String>, HashSet<String>) {
+        match self.run("apt-get", ["-s", "upgrade"]).await {
+            Ok(output) if output.status.success() => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                (
+                    Self::parse_apt_kept_back(&stdout),
+                    Self::parse_apt_phased(&stdout),
+                )
+            }
+            Ok(output) => {
+                crate::check_log::warn(format!(
+                    "apt-get -s upgrade failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+                Default::default()
+            }
+            Err(e) => {
+                crate::check_log::warn(format!("Failed to run apt-get -s upgrade: {}", e));
+                Default::default()
+            }
+        }
+    }
+
+    /// Parse the indented package names following
+    /// "The following packages have been kept back:" in `apt-get -s upgrade` output.
+    fn parse_apt_kept_back(output: &str) -> HashSet<String> {
+        Self::parse_apt_package_section(output, "The following packages have been kept back")
+    }
+
+    /// Parse the names following "The following upgrades have been deferred
+    /// due to phasing:", printed by apt 2.1 and later.
+    fn parse_apt_phased(output: &str) -> HashSet<String> {
+        Self::parse_apt_package_section(
+            output,
+            "The following upgrades have been deferred due to phasing",
+        )
+    }
+
+    /// Indented package names in the `apt-get` output section after `header`
+    fn parse_apt_package_section(output: &str, header: &str) -> HashSet<String> {
+        output
+            .lines()
+            .skip_while(|line| !line.starts_with(header))
+            .skip(1)
+            .take_while(|line| line.starts_with(' '))
+            .flat_map(str::split_whitespace)
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Parse `dnf updateinfo list security` output into package names.
+    ///
+    /// Each line ends with the NEVRA of the fixed package
+    /// (`FEDORA-2024-1a2b3c Important/Sec. kernel-6.5.0-1.fc38.x86_64`);
+    /// the name is everything before the version and release.
+    fn parse_dnf_security_names(output: &str) -> HashSet<String> {
+        output
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .filter_map(|nevra| {
+                let (rest, _release) = nevra.rsplit_once('-')?;
+                let (name, _version) = rest.rsplit_once('-')?;
+                Some(name.to_string())
+            })
+            .collect()
+    }
+
+    /// Check every enabled Flatpak remote separately.
+    ///
+    /// Each remote gets its own retry. Remotes that still fail are reported in
+    /// `warnings` while the updates from reachable remotes are returned; the
+    /// check only fails when no remote could be queried.
+    async fn check_flatpak_updates(
         &self,
-        cmd: &str,
-        args: Vec<&str>,
-        is_aur: bool,
+        warnings: &mut Vec<String>,
     ) -> Result<Vec<PackageUpdate>> {
-        let output = TokioCommand::new(cmd).args(&args).output().await?;
+        let output = self
+            .run("flatpak", ["remotes", "--columns=name,options"])
+            .await
+            .map_err(|e| spawn_error("flatpak", e))?;
 
         if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(-1);
-
-            // Handle exit codes more carefully
-            // checkupdates returns 2 when no updates are available
-            // paru/yay return 1 when no updates are available
-            // dnf returns 100 when updates are available, 0 when no updates
-            // apt returns non-zero on error but we check stdout
-            if (cmd == "checkupdates" && exit_code == 2)
-                || ((cmd == "paru" || cmd == "yay") && exit_code == 1)
-                || (cmd == "dnf" && exit_code == 100)
-            {
-                // No updates available or special success case
-                if cmd == "dnf" && exit_code == 100 {
-                    // dnf exit code 100 means updates ARE available, continue parsing
-                } else {
-                    return Ok(Vec::new());
-                }
-            } else {
-                // Any other exit code might still have valid output for some package managers
-                // Check if we have stdout output before failing
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                if stdout.trim().is_empty() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    eprintln!(
-                        "Update check failed with exit code {}: {}",
-                        exit_code, stderr
-                    );
-                    return Err(anyhow!(
-                        "Failed to check for updates (exit {}): {}",
-                        exit_code,
-                        stderr
-                    ));
-                }
-                // Otherwise continue to parse the output
-            }
+            return Err(anyhow!(
+                "Failed to list Flatpak remotes: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
+        let remotes = Self::parse_flatpak_remotes(&String::from_utf8_lossy(&output.stdout));
         let mut packages = Vec::new();
+        let mut failed = 0;
+        let mut last_error = None;
+
+        for (remote, installation) in &remotes {
+            let what = format!("Flatpak remote '{}'", remote);
+            let args = vec![
+                "remote-ls",
+                *installation,
+                "--updates",
+                FLATPAK_UPDATE_COLUMNS,
+                remote.as_str(),
+            ];
+
+            let result = Self::retry_once(&what, || {
+                self.parse_update_output("flatpak", args.clone(), false)
+            })
+            .await;
 
-        for line in stdout.lines() {
-            if let Some(package) = self.parse_package_line(line, is_aur) {
-                packages.push(package);
+            match result {
+                Ok(found) => packages.extend(found),
+                Err(e) => {
+                    warnings.push(format!("{} could not be reached: {}", what, e));
+                    failed += 1;
+                    last_error = Some(e);
+                }
             }
         }
 
-        Ok(packages)
+        match last_error {
+            Some(e) if failed == remotes.len() => Err(e),
+            _ => Ok(packages),
+        }
     }
 
-    fn parse_package_line(&self, line: &str, is_aur: bool) -> Option<PackageUpdate> {
-        // Skip header lines
-        if line.starts_with("Listing...")
-            || line.starts_with("Done")
-            || line.starts_with("WARNING:")
-            || line.starts_with("S |")
-            || line.starts_with("--+")
-            || line.trim().is_empty()
+    /// Parse `flatpak remotes --columns=name,options` into remote names and the
+    /// installation flag (`--user`/`--system`) to query them with.
+    ///
+    /// Disabled remotes are skipped.
+    fn parse_flatpak_remotes(output: &str) -> Vec<(String, &'static str)> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split_whitespace();
+                let name = columns.next()?;
+                let options = columns.next().unwrap_or("system");
+                if options.split(',').any(|option| option == "disabled") {
+                    return None;
+                }
+                let installation = if options.split(',').any(|option| option == "user") {
+                    "--user"
+                } else {
+                    "--system"
+                };
+                Some((name.to_string(), installation))
+            })
+            .collect()
+    }
+
+    /// Refresh the apk index so `apk -u list` compares against current
+    /// repositories, returning whether it worked.
+    ///
+    /// Failures are only logged: a stale index still gives a usable result.
+    async fn refresh_apk_index() -> bool {
+        if let Err(e) = crate::polkit::execute_privileged(
+            "apk",
+            &["update"],
+            crate::polkit::POLKIT_ACTION_CHECK,
+            "Authentication required to refresh the Alpine package index",
+        )
+        .await
         {
-            return None;
+            crate::check_log::warn(format!(
+                "Failed to refresh apk index, using cached index: {}",
+                e
+            ));
+            return false;
         }
+        true
+    }
 
-        match self.package_manager {
-            // Arch-based: "package 1.0.0-1 -> 1.0.1-1" or "package 1.0.1-1"
-            PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay => {
-                if line.contains(" -> ") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 4 && parts[2] == "->" {
-                        return Some(PackageUpdate {
-                            name: parts[0].to_string(),
-                            current_version: parts[1].to_string(),
-                            new_version: parts[3].to_string(),
-                            is_aur,
-                        });
-                    }
-                } else {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 {
-                        return Some(PackageUpdate {
-                            name: parts[0].to_string(),
-                            current_version: "unknown".to_string(),
-                            new_version: parts[1].to_string(),
-                            is_aur,
-                        });
-                    }
-                }
-            }
+    /// Refresh the zypper repositories so `zypper list-updates` sees current
+    /// metadata, returning whether it worked.
+    ///
+    /// Failures are only logged: the cached metadata still gives a usable result.
+    async fn refresh_zypper_repos() -> bool {
+        if let Err(e) = crate::polkit::execute_privileged(
+            "zypper",
+            &["--non-interactive", "refresh"],
+            crate::polkit::POLKIT_ACTION_CHECK,
+            "Authentication required to refresh the package repositories",
+        )
+        .await
+        {
+            crate::check_log::warn(format!(
+                "Failed to refresh zypper repositories, using cached metadata: {}",
+                e
+            ));
+            return false;
+        }
+        true
+    }
 
-            // APT: "package/suite version arch [upgradable from: old-version]"
-            PackageManager::Apt => {
-                if line.contains("[upgradable from:") {
-                    // Split by '/' to get package name
-                    let name = line.split('/').next()?.to_string();
+    /// Check repo and AUR updates with a single `-Qu` call of the AUR helper.
+    ///
+    /// Entries are classified as AUR when the package is foreign (`-Qm`). Repo
+    /// updates are computed against the local sync database rather than the
+    /// fresh temporary one `checkupdates` uses, in exchange for half the
+    /// subprocess spawns.
+    async fn check_combined_updates(&self) -> Result<Vec<PackageUpdate>> {
+        self.cached_metadata.store(true, Ordering::Relaxed);
+        let helper = self.package_manager.name();
+        let foreign = self.foreign_packages().await?;
+        let mut packages = self.parse_update_output(helper, vec!["-Qu"], false).await?;
+        for package in &mut packages {
+            package.is_aur = foreign.contains(&package.name);
+        }
+        Ok(packages)
+    }
 
-                    // Extract new version (between '/' and architecture)
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    let new_version = if parts.len() >= 2 {
-                        parts[1].to_string()
-                    } else {
-                        "unknown".to_string()
-                    };
+    /// Names of installed packages not found in any sync repository (`-Qm`)
+    async fn foreign_packages(&self) -> Result<HashSet<String>> {
+        let output = self.run(self.package_manager.name(), ["-Qm"]).await?;
 
-                    // Extract old version from [upgradable from: X]
-                    let current_version = if let Some(from_idx) = line.find("[upgradable from: ") {
-                        let start = from_idx + "[upgradable from: ".len();
-                        if let Some(end_idx) = line[start..].find(']') {
-                            line[start..start + end_idx].to_string()
-                        } else {
-                            "unknown".to_string()
-                        }
-                    } else {
-                        "unknown".to_string()
-                    };
+        // -Qm exits 1 when there are no foreign packages, so only stdout matters
+        Ok(Self::parse_foreign_packages(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
 
-                    return Some(PackageUpdate {
-                        name,
-                        current_version,
-                        new_version,
-                        is_aur: false,
-                    });
-                }
+    /// Parse the part of an `apt-get -s` "Inst" line after the prefix:
+    /// `package [old-version] (new-version suite [arch])`.
+    ///
+    /// Packages newly pulled in by the upgrade have no `[old-version]`.
+    fn parse_apt_simulation_line(rest: &str) -> Option<PackageUpdate> {
+        let mut parts = rest.split_whitespace();
+        let name = parts.next()?.to_string();
+        let mut next = parts.next()?;
+
+        let current_version = match next.strip_prefix('[') {
+            Some(old) => {
+                let old = old.strip_suffix(']')?.to_string();
+                next = parts.next()?;
+                old
             }
+            None => "none".to_string(),
+        };
 
-            // DNF: "package.arch version repo" (3 columns)
-            PackageManager::Dnf => {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    // First part is "package.arch"
-                    let name = parts[0].split('.').next()?.to_string();
-                    let new_version = parts[1].to_string();
+        let new_version = next.strip_prefix('(')?.trim_end_matches(')').to_string();
+        if new_version.is_empty() {
+            return None;
+        }
 
-                    return Some(PackageUpdate {
-                        name,
-                        current_version: "unknown".to_string(),
-                        new_version,
-                        is_aur: false,
-                    });
-                }
-            }
+        let is_security = Self::apt_simulation_suites(rest)
+            .iter()
+            .any(|suite| suite.ends_with("-security"));
 
-            // Zypper: table format with columns
-            // Skip status column and parse name and version
-            PackageManager::Zypper => {
-                let parts: Vec<&str> = line.split('|').collect();
-                if parts.len() >= 4 {
-                    let name = parts[1].trim().to_string();
-                    let new_version = parts[3].trim().to_string();
+        Some(PackageUpdate {
+            name,
+            current_version,
+            new_version,
+            is_aur: false,
+            is_security,
+            ..Default::default()
+        })
+    }
 
-                    return Some(PackageUpdate {
-                        name,
-                        current_version: "unknown".to_string(),
-                        new_version,
-                        is_aur: false,
-                    });
-                }
-            }
+    /// Suites of an `apt list --upgradable` line (`package/suite1,suite2 version ...`)
+    fn apt_list_suites(line: &str) -> Vec<&str> {
+        line.split_whitespace()
+            .next()
+            .and_then(|source| source.split_once('/'))
+            .map(|(_, suites)| suites.split(',').collect())
+            .unwrap_or_default()
+    }
 
-            // APK: "package-version [upgradable from: old-version]"
-            PackageManager::Apk => {
-                if line.contains("[upgradable from:") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if !parts.is_empty() {
-                        // First part contains package-version, need to extract package name
-                        let pkg_info = parts[0];
-                        let name = if let Some(dash_idx) = pkg_info.rfind('-') {
-                            pkg_info[..dash_idx].to_string()
-                        } else {
-                            pkg_info.to_string()
-                        };
+    /// Suites of an `apt-get -s` "Inst" line, taken from the origins after the
+    /// new version: `(1.1 Ubuntu:22.04/jammy-updates, Ubuntu:22.04/jammy-security [amd64])`
+    fn apt_simulation_suites(rest: &str) -> Vec<&str> {
+        let Some(start) = rest.find('(') else {
+            return Vec::new();
+        };
 
-                        // Extract versions
-                        let new_version = parts.get(1).unwrap_or(&"unknown").to_string();
-
-                        let current_version =
-                            if let Some(from_idx) = line.find("[upgradable from: ") {
-                                let start = from_idx + "[upgradable from: ".len();
-                                if let Some(end_idx) = line[start..].find(']') {
-                                    line[start..start + end_idx].to_string()
-                                } else {
-                                    "unknown".to_string()
-                                }
-                            } else {
-                                "unknown".to_string()
-                            };
+        rest[start + 1..]
+            .trim_end_matches(')')
+            .split(',')
+            .filter_map(|source| {
+                let origin = source.split_whitespace().find(|token| token.contains('/'))?;
+                origin.split_once('/').map(|(_, suite)| suite)
+            })
+            .collect()
+    }
 
-                        return Some(PackageUpdate {
-                            name,
-                            current_version,
-                            new_version,
-                            is_aur: false,
-                        });
-                    }
-                }
-            }
+    /// Whether an apt update from these suites passes the configured origin filter
+    fn apt_origin_allowed(&self, suites: &[&str]) -> bool {
+        self.options.apt_origins.is_empty()
+            || suites
+                .iter()
+                .any(|suite| self.options.apt_origins.iter().any(|origin| origin == suite))
+    }
 
-            // Flatpak: "name\tapp-id\tversion\tbranch\tremote"
-            PackageManager::Flatpak => {
-                let parts: Vec<&str> = line.split('\t').collect();
-                if parts.len() >= 3 {
-                    let name = parts[0].to_string();
-                    let new_version = parts[2].to_string();
+    /// Find installed AUR packages that depend on one of the pending official
+    /// updates and may need a rebuild, even without an AUR version bump.
+    ///
+    /// Only updates that change the major or minor version count, since
+    /// patch releases and package rebuilds (glibc 2.39-1 to 2.39-2) keep
+    /// the ABI that AUR builds link against. AUR packages with their own
+    /// pending update are skipped since updating them rebuilds them anyway.
+    async fn aur_rebuild_hints(&self, updates: &[PackageUpdate]) -> Result<Vec<RebuildHint>> {
+        let changed: HashSet<String> = updates
+            .iter()
+            .filter(|p| {
+                !p.is_aur && Self::minor_version_changed(&p.current_version, &p.new_version)
+            })
+            .map(|p| p.name.clone())
+            .collect();
+        if changed.is_empty() {
+            return Ok(Vec::new());
+        }
 
-                    return Some(PackageUpdate {
-                        name,
-                        current_version: "unknown".to_string(),
-                        new_version,
-                        is_aur: false,
-                    });
-                }
-            }
+        let updated_aur: HashSet<&str> = updates
+            .iter()
+            .filter(|p| p.is_aur)
+            .map(|p| p.name.as_str())
+            .collect();
+        let mut foreign: Vec<String> = self
+            .foreign_packages()
+            .await?
+            .into_iter()
+            .filter(|name| !updated_aur.contains(name.as_str()))
+            .collect();
+        if foreign.is_empty() {
+            return Ok(Vec::new());
+        }
+        foreign.sort();
+
+        let args: Vec<&str> = std::iter::once("-Qi")
+            .chain(foreign.iter().map(String::as_str))
+            .collect();
+        // The field names parse_pacman_depends looks for are translated
+        let output = self
+            .run_with_env("pacman", &args, &[("LC_ALL", "C")])
+            .await
+            .map_err(|e| spawn_error("pacman", e))?;
 
-            // NixOS: Handled separately by check_nixos_updates, never reaches this function
-            PackageManager::NixOS => {
-                return None;
+        let depends = Self::parse_pacman_depends(&String::from_utf8_lossy(&output.stdout));
+        Ok(Self::rebuild_hints(&depends, &changed))
+    }
+
+    /// Parse `pacman -Qi` output into each package's dependency names.
+    ///
+    /// Long "Depends On" lists continue on indented lines; version constraints
+    /// (`glibc>=2.38`) are dropped.
+    fn parse_pacman_depends(output: &str) -> Vec<(String, Vec<String>)> {
+        let mut packages: Vec<(String, Vec<String>)> = Vec::new();
+        let mut in_depends = false;
+
+        for line in output.lines() {
+            let (key, value) = match line.split_once(" : ") {
+                Some((key, value)) if !key.starts_with(' ') => (key.trim(), value),
+                _ if in_depends => ("Depends On", line),
+                _ => continue,
+            };
+
+            in_depends = key == "Depends On";
+            match key {
+                "Name" => packages.push((value.trim().to_string(), Vec::new())),
+                "Depends On" => {
+                    if let Some((_, deps)) = packages.last_mut() {
+                        deps.extend(
+                            value
+                                .split_whitespace()
+                                .filter(|dep| *dep != "None")
+                                .filter_map(|dep| dep.split(['<', '>', '=', ':']).next())
+                                .map(str::to_string),
+                        );
+                    }
+                }
+                _ => {}
             }
         }
 
-        None
+        packages
     }
 
-    /// Check if passwordless sudo is configured for the current user
-    async fn check_passwordless_sudo() -> Result<bool> {
-        let output = TokioCommand::new("sudo")
-            .args(["-n", "true"]) // -n = non-interactive
-            .output()
-            .await?;
-        Ok(output.status.success())
+    /// Whether a pacman version bump changes the major or minor version,
+    /// ignoring the epoch and pkgrel
+    fn minor_version_changed(current: &str, new: &str) -> bool {
+        fn major_minor(version: &str) -> Vec<&str> {
+            let version = version.split_once(':').map_or(version, |(_, rest)| rest);
+            let pkgver = version.rsplit_once('-').map_or(version, |(pkgver, _)| pkgver);
+            pkgver.split(['.', '+']).take(2).collect()
+        }
+        major_minor(current) != major_minor(new)
     }
 
-    async fn check_nixos_channels(&self) -> Result<Vec<PackageUpdate>> {
-        // Try PolicyKit first, fall back to passwordless sudo check if not available
-        if crate::polkit::PolkitAuth::is_available().await {
-            // Use PolicyKit for privilege escalation
-            match crate::polkit::execute_privileged(
-                "nixos-rebuild",
-                &["dry-build", "--upgrade"],
-                crate::polkit::POLKIT_ACTION_CHECK,
-                "Authentication required to check for NixOS updates",
-            )
-            .await
-            {
-                Ok(output) => {
-                    if !output.status.success() {
-                        let stderr = String::from_utf8_lossy(&output.stderr);
-                        return Err(anyhow!("Failed to check NixOS updates: {}", stderr));
-                    }
+    /// Packages with at least one dependency in `changed`
+    fn rebuild_hints(
+        depends: &[(String, Vec<String>)],
+        changed: &HashSet<String>,
+    ) -> Vec<RebuildHint> {
+        depends
+            .iter()
+            .filter_map(|(package, deps)| {
+                let changed_dependencies: Vec<String> =
+                    deps.iter().filter(|dep| changed.contains(*dep)).cloned().collect();
+                (!changed_dependencies.is_empty()).then(|| RebuildHint {
+                    package: package.clone(),
+                    changed_dependencies,
+                })
+            })
+            .collect()
+    }
 
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    let combined_output = format!("{}\n{}", stdout, stderr);
-                    return self.parse_nixos_rebuild_output(&combined_output);
-                }
-                Err(e) => {
-                    eprintln!("PolicyKit execution failed: {}, trying sudo fallback", e);
-                    // Continue to sudo fallback below
-                }
+    /// Look up the download size of each pending repo update.
+    ///
+    /// Uses the temporary database `checkupdates` synced, when present, so the
+    /// sizes match the new versions rather than a stale system sync database.
+    /// AUR packages are skipped since pacman fails on unknown targets.
+    async fn download_sizes(&self, updates: &[PackageUpdate]) -> Result<HashMap<String, u64>> {
+        let mut names: Vec<&str> = updates
+            .iter()
+            .filter(|p| !p.is_aur)
+            .map(|p| p.name.as_str())
+            .collect();
+        if names.is_empty() {
+            return Ok(HashMap::new());
+        }
+        names.sort_unstable();
+
+        let mut args: Vec<String> = ["-Sddp", "--print-format", "%n %s"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        if self.runner.is_local() {
+            if let Some(db) = Self::checkupdates_db().filter(|db| db.join("sync").is_dir()) {
+                args.push("--dbpath".to_string());
+                args.push(db.to_string_lossy().into_owned());
             }
         }
+        args.extend(names.iter().map(|name| name.to_string()));
+        let output = self
+            .run("pacman", &args)
+            .await
+            .map_err(|e| spawn_error("pacman", e))?;
 
-        // Fallback to sudo if PolicyKit unavailable or failed
-        // Check for passwordless sudo first
-        if !Self::check_passwordless_sudo().await.unwrap_or(false) {
+        if !output.status.success() {
             return Err(anyhow!(
-                "NixOS channels mode requires passwordless sudo or PolicyKit.\n\
-                 \n\
-                 Option 1 (Recommended): PolicyKit is not available or failed.\n\
-                 Install PolicyKit and ensure pkexec is available.\n\
-                 \n\
-                 Option 2: Configure passwordless sudo by adding to /etc/sudoers.d/nixos-rebuild:\n\
-                 %wheel ALL=(ALL) NOPASSWD: /run/current-system/sw/bin/nixos-rebuild"
+                "pacman -Sddp failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
             ));
         }
 
-        // Run nixos-rebuild dry-build with upgrade flag to show package statistics
-        let output = TokioCommand::new("sudo")
-            .args(["nixos-rebuild", "dry-build", "--upgrade"])
-            .output()
-            .await?;
+        Ok(Self::parse_pacman_sizes(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+    /// Database directory `checkupdates` uses, as it computes it
+    fn checkupdates_db() -> Option<PathBuf> {
+        if let Some(db) = std::env::var_os("CHECKUPDATES_DB") {
+            return Some(PathBuf::from(db));
+        }
+        let tmp = std::env::var_os("TMPDIR").unwrap_or_else(|| "/tmp".into());
+        Some(PathBuf::from(tmp).join(format!("checkup-db-{}", nix::unistd::getuid())))
+    }
 
-            // Check if it's a permission issue
-            if stderr.contains("not allowed")
-                || stderr.contains("password")
-                || stderr.contains("sudo")
-            {
-                return Err(anyhow!(
-                    "Permission denied. Use PolicyKit or configure passwordless sudo for nixos-rebuild:\n\
-                     Add to /etc/sudoers.d/nixos-rebuild:\n\
-                     %wheel ALL=(ALL) NOPASSWD: /run/current-system/sw/bin/nixos-rebuild"
-                ));
-            }
+    /// Parse `--print-format '%n %s'` output (`name bytes` per line)
+    fn parse_pacman_sizes(output: &str) -> HashMap<String, u64> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let (name, size) = line.trim().split_once(' ')?;
+                Some((name.to_string(), size.trim().parse().ok()?))
+            })
+            .collect()
+    }
 
-            return Err(anyhow!("Failed to check NixOS updates: {}", stderr));
+    /// Fill in per-package sizes and their total; the total stays `None`
+    /// when no size is known
+    fn apply_download_sizes(update_info: &mut UpdateInfo, sizes: &HashMap<String, u64>) {
+        for package in &mut update_info.packages {
+            package.download_size = sizes.get(&package.name).copied();
+        }
+        let known: Vec<u64> = update_info
+            .packages
+            .iter()
+            .filter_map(|p| p.download_size)
+            .collect();
+        update_info.total_download_size = (!known.is_empty()).then(|| known.iter().sum());
+    }
+
+    /// Compare `uname -r` with the newest installed kernel package.
+    ///
+    /// `None` when they match or the package manager has no known kernel
+    /// package (pacman, dpkg and rpm based only).
+    async fn kernel_mismatch(&self) -> Result<Option<KernelMismatch>> {
+        let (program, args): (&str, Vec<&str>) = match self.package_manager {
+            PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay => (
+                "pacman",
+                std::iter::once("-Q")
+                    .chain(PACMAN_KERNELS.iter().copied())
+                    .collect(),
+            ),
+            PackageManager::Apt | PackageManager::Nala => (
+                "dpkg-query",
+                vec!["-W", "-f", r"${Status} ${Package}\n", "linux-image-[0-9]*"],
+            ),
+            PackageManager::Dnf => (
+                "rpm",
+                vec![
+                    "-q",
+                    "--qf",
+                    r"%{VERSION}-%{RELEASE}.%{ARCH}\n",
+                    "kernel-core",
+                ],
+            ),
+            _ => return Ok(None),
+        };
+        let Some(scheme) = self.version_scheme() else {
+            return Ok(None);
+        };
+
+        let uname = self
+            .run("uname", ["-r"])
+            .await
+            .map_err(|e| spawn_error("uname", e))?;
+        let running = String::from_utf8_lossy(&uname.stdout).trim().to_string();
+        if running.is_empty() {
+            return Ok(None);
         }
 
+        // pacman -Q still lists the installed kernels when some of the names
+        // are unknown, so only dpkg-query and rpm failures mean "none found"
+        let output = self
+            .run(program, &args)
+            .await
+            .map_err(|e| spawn_error(program, e))?;
+        if !output.status.success() && program != "pacman" {
+            return Ok(None);
+        }
         let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        let installed = match program {
+            "pacman" => Self::parse_pacman_kernels(&stdout),
+            "dpkg-query" => Self::parse_dpkg_kernels(&stdout),
+            _ => stdout.lines().map(str::trim).map(str::to_string).collect(),
+        };
 
-        // Combine stdout and stderr as nixos-rebuild outputs to both
-        let combined_output = format!("{}\n{}", stdout, stderr);
+        Ok(Self::compare_kernels(scheme, &running, &installed))
+    }
 
-        // Parse output to detect changes
-        self.parse_nixos_rebuild_output(&combined_output)
+    /// Kernel releases (as `uname -r` prints them) of `pacman -Q` output.
+    ///
+    /// `linux 6.9.7.arch1-1` boots as `6.9.7-arch1-1`, `linux-lts 6.6.30-1`
+    /// as `6.6.30-1-lts`.
+    fn parse_pacman_kernels(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let (package, version) = line.trim().split_once(' ')?;
+                let flavor = package.strip_prefix("linux")?;
+                let (numbers, rest) = match version.find(|c: char| c.is_ascii_alphabetic()) {
+                    Some(at) if version[..at].ends_with('.') => {
+                        (&version[..at - 1], format!("-{}", &version[at..]))
+                    }
+                    _ => match version.split_once('-') {
+                        Some((numbers, pkgrel)) => (numbers, format!("-{}", pkgrel)),
+                        None => (version, String::new()),
+                    },
+                };
+                // The kernel always reports three components: 6.10 is 6.10.0
+                let zero = if numbers.matches('.').count() == 1 {
+                    ".0"
+                } else {
+                    ""
+                };
+                Some(format!("{}{}{}{}", numbers, zero, rest, flavor))
+            })
+            .collect()
     }
 
-    /// Parse flake update output to extract input changes
-    fn parse_flake_updates(&self, output: &str) -> Vec<PackageUpdate> {
-        let mut updates = Vec::new();
+    /// Kernel releases of installed `linux-image-<release>` packages, from
+    /// `dpkg-query -f '${Status} ${Package}\n'`
+    fn parse_dpkg_kernels(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("install ok installed "))
+            .filter_map(|package| package.strip_prefix("linux-image-"))
+            .map(str::to_string)
+            .collect()
+    }
 
-        for cap in FLAKE_UPDATE_REGEX.captures_iter(output) {
-            if let (Some(input_name), Some(old_ref), Some(new_ref)) =
-                (cap.get(1), cap.get(2), cap.get(3))
-            {
-                let input_name = input_name.as_str();
-                let old_ref_str = old_ref.as_str();
-                let new_ref_str = new_ref.as_str();
+    /// Newest installed kernel of the running flavor, when it is not the
+    /// running one
+    fn compare_kernels(
+        scheme: VersionScheme,
+        running: &str,
+        installed: &[String],
+    ) -> Option<KernelMismatch> {
+        let flavor = kernel_flavor(running);
+        let newest = installed
+            .iter()
+            .filter(|release| kernel_flavor(release) == flavor)
+            .max_by(|a, b| version::compare(scheme, a, b).unwrap_or(std::cmp::Ordering::Equal))?;
+
+        let outdated = newest != running
+            && version::compare(scheme, newest, running) != Some(std::cmp::Ordering::Less);
+        outdated.then(|| KernelMismatch {
+            running: running.to_string(),
+            installed: newest.clone(),
+        })
+    }
 
-                // Extract commit hashes if present (first 7 chars)
-                let old_version = Self::extract_commit_hash(old_ref_str);
-                let new_version = Self::extract_commit_hash(new_ref_str);
+    /// Parse `-Qm` output (`name version` per line) into a set of names
+    fn parse_foreign_packages(output: &str) -> HashSet<String> {
+        output
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_string)
+            .collect()
+    }
 
-                updates.push(PackageUpdate {
-                    name: format!("flake:{}", input_name),
-                    current_version: old_version,
-                    new_version,
-                    is_aur: false,
-                });
+    async fn check_aur_updates(&self) -> Result<Vec<PackageUpdate>> {
+        let (cmd, args) = match self.package_manager {
+            PackageManager::Pacman => return Ok(Vec::new()),
+            PackageManager::Paru => ("paru", vec!["-Qu", "--aur"]),
+            PackageManager::Yay => ("yay", vec!["-Qu", "--aur"]),
+            // Other package managers don't have AUR support
+            _ => return Ok(Vec::new()),
+        };
+
+        if self.options.aur_backend == AurCheckBackend::Rpc {
+            match self.check_aur_rpc_updates().await {
+                Ok(updates) => return Ok(updates),
+                Err(e) => crate::check_log::warn(format!(
+                    "AUR RPC check failed, falling back to {}: {}",
+                    cmd, e
+                )),
             }
         }
 
-        updates
+        self.parse_update_output(cmd, args, true).await
     }
 
-    /// Extract commit hash from git reference (first 7 chars)
-    fn extract_commit_hash(git_ref: &str) -> String {
-        // Try to extract hash from various formats:
-        // - github:NixOS/nixpkgs/abc123def456...
-        // - abc123def456789012345678901234567890
-        if let Some(hash_start) = git_ref.rfind('/') {
-            let hash = &git_ref[hash_start + 1..];
-            if hash.len() >= 7 {
-                return hash[..7].to_string();
+    /// AUR updates without the helper's per-package queries: the foreign
+    /// packages are looked up in as few RPC `info` requests as the URL
+    /// length limit allows, and their versions compared locally.
+    ///
+    /// Foreign packages the AUR does not know (local builds) are skipped.
+    async fn check_aur_rpc_updates(&self) -> Result<Vec<PackageUpdate>> {
+        let output = self
+            .run("pacman", ["-Qm"])
+            .await
+            .map_err(|e| spawn_error("pacman", e))?;
+        // -Qm exits 1 when there are no foreign packages, so only stdout matters
+        let installed = Self::parse_installed_versions(&String::from_utf8_lossy(&output.stdout));
+
+        let mut latest = HashMap::new();
+        for url in Self::aur_rpc_urls(installed.iter().map(|(name, _)| name.as_str())) {
+            let output = self
+                .run("curl", ["-fsS", "--max-time", "20", url.as_str()])
+                .await
+                .map_err(|e| spawn_error("curl", e))?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "AUR RPC request failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
             }
+            latest.extend(Self::parse_aur_rpc_versions(&String::from_utf8_lossy(
+                &output.stdout,
+            ))?);
         }
 
-        // If it's already a hash
-        if git_ref.len() >= 7 && git_ref.chars().all(|c| c.is_ascii_hexdigit()) {
-            return git_ref[..7].to_string();
+        Ok(installed
+            .into_iter()
+            .filter_map(|(name, current_version)| {
+                let new_version = latest.remove(&name)?;
+                let is_newer =
+                    match version::compare(VersionScheme::Pacman, &new_version, &current_version) {
+                        Some(ordering) => ordering == std::cmp::Ordering::Greater,
+                        None => new_version != current_version,
+                    };
+                is_newer.then(|| PackageUpdate {
+                    name,
+                    current_version,
+                    new_version,
+                    is_aur: true,
+                    is_security: false,
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+
+    /// `name version` pairs of `pacman -Q` style output
+    fn parse_installed_versions(output: &str) -> Vec<(String, String)> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                Some((parts.next()?.to_string(), parts.next()?.to_string()))
+            })
+            .collect()
+    }
+
+    /// RPC `info` URLs covering `names`, each at most `AUR_RPC_MAX_URL_LEN`
+    /// long unless a single name is longer
+    fn aur_rpc_urls<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut urls = Vec::new();
+        let mut url = String::from(AUR_RPC_INFO_URL);
+        for name in names {
+            // Brackets are encoded so curl does not read them as a glob
+            let arg = format!("arg%5B%5D={}", name.replace('+', "%2B"));
+            let has_args = url.len() > AUR_RPC_INFO_URL.len();
+            if has_args && url.len() + 1 + arg.len() > AUR_RPC_MAX_URL_LEN {
+                urls.push(std::mem::replace(&mut url, String::from(AUR_RPC_INFO_URL)));
+            }
+            if url.len() > AUR_RPC_INFO_URL.len() {
+                url.push('&');
+            }
+            url.push_str(&arg);
         }
+        if url.len() > AUR_RPC_INFO_URL.len() {
+            urls.push(url);
+        }
+        urls
+    }
 
-        // Fallback: use the whole string (truncated if necessary)
-        if git_ref.len() > 12 {
-            git_ref[..12].to_string()
-        } else {
-            git_ref.to_string()
+    /// Name to version map of an RPC `info` response
+    fn parse_aur_rpc_versions(json: &str) -> Result<HashMap<String, String>> {
+        let response: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| anyhow!("Invalid AUR RPC response: {}", e))?;
+        if let Some(error) = response["error"].as_str() {
+            return Err(anyhow!("AUR RPC error: {}", error));
         }
+        let results = response["results"]
+            .as_array()
+            .ok_or_else(|| anyhow!("AUR RPC response has no results"))?;
+        Ok(results
+            .iter()
+            .filter_map(|result| {
+                Some((
+                    result["Name"].as_str()?.to_string(),
+                    result["Version"].as_str()?.to_string(),
+                ))
+            })
+            .collect())
     }
 
-    async fn check_nixos_flakes(
+    async fn parse_update_output(
         &self,
-        config: &crate::config::NixOSConfig,
+        cmd: &str,
+        args: Vec<&str>,
+        is_aur: bool,
     ) -> Result<Vec<PackageUpdate>> {
-        let config_path = &config.config_path;
-        let flake_lock_path = std::path::Path::new(config_path).join("flake.lock");
-
-        // Check if flake.lock exists
-        if !flake_lock_path.exists() {
-            return Err(anyhow!(
-                "flake.lock not found in {}. Run 'nix flake update' first.",
-                config_path
-            ));
-        }
-
-        let mut all_updates = Vec::new();
-
-        // Check what flake input updates are available (dry-run)
-        let update_check = TokioCommand::new("nix")
-            .args(["flake", "update", "--dry-run", config_path])
-            .output()
-            .await;
+        let output = self
+            .run(cmd, &args)
+            .await
+            .map_err(|e| spawn_error(cmd, e))?;
 
-        if let Ok(output) = update_check {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let combined = format!("{}\n{}", stdout, stderr);
+        // Exit code of a failed command whose output is parsed anyway
+        let mut failed_exit = None;
 
-            let flake_updates = self.parse_flake_updates(&combined);
-            all_updates.extend(flake_updates);
-        }
+        if !output.status.success() {
+            let exit_code = output.status.code().unwrap_or(-1);
 
-        // Build flake reference with optional hostname
-        let flake_ref = match &config.hostname {
-            Some(h) if !h.is_empty() => format!("{}#{}", config_path, h),
-            _ => format!("{}#", config_path),
-        };
+            // The AUR helpers list their official updates with pacman's checkupdates
+            let lister = if cmd == "checkupdates" {
+                PackageManager::Pacman
+            } else {
+                self.package_manager
+            };
 
-        // Always check what derivations would be rebuilt, even if flake inputs
-        // are unchanged -- local configuration changes also require rebuilding
-        let rebuild_output = TokioCommand::new("nixos-rebuild")
-            .args(["dry-build", "--flake", &flake_ref])
-            .output()
-            .await;
-
-        if let Ok(output) = rebuild_output {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let combined_output = format!("{}\n{}", stdout, stderr);
+            if lister.no_updates_exit_code() == Some(exit_code) {
+                return Ok(Vec::new());
+            }
+            // An "updates available" code is parsed like a success
+            if lister.updates_available_exit_code() != Some(exit_code) {
+                // Any other exit code might still have valid output for some package managers
+                // Check if we have stdout output before failing
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                if stdout.trim().is_empty() {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    crate::check_log::warn(format!(
+                        "Update check failed with exit code {}: {}",
+                        exit_code, stderr
+                    ));
+                    return Err(anyhow!(
+                        "Failed to check for updates (exit {}): {}",
+                        exit_code,
+                        stderr
+                    ));
+                }
+                // Otherwise continue to parse the output
+                failed_exit = Some(exit_code);
+            }
+        }
 
-            if let Ok(rebuild_updates) = self.parse_nixos_rebuild_output(&combined_output) {
-                all_updates.extend(rebuild_updates);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let packages = self.parse_output_lines(&stdout, is_aur);
+
+        if let Some(exit_code) = failed_exit {
+            if packages.is_empty() {
+                return Err(CheckError::ParseError(format!(
+                    "{} exited with code {} without listing any updates",
+                    cmd, exit_code
+                ))
+                .into());
             }
         }
 
-        Ok(all_updates)
+        Ok(packages)
     }
 
-    fn parse_nixos_rebuild_output(&self, output: &str) -> Result<Vec<PackageUpdate>> {
-        let mut updates = Vec::new();
-        let mut in_build_section = false;
-        let mut in_fetch_section = false;
+    /// Parse the full output of an update listing command
+    fn parse_output_lines(&self, output: &str, is_aur: bool) -> Vec<PackageUpdate> {
+        let mut packages = Vec::new();
+        let output = clean_terminal_output(output);
 
         for line in output.lines() {
-            // Detect section headers
-            if line.contains("derivations will be built")
-                || line.contains("derivation will be built")
+            // dnf lists packages replaced by others after the updates; those
+            // are not upgrades of installed packages
+            if self.package_manager == PackageManager::Dnf
+                && line.starts_with("Obsoleting Packages")
             {
-                in_build_section = true;
-                in_fetch_section = false;
-                continue;
-            }
-            if line.contains("paths will be fetched") || line.contains("path will be fetched") {
-                in_build_section = false;
-                in_fetch_section = true;
-                continue;
+                break;
             }
 
-            // Parse individual store paths (indented lines starting with /nix/store/)
-            let trimmed = line.trim();
-            if trimmed.starts_with("/nix/store/") && (in_build_section || in_fetch_section) {
-                if let Some(update) =
-                    Self::parse_nix_store_path(trimmed, if in_build_section { "build" } else { "fetch" })
-                {
-                    updates.push(update);
+            // nala prints the installed version on a tree line below the package:
+            // "└── is upgradable from 7.88.1-10+deb12u4"
+            if self.package_manager == PackageManager::Nala {
+                let detail = line.trim_start_matches(|c: char| !c.is_alphanumeric());
+                if let Some(from) = detail.strip_prefix("is upgradable from ") {
+                    if let Some(package) = packages.last_mut() {
+                        package.current_version = from.trim().to_string();
+                    }
+                    continue;
                 }
-                continue;
             }
 
-            // A non-indented, non-empty line ends the current section
-            if !trimmed.is_empty() && !line.starts_with(' ') && !line.starts_with('\t') {
-                in_build_section = false;
-                in_fetch_section = false;
+            if let Some(package) = self.parse_package_line(line, is_aur) {
+                packages.push(package);
             }
         }
 
-        // If no individual derivations were found, check if system is up to date
-        if updates.is_empty()
-            && (output.contains("up to date") || output.contains("already built"))
-        {
-            return Ok(Vec::new());
+        if let Some(scheme) = self.version_scheme() {
+            packages.retain(|package| {
+                let is_upgrade =
+                    version::compare(scheme, &package.new_version, &package.current_version)
+                        .map_or(true, |ordering| ordering == std::cmp::Ordering::Greater);
+                if !is_upgrade {
+                    crate::check_log::warn(format!(
+                        "Skipping {}: {} is not newer than the installed {}",
+                        package.name, package.new_version, package.current_version
+                    ));
+                }
+                is_upgrade
+            });
         }
 
-        Ok(updates)
+        packages
     }
 
-    /// Parse a nix store path into a package name and version.
-    ///
-    /// Store paths follow the format: `/nix/store/<32-char-hash>-<name>-<version>.drv`
-    /// The version is the trailing hyphen-separated segments that start with a digit.
-    fn parse_nix_store_path(path: &str, action: &str) -> Option<PackageUpdate> {
-        // Strip /nix/store/<hash>- prefix (hash is 32 chars)
-        let after_store = path.strip_prefix("/nix/store/")?;
+    /// How this manager orders versions, for skipping listings that would
+    /// not be upgrades (rollbacks, epoch mix-ups)
+    fn version_scheme(&self) -> Option<VersionScheme> {
+        match self.package_manager {
+            PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay => {
+                Some(VersionScheme::Pacman)
+            }
+            PackageManager::Apt | PackageManager::Nala => Some(VersionScheme::Dpkg),
+            PackageManager::Dnf | PackageManager::Zypper => Some(VersionScheme::Rpm),
+            _ => None,
+        }
+    }
 
-        // The hash is 32 lowercase hex chars followed by a hyphen
-        if after_store.len() < 34 || after_store.as_bytes()[32] != b'-' {
+    fn parse_package_line(&self, line: &str, is_aur: bool) -> Option<PackageUpdate> {
+        // Skip header lines
+        if line.starts_with("Listing...")
+            || line.starts_with("Done")
+            || line.starts_with("WARNING:")
+            || line.starts_with("S |")
+            || line.starts_with("--+")
+            || line.trim().is_empty()
+        {
             return None;
         }
-        let name_version = &after_store[33..];
 
-        // Strip .drv suffix if present
-        let name_version = name_version.strip_suffix(".drv").unwrap_or(name_version);
+        match self.package_manager {
+            // Arch-based: "package 1.0.0-1 -> 1.0.1-1" or "package 1.0.1-1"
+            PackageManager::Pacman | PackageManager::Paru | PackageManager::Yay => {
+                if line.contains(" -> ") {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() >= 4 && parts[2] == "->" {
+                        return Some(PackageUpdate {
+                            name: parts[0].to_string(),
+                            current_version: parts[1].to_string(),
+                            new_version: parts[3].to_string(),
+                            is_aur,
+                            is_security: false,
+                            ..Default::default()
+                        });
+                    }
+                } else {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() >= 2 {
+                        return Some(PackageUpdate {
+                            name: parts[0].to_string(),
+                            current_version: "unknown".to_string(),
+                            new_version: parts[1].to_string(),
+                            is_aur,
+                            is_security: false,
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
 
-        // Split on hyphens and find where the version starts.
-        // Version segments start with a digit (e.g., "6.1.0", "26.05.20260208").
-        let parts: Vec<&str> = name_version.split('-').collect();
-        let version_start = parts
-            .iter()
-            .position(|p| p.starts_with(|c: char| c.is_ascii_digit()));
+            // APT: "package/suite version arch [upgradable from: old-version]"
+            // or, when simulating dist-upgrade, "Inst package [old] (new suite [arch])"
+            // nala: "curl 7.88.1-10+deb12u5 [Debian/stable-security main]"
+            PackageManager::Nala => {
+                let (head, origin) = line.split_once(" [")?;
+                let mut parts = head.split_whitespace();
+                let name = parts.next()?.to_string();
+                let new_version = parts.next()?.to_string();
+                if parts.next().is_some() {
+                    return None;
+                }
 
-        let (name, version) = match version_start {
-            Some(0) => {
-                // Entire string is version-like, use the full thing as name
-                (name_version.to_string(), "unknown".to_string())
+                let suites: Vec<&str> = origin
+                    .split_whitespace()
+                    .next()
+                    .and_then(|origin| origin.split_once('/'))
+                    .map(|(_, suite)| suite.split(',').collect())
+                    .unwrap_or_default();
+                if !self.apt_origin_allowed(&suites) {
+                    return None;
+                }
+
+                return Some(PackageUpdate {
+                    name,
+                    current_version: "unknown".to_string(),
+                    new_version,
+                    is_aur: false,
+                    is_security: suites.iter().any(|suite| suite.ends_with("-security")),
+                    ..Default::default()
+                });
             }
-            Some(idx) => {
-                let name = parts[..idx].join("-");
-                let version = parts[idx..].join("-");
-                (name, version)
+
+            PackageManager::Apt => {
+                if let Some(rest) = line.strip_prefix("Inst ") {
+                    if !self.apt_origin_allowed(&Self::apt_simulation_suites(rest)) {
+                        return None;
+                    }
+                    return Self::parse_apt_simulation_line(rest);
+                }
+
+                if line.contains("[upgradable from:") {
+                    // Split by '/' to get package name
+                    let name = line.split('/').next()?.to_string();
+
+                    let suites = Self::apt_list_suites(line);
+                    if !self.apt_origin_allowed(&suites) {
+                        return None;
+                    }
+
+                    // Updates from a security pocket list it among the suites,
+                    // e.g. "jammy-updates,jammy-security"
+                    let is_security = suites.iter().any(|suite| suite.ends_with("-security"));
+
+                    // Extract new version (between '/' and architecture)
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    let new_version = if parts.len() >= 2 {
+                        parts[1].to_string()
+                    } else {
+                        "unknown".to_string()
+                    };
+
+                    // Extract old version from [upgradable from: X]
+                    let current_version = if let Some(from_idx) = line.find("[upgradable from: ") {
+                        let start = from_idx + "[upgradable from: ".len();
+                        if let Some(end_idx) = line[start..].find(']') {
+                            line[start..start + end_idx].to_string()
+                        } else {
+                            "unknown".to_string()
+                        }
+                    } else {
+                        "unknown".to_string()
+                    };
+
+                    return Some(PackageUpdate {
+                        name,
+                        current_version,
+                        new_version,
+                        is_aur: false,
+                        is_security,
+                        ..Default::default()
+                    });
+                }
             }
-            None => {
-                // No version segment found
-                (name_version.to_string(), action.to_string())
+
+            // DNF: "package.arch version repo" (3 columns)
+            PackageManager::Dnf => {
+                // Repository metadata status, e.g. "Last metadata expiration check: ..."
+                if line.starts_with("Last metadata") || line.starts_with("Security:") {
+                    return None;
+                }
+
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() >= 2 && parts[0].contains('.') {
+                    // First part is "package.arch"; names may contain dots (python3.12)
+                    let name = parts[0].rsplit_once('.')?.0.to_string();
+                    let new_version = parts[1].to_string();
+
+                    return Some(PackageUpdate {
+                        name,
+                        current_version: "unknown".to_string(),
+                        new_version,
+                        is_aur: false,
+                        is_security: false,
+                        ..Default::default()
+                    });
+                }
             }
-        };
 
-        Some(PackageUpdate {
-            name,
-            current_version: "unknown".to_string(),
-            new_version: version,
-            is_aur: false,
-        })
-    }
+            // Zypper: table format with columns
+            // "S | Repository | Name | Current Version | Available Version | Arch",
+            // or the short "S | Name | Type | Version | Arch"
+            PackageManager::Zypper | PackageManager::TransactionalUpdate => {
+                let parts: Vec<&str> = line.split('|').map(str::trim).collect();
+                let columns = if parts.len() >= 6 {
+                    Some((parts[2], parts[3], parts[4]))
+                } else if parts.len() >= 4 {
+                    Some((parts[1], "unknown", parts[3]))
+                } else {
+                    None
+                };
+                if let Some((name, current_version, new_version)) = columns {
+                    return Some(PackageUpdate {
+                        name: name.to_string(),
+                        current_version: current_version.to_string(),
+                        new_version: new_version.to_string(),
+                        is_aur: false,
+                        is_security: false,
+                        ..Default::default()
+                    });
+                }
+            }
 
-    async fn check_nixos_updates(
-        &self,
-        config: &crate::config::NixOSConfig,
-    ) -> Result<Vec<PackageUpdate>> {
-        match config.mode {
-            crate::config::NixOSMode::Channels => self.check_nixos_channels().await,
-            crate::config::NixOSMode::Flakes => self.check_nixos_flakes(config).await,
+            // APK: "name-version-rN arch {origin} (license) [upgradable from: name-oldversion-rN]"
+            PackageManager::Apk => {
+                if line.contains("[upgradable from:") {
+                    let pkg_info = line.split_whitespace().next()?;
+                    let (name, new_version) = Self::split_apk_package(pkg_info)?;
+
+                    let current_version =
+                        if let Some(from_idx) = line.find("[upgradable from: ") {
+                            let start = from_idx + "[upgradable from: ".len();
+                            if let Some(end_idx) = line[start..].find(']') {
+                                let old = &line[start..start + end_idx];
+                                Self::split_apk_package(old)
+                                    .map(|(_, version)| version)
+                                    .unwrap_or_else(|| old.to_string())
+                            } else {
+                                "unknown".to_string()
+                            }
+                        } else {
+                            "unknown".to_string()
+                        };
+
+                    return Some(PackageUpdate {
+                        name: name.to_string(),
+                        current_version,
+                        new_version,
+                        is_aur: false,
+                        is_security: false,
+                        ..Default::default()
+                    });
+                }
+            }
+
+            // eopkg: "name<padding> - summary", where builds that print the
+            // new version put it after the name. The first " - " ends the
+            // package part, so a summary may contain it too; notices like
+            // "No packages to upgrade." have no separator.
+            PackageManager::Eopkg => {
+                let (package, _summary) = line.split_once(" - ")?;
+                let mut words = package.split_whitespace();
+                let name = words.next()?;
+                let new_version = words.next().unwrap_or("latest");
+                if words.next().is_none() {
+                    return Some(PackageUpdate {
+                        name: name.to_string(),
+                        current_version: "unknown".to_string(),
+                        new_version: new_version.to_string(),
+                        is_aur: false,
+                        is_security: false,
+                        ..Default::default()
+                    });
+                }
+            }
+
+            // Flatpak: "app-id\tversion\tbranch\torigin" (FLATPAK_UPDATE_COLUMNS)
+            PackageManager::Flatpak => {
+                let mut columns = line.split('\t').map(str::trim);
+                let application = columns.next().filter(|id| !id.is_empty())?;
+                let version = columns.next().unwrap_or_default();
+                let branch = columns.next().unwrap_or_default();
+                // Runtimes often carry no version; the branch identifies them
+                let new_version = if !version.is_empty() {
+                    version
+                } else if !branch.is_empty() {
+                    branch
+                } else {
+                    "latest"
+                };
+
+                if let Some(origin) = columns.next() {
+                    return Some(PackageUpdate {
+                        name: application.to_string(),
+                        current_version: "unknown".to_string(),
+                        new_version: new_version.to_string(),
+                        is_aur: false,
+                        is_security: false,
+                        origin: (!origin.is_empty()).then(|| origin.to_string()),
+                        ..Default::default()
+                    });
+                }
+            }
+
+            // NixOS: Handled separately by check_nixos_updates, never reaches this function
+            // Nix profile, home-manager and Guix: handled by their own check functions
+            PackageManager::NixOS
+            | PackageManager::NixProfile
+            | PackageManager::HomeManager
+            | PackageManager::Guix => {
+                return None;
+            }
         }
+
+        None
+    }
+
+    /// Split an apk package identifier into name and version.
+    ///
+    /// apk prints packages as `<name>-<version>-r<revision>`, where the version
+    /// always starts with a digit, e.g. `py3-setuptools-68.0.0-r1`. The returned
+    /// version keeps the revision (`68.0.0-r1`).
+    fn split_apk_package(pkg: &str) -> Option<(&str, String)> {
+        // Strip the trailing "-rN" revision first so it isn't mistaken for the version
+        let (base, revision) = match pkg.rsplit_once("-r") {
+            Some((base, rev)) if !rev.is_empty() && rev.chars().all(|c| c.is_ascii_digit()) => {
+                (base, Some(rev))
+            }
+            _ => (pkg, None),
+        };
+
+        let (name, version) = base.rsplit_once('-')?;
+        if name.is_empty() || !version.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let version = match revision {
+            Some(rev) => format!("{}-r{}", version, rev),
+            None => version.to_string(),
+        };
+        Some((name, version))
+    }
+
+    /// Check if passwordless sudo is configured for the current user
+    async fn check_passwordless_sudo(&self) -> Result<bool> {
+        let output = self.run("sudo", ["-n", "true"]).await?; // -n = non-interactive
+        Ok(output.status.success())
+    }
+
+    async fn check_nixos_channels(
+        &self,
+        config: &crate::config::NixOSConfig,
+    ) -> Result<Vec<PackageUpdate>> {
+        // Try PolicyKit first, fall back to passwordless sudo check if not available.
+        // PolicyKit only runs commands on this machine.
+        if self.runner.is_local() && crate::polkit::PolkitAuth::is_available().await {
+            // Use PolicyKit for privilege escalation
+            match crate::polkit::execute_privileged(
+                "nixos-rebuild",
+                &["dry-build", "--upgrade"],
+                crate::polkit::POLKIT_ACTION_CHECK,
+                "Authentication required to check for NixOS updates",
+            )
+            .await
+            {
+                Ok(output) => {
+                    if !output.status.success() {
+                        let stderr = String::from_utf8_lossy(&output.stderr);
+                        return Err(anyhow!("Failed to check NixOS updates: {}", stderr));
+                    }
+
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    let combined_output = format!("{}\n{}", stdout, stderr);
+                    return self.parse_nixos_rebuild_output(&combined_output, config.update_count);
+                }
+                Err(e) => {
+                    crate::check_log::warn(format!(
+                        "PolicyKit execution failed: {}, trying sudo fallback",
+                        e
+                    ));
+                    // Continue to sudo fallback below
+                }
+            }
+        }
+
+        // Fallback to sudo if PolicyKit unavailable or failed
+        // Check for passwordless sudo first
+        let passwordless = self.check_passwordless_sudo().await.unwrap_or(false);
+
+        // Otherwise a graphical prompt on this machine can ask for the password
+        let askpass = if !passwordless && self.options.sudo_askpass && self.runner.is_local() {
+            find_askpass()
+        } else {
+            None
+        };
+
+        if !passwordless && askpass.is_none() {
+            return Err(CheckError::PermissionDenied(
+                "NixOS channels mode requires passwordless sudo or PolicyKit.\n\
+                 \n\
+                 Option 1 (Recommended): PolicyKit is not available or failed.\n\
+                 Install PolicyKit and ensure pkexec is available.\n\
+                 \n\
+                 Option 2: Configure passwordless sudo by adding to /etc/sudoers.d/nixos-rebuild:\n\
+                 %wheel ALL=(ALL) NOPASSWD: /run/current-system/sw/bin/nixos-rebuild\n\
+                 \n\
+                 Option 3: Install a graphical askpass helper (e.g. ksshaskpass or\n\
+                 ssh-askpass) or point SUDO_ASKPASS at one to be asked for the password."
+                    .to_string(),
+            )
+            .into());
+        }
+
+        // Run nixos-rebuild dry-build with upgrade flag to show package statistics
+        let output = match askpass {
+            Some(askpass) => {
+                // sudo is handed the password, so it has to be the real one
+                let sudo = which_async("sudo")
+                    .await
+                    .filter(|path| is_safe_executable_path(path))
+                    .ok_or_else(|| anyhow!("sudo was not found in a trusted location"))?;
+                self.run_with_env(
+                    &sudo,
+                    ["-A", "nixos-rebuild", "dry-build", "--upgrade"],
+                    &[("SUDO_ASKPASS", askpass.as_str())],
+                )
+                .await?
+            }
+            None => {
+                self.run("sudo", ["nixos-rebuild", "dry-build", "--upgrade"])
+                    .await?
+            }
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            // Check if it's a permission issue
+            if stderr.contains("not allowed")
+                || stderr.contains("password")
+                || stderr.contains("sudo")
+            {
+                return Err(CheckError::PermissionDenied(
+                    "Use PolicyKit or configure passwordless sudo for nixos-rebuild:\n\
+                     Add to /etc/sudoers.d/nixos-rebuild:\n\
+                     %wheel ALL=(ALL) NOPASSWD: /run/current-system/sw/bin/nixos-rebuild"
+                        .to_string(),
+                )
+                .into());
+            }
+
+            return Err(anyhow!("Failed to check NixOS updates: {}", stderr));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        // Combine stdout and stderr as nixos-rebuild outputs to both
+        let combined_output = format!("{}\n{}", stdout, stderr);
+
+        // Parse output to detect changes
+        self.parse_nixos_rebuild_output(&combined_output, config.update_count)
+    }
+
+    /// Parse flake update output to extract input changes
+    fn parse_flake_updates(&self, output: &str) -> Vec<PackageUpdate> {
+        let mut updates = Vec::new();
+
+        for cap in FLAKE_UPDATE_REGEX.captures_iter(output) {
+            if let (Some(input_name), Some(old_ref), Some(new_ref)) =
+                (cap.get(1), cap.get(2), cap.get(3))
+            {
+                let input_name = input_name.as_str();
+                let old_ref_str = old_ref.as_str();
+                let new_ref_str = new_ref.as_str();
+
+                // Extract commit hashes if present (first 7 chars)
+                let old_version = Self::extract_commit_hash(old_ref_str);
+                let new_version = Self::extract_commit_hash(new_ref_str);
+
+                updates.push(PackageUpdate {
+                    name: format!("flake:{}", input_name),
+                    current_version: old_version,
+                    new_version,
+                    is_aur: false,
+                    is_security: false,
+                    ..Default::default()
+                });
+            }
+        }
+
+        updates
+    }
+
+    /// Explanation for a nix or nixos-rebuild failure caused by missing
+    /// flake support, e.g. a NixOS release older than 20.09
+    fn flake_support_error(stderr: &str) -> Option<&'static str> {
+        let lower = stderr.to_lowercase();
+        if lower.contains("experimental nix feature 'flakes' is disabled") {
+            Some(
+                "Flakes are disabled in nix. Enable them with \
+                 'experimental-features = nix-command flakes' in nix.conf, \
+                 or switch to Channels mode in Settings.",
+            )
+        } else if ((lower.contains("unknown option") || lower.contains("unrecognised flag"))
+            && lower.contains("--flake"))
+            || lower.contains("'flake' is not a recognised command")
+        {
+            Some(
+                "This NixOS version does not support flakes in nix or nixos-rebuild. \
+                 Upgrade to NixOS 20.09 or later, or switch to Channels mode in Settings.",
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Replace the current version of flake input updates with the revision
+    /// `flake.lock` actually pins, from `nix flake metadata --json`.
+    ///
+    /// Failures are only logged: the versions from the dry-run output stay.
+    async fn apply_flake_locked_revs(&self, flake_dir: &str, updates: &mut [PackageUpdate]) {
+        if updates.is_empty() {
+            return;
+        }
+
+        let output = match self.run("nix", ["flake", "metadata", "--json", flake_dir]).await {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                crate::check_log::warn(format!(
+                    "nix flake metadata failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+                return;
+            }
+            Err(e) => {
+                crate::check_log::warn(format!("Failed to run nix flake metadata: {}", e));
+                return;
+            }
+        };
+
+        let revs = Self::parse_flake_locked_revs(&String::from_utf8_lossy(&output.stdout));
+        for update in updates {
+            let input = update.name.strip_prefix("flake:").unwrap_or(&update.name);
+            if let Some(rev) = revs.get(input) {
+                update.current_version = Self::extract_commit_hash(rev);
+            }
+        }
+    }
+
+    /// Map each root input of `nix flake metadata --json` output to its
+    /// locked revision.
+    ///
+    /// `locks.nodes.<root>.inputs` names each input's node; inputs that
+    /// `follows` another one (a path array) are skipped.
+    fn parse_flake_locked_revs(json: &str) -> HashMap<String, String> {
+        let Ok(metadata) = serde_json::from_str::<serde_json::Value>(json) else {
+            return HashMap::new();
+        };
+        let locks = &metadata["locks"];
+        let nodes = &locks["nodes"];
+        let root = locks["root"].as_str().unwrap_or("root");
+
+        let Some(inputs) = nodes[root]["inputs"].as_object() else {
+            return HashMap::new();
+        };
+        inputs
+            .iter()
+            .filter_map(|(input, node)| {
+                let rev = nodes[node.as_str()?]["locked"]["rev"].as_str()?;
+                Some((input.clone(), rev.to_string()))
+            })
+            .collect()
+    }
+
+    /// Extract commit hash from git reference (first 7 chars)
+    fn extract_commit_hash(git_ref: &str) -> String {
+        // Try to extract hash from various formats:
+        // - github:NixOS/nixpkgs/abc123def456...
+        // - abc123def456789012345678901234567890
+        if let Some(hash_start) = git_ref.rfind('/') {
+            let hash = &git_ref[hash_start + 1..];
+            if hash.len() >= 7 {
+                return hash[..7].to_string();
+            }
+        }
+
+        // If it's already a hash
+        if git_ref.len() >= 7 && git_ref.chars().all(|c| c.is_ascii_hexdigit()) {
+            return git_ref[..7].to_string();
+        }
+
+        // Fallback: use the whole string (truncated if necessary)
+        if git_ref.len() > 12 {
+            git_ref[..12].to_string()
+        } else {
+            git_ref.to_string()
+        }
+    }
+
+    async fn check_nixos_flakes(
+        &self,
+        config: &crate::config::NixOSConfig,
+    ) -> Result<Vec<PackageUpdate>> {
+        let config_path = &config.config_path;
+        let flake_lock_path = std::path::Path::new(config_path).join("flake.lock");
+
+        // Check if flake.lock exists
+        if !flake_lock_path.exists() {
+            return Err(anyhow!(
+                "flake.lock not found in {}. Run 'nix flake update' first.",
+                config_path
+            ));
+        }
+
+        let mut all_updates = Vec::new();
+
+        // Check what flake input updates are available (dry-run)
+        let update_check = self
+            .run("nix", ["flake", "update", "--dry-run", config_path])
+            .await;
+
+        if let Ok(output) = update_check {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            // Without flake support in nix the nixos-rebuild --flake call
+            // below cannot work either
+            if !output.status.success() {
+                if let Some(message) = Self::flake_support_error(&stderr) {
+                    return Err(anyhow!(message));
+                }
+            }
+
+            let combined = format!("{}\n{}", stdout, stderr);
+            let flake_updates = self.parse_flake_updates(&combined);
+            all_updates.extend(flake_updates);
+        }
+        self.apply_flake_locked_revs(config_path, &mut all_updates)
+            .await;
+
+        // Build flake reference with optional hostname
+        let flake_ref = match &config.hostname {
+            Some(h) if !h.is_empty() => format!("{}#{}", config_path, h),
+            _ => format!("{}#", config_path),
+        };
+
+        // Always check what derivations would be rebuilt, even if flake inputs
+        // are unchanged -- local configuration changes also require rebuilding
+        let rebuild_output = self
+            .run("nixos-rebuild", ["dry-build", "--flake", &flake_ref])
+            .await;
+
+        if let Ok(output) = rebuild_output {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.status.success() {
+                if let Some(message) = Self::flake_support_error(&stderr) {
+                    return Err(anyhow!(message));
+                }
+            }
+            let combined_output = format!("{}\n{}", stdout, stderr);
+
+            if let Ok(rebuild_updates) =
+                self.parse_nixos_rebuild_output(&combined_output, config.update_count)
+            {
+                all_updates.extend(rebuild_updates);
+            }
+        }
+
+        Ok(all_updates)
+    }
+
+    /// Parse the store paths a dry-build lists, keeping the sections `count`
+    /// selects.
+    fn parse_nixos_rebuild_output(
+        &self,
+        output: &str,
+        count: crate::config::NixOSUpdateCount,
+    ) -> Result<Vec<PackageUpdate>> {
+        let mut updates = Vec::new();
+        let mut in_build_section = false;
+        let mut in_fetch_section = false;
+
+        for line in output.lines() {
+            // Detect section headers
+            if line.contains("derivations will be built")
+                || line.contains("derivation will be built")
+            {
+                in_build_section = true;
+                in_fetch_section = false;
+                continue;
+            }
+            if line.contains("paths will be fetched") || line.contains("path will be fetched") {
+                in_build_section = false;
+                in_fetch_section = true;
+                continue;
+            }
+
+            // Parse individual store paths (indented lines starting with /nix/store/)
+            let trimmed = line.trim();
+            let counted = (in_build_section && count.includes_builds())
+                || (in_fetch_section && count.includes_fetches());
+            if trimmed.starts_with("/nix/store/") && counted {
+                if let Some(update) =
+                    Self::parse_nix_store_path(trimmed, if in_build_section { "build" } else { "fetch" })
+                {
+                    updates.push(update);
+                }
+                continue;
+            }
+
+            // A non-indented, non-empty line ends the current section
+            if !trimmed.is_empty() && !line.starts_with(' ') && !line.starts_with('\t') {
+                in_build_section = false;
+                in_fetch_section = false;
+            }
+        }
+
+        // If no individual derivations were found, check if system is up to date
+        if updates.is_empty()
+            && (output.contains("up to date") || output.contains("already built"))
+        {
+            return Ok(Vec::new());
+        }
+
+        Ok(updates)
+    }
+
+    /// Parse a nix store path into a package name and version.
+    ///
+    /// Store paths follow the format: `/nix/store/<32-char-hash>-<name>-<version>.drv`
+    /// The version is the trailing hyphen-separated segments that start with a digit.
+    fn parse_nix_store_path(path: &str, action: &str) -> Option<PackageUpdate> {
+        // Strip /nix/store/<hash>- prefix (hash is 32 chars)
+        let after_store = path.strip_prefix("/nix/store/")?;
+
+        // The hash is 32 lowercase hex chars followed by a hyphen
+        if after_store.len() < 34 || after_store.as_bytes()[32] != b'-' {
+            return None;
+        }
+        let name_version = &after_store[33..];
+
+        // Strip .drv suffix if present
+        let name_version = name_version.strip_suffix(".drv").unwrap_or(name_version);
+
+        // Split on hyphens and find where the version starts.
+        // Version segments start with a digit (e.g., "6.1.0", "26.05.20260208").
+        let parts: Vec<&str> = name_version.split('-').collect();
+        let version_start = parts
+            .iter()
+            .position(|p| p.starts_with(|c: char| c.is_ascii_digit()));
+
+        let (name, version) = match version_start {
+            Some(0) => {
+                // Entire string is version-like, use the full thing as name
+                (name_version.to_string(), "unknown".to_string())
+            }
+            Some(idx) => {
+                let name = parts[..idx].join("-");
+                let version = parts[idx..].join("-");
+                (name, version)
+            }
+            None => {
+                // No version segment found
+                (name_version.to_string(), action.to_string())
+            }
+        };
+
+        Some(PackageUpdate {
+            name,
+            current_version: "unknown".to_string(),
+            new_version: version,
+            is_aur: false,
+            is_security: false,
+            ..Default::default()
+        })
+    }
+
+    /// List the user profile entries `nix profile upgrade` would move to a
+    /// newer flake revision, without changing the profile.
+    async fn check_nix_profile_updates(&self) -> Result<Vec<PackageUpdate>> {
+        let output = self
+            .run(
+                "nix",
+                [
+                    "--extra-experimental-features",
+                    "nix-command flakes",
+                    "profile",
+                    "upgrade",
+                    "--dry-run",
+                    ".*",
+                ],
+            )
+            .await
+            .map_err(|e| spawn_error("nix", e))?;
+
+        // nix reports the upgrades on stderr
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(anyhow!("Failed to check Nix profile updates: {}", stderr));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(Self::parse_nix_profile_upgrades(&format!("{}\n{}", stdout, stderr)))
+    }
+
+    /// Parse the "upgrading '<attr path>' from flake '<old>' to '<new>'" lines
+    /// of `nix profile upgrade`.
+    ///
+    /// The package name is the last attribute of the path
+    /// (`legacyPackages.x86_64-linux.hello` becomes `hello`) and the versions
+    /// are the short flake revisions.
+    fn parse_nix_profile_upgrades(output: &str) -> Vec<PackageUpdate> {
+        NIX_PROFILE_UPGRADE_REGEX
+            .captures_iter(output)
+            .filter(|cap| cap[2] != cap[3])
+            .map(|cap| {
+                let attr_path = &cap[1];
+                let name = attr_path.rsplit('.').next().unwrap_or(attr_path);
+                PackageUpdate {
+                    name: name.to_string(),
+                    current_version: Self::extract_commit_hash(&cap[2]),
+                    new_version: Self::extract_commit_hash(&cap[3]),
+                    is_aur: false,
+                    is_security: false,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// List the upgrades of the user profile with `guix package -u --dry-run`.
+    ///
+    /// They are relative to the channels of the last `guix pull`, which the
+    /// check does not run.
+    async fn check_guix_updates(&self) -> Result<Vec<PackageUpdate>> {
+        self.cached_metadata.store(true, Ordering::Relaxed);
+        let output = self
+            .run("guix", ["package", "-u", "--dry-run"])
+            .await
+            .map_err(|e| spawn_error("guix", e))?;
+
+        // guix reports the transaction on stderr
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(anyhow!("Failed to check Guix profile updates: {}", stderr));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(Self::parse_guix_upgrades(&format!("{}\n{}", stdout, stderr)))
+    }
+
+    /// Parse the "The following packages would be upgraded:" block of a
+    /// `guix package` dry run, one "name old → new store-item" row per
+    /// package; a non-default output is part of the name ("gcc:lib")
+    fn parse_guix_upgrades(output: &str) -> Vec<PackageUpdate> {
+        let mut packages = Vec::new();
+        let mut in_upgrades = false;
+        for line in output.lines() {
+            if line.contains("would be upgraded:") {
+                in_upgrades = true;
+                continue;
+            }
+            if !in_upgrades {
+                continue;
+            }
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            match columns.as_slice() {
+                [name, current, "→" | "->", new, ..] => packages.push(PackageUpdate {
+                    name: name.to_string(),
+                    current_version: current.to_string(),
+                    new_version: new.to_string(),
+                    is_aur: false,
+                    is_security: false,
+                    ..Default::default()
+                }),
+                // A blank line or the next block ends the list
+                _ => in_upgrades = false,
+            }
+        }
+        packages
+    }
+
+    /// Check what a home-manager switch would change.
+    ///
+    /// For flake configurations the pending flake input updates are listed
+    /// first; the derivations `home-manager build --dry-run` would build or
+    /// fetch are parsed like a NixOS dry-build.
+    async fn check_home_manager_updates(
+        &self,
+        config: &crate::config::NixOSConfig,
+    ) -> Result<Vec<PackageUpdate>> {
+        let hm_dir = config.home_manager_dir();
+        let mut all_updates = Vec::new();
+        let mut args = vec!["build", "--dry-run"];
+
+        if config.home_manager_uses_flake() {
+            let update_check = self
+                .run("nix", ["flake", "update", "--dry-run", &hm_dir])
+                .await;
+
+            if let Ok(output) = update_check {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                all_updates.extend(self.parse_flake_updates(&format!("{}\n{}", stdout, stderr)));
+            }
+            self.apply_flake_locked_revs(&hm_dir, &mut all_updates)
+                .await;
+
+            args.extend(["--flake", hm_dir.as_str()]);
+        }
+
+        let output = self
+            .run("home-manager", &args)
+            .await
+            .map_err(|e| spawn_error("home-manager", e))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(anyhow!("Failed to check home-manager updates: {}", stderr));
+        }
+
+        // home-manager passes nix's dry-run report through on stderr
+        all_updates.extend(self.parse_nixos_rebuild_output(
+            &format!("{}\n{}", stdout, stderr),
+            NixOSUpdateCount::BuildsAndFetches,
+        )?);
+        Ok(all_updates)
+    }
+
+    async fn check_nixos_updates(
+        &self,
+        config: &crate::config::NixOSConfig,
+    ) -> Result<Vec<PackageUpdate>> {
+        match config.mode {
+            crate::config::NixOSMode::Channels => self.check_nixos_channels(config).await,
+            crate::config::NixOSMode::Flakes => self.check_nixos_flakes(config).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::MockRunner;
+
+    /// Run a full check against canned command output
+    async fn mock_check(
+        package_manager: PackageManager,
+        runner: MockRunner,
+    ) -> Result<UpdateInfo, CheckError> {
+        let nixos_config = crate::config::NixOSConfig {
+            mode: crate::config::NixOSMode::Channels,
+            home_manager_path: Some("/nonexistent/home-manager".to_string()),
+            ..Default::default()
+        };
+        UpdateChecker::new(package_manager)
+            .with_runner(runner)
+            .check_updates(true, &nixos_config)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_check_pacman_pipeline() {
+        let runner = MockRunner::new().respond(
+            "checkupdates",
+            0,
+            "linux 6.9.7.arch1-1 -> 6.9.8.arch1-1\nmesa 1:24.1.2-1 -> 1:24.1.3-1\n",
+        );
+        let info = mock_check(PackageManager::Pacman, runner).await.unwrap();
+
+        assert_eq!(info.official_updates, 2);
+        assert_eq!(info.packages[1].name, "mesa");
+        assert_eq!(info.packages[1].current_version, "1:24.1.2-1");
+    }
+
+    #[tokio::test]
+    async fn test_check_pacman_no_updates_exit_code() {
+        // checkupdates exits 2 when there is nothing to update
+        let runner = MockRunner::new().respond("checkupdates", 2, "");
+        let info = mock_check(PackageManager::Pacman, runner).await.unwrap();
+
+        assert_eq!(info.total_updates, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_skips_errors_that_would_repeat() {
+        // Output without any update: a second run would read the same
+        let runner = MockRunner::new().respond("checkupdates", 1, "interrupted\n");
+        let calls = runner.calls();
+        let error = mock_check(PackageManager::Pacman, runner)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, CheckError::ParseError(_)));
+        let runs = |calls: &std::sync::Mutex<Vec<String>>| {
+            calls
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|line| *line == "checkupdates")
+                .count()
+        };
+        assert_eq!(runs(&calls), 1);
+
+        // An unexplained failure, like an unreachable mirror, is tried again
+        let runner = MockRunner::new().respond("checkupdates", 1, "");
+        let calls = runner.calls();
+        let error = mock_check(PackageManager::Pacman, runner)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, CheckError::Other(_)));
+        assert_eq!(runs(&calls), 2);
+    }
+
+    #[tokio::test]
+    async fn test_check_paru_pipeline() {
+        let runner = MockRunner::new()
+            .respond("checkupdates", 0, "glibc 2.39+r52-1 -> 2.40-1\n")
+            .respond("paru -Qu --aur", 0, "visual-studio-code-bin 1.90.0-1 -> 1.91.0-1\n");
+        let info = mock_check(PackageManager::Paru, runner).await.unwrap();
+
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.aur_updates, 1);
+        assert!(info.packages.iter().any(|p| p.is_aur && p.name == "visual-studio-code-bin"));
+
+        // paru exits 1 when no AUR package can be updated
+        let runner = MockRunner::new()
+            .respond("checkupdates", 2, "")
+            .respond("paru -Qu --aur", 1, "");
+        let info = mock_check(PackageManager::Paru, runner).await.unwrap();
+        assert_eq!(info.total_updates, 0);
+    }
+
+    #[tokio::test]
+    async fn test_custom_check_command() {
+        let runner = MockRunner::new().respond(
+            "sh -c 'checkupdates-with-aur --nocolor'",
+            0,
+            "linux 6.9.6.arch1-1 -> 6.9.7.arch1-1\nparu-bin 2.0.2-1 -> 2.0.3-1\n",
+        );
+        let info = UpdateChecker::with_options(
+            PackageManager::Pacman,
+            CheckOptions {
+                custom_check_command: Some("checkupdates-with-aur --nocolor".to_string()),
+                ..CheckOptions::default()
+            },
+        )
+        .with_runner(runner)
+        .check_updates(false, &crate::config::NixOSConfig::default())
+        .await
+        .unwrap();
+
+        assert_eq!(info.total_updates, 2);
+        assert_eq!(info.packages[1].name, "paru-bin");
+        assert_eq!(info.packages[1].new_version, "2.0.3-1");
+    }
+
+    #[tokio::test]
+    async fn test_command_exists() {
+        assert!(command_exists("/bin/sh -c true").await);
+        assert!(command_exists("LC_ALL=C /bin/sh").await);
+        assert!(command_exists("sh -c true").await);
+        assert!(!command_exists("/nonexistent/checkupdates-with-aur").await);
+        assert!(!command_exists("   ").await);
+    }
+
+    #[tokio::test]
+    async fn test_check_aur_rpc_backend() {
+        let checker = |runner| {
+            UpdateChecker::with_options(
+                PackageManager::Paru,
+                CheckOptions {
+                    aur_backend: AurCheckBackend::Rpc,
+                    ..CheckOptions::default()
+                },
+            )
+            .with_runner(runner)
+        };
+        let rpc = "curl -fsS --max-time 20 'https://aur.archlinux.org/rpc/v5/info?arg%5B%5D=paru&arg%5B%5D=my-local-build&arg%5B%5D=visual-studio-code-bin'";
+        let installed = "paru 2.0.3-1\nmy-local-build 1.0-1\nvisual-studio-code-bin 1.90.2-1\n";
+
+        let runner = MockRunner::new()
+            .respond("pacman -Qm", 0, installed)
+            .respond(
+                rpc,
+                0,
+                r#"{"resultcount":2,"results":[{"Name":"paru","Version":"2.0.3-1"},{"Name":"visual-studio-code-bin","Version":"1.91.0-1"}],"type":"multiple","version":5}"#,
+            );
+        let updates = checker(runner).check_aur_updates().await.unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].name, "visual-studio-code-bin");
+        assert_eq!(updates[0].current_version, "1.90.2-1");
+        assert_eq!(updates[0].new_version, "1.91.0-1");
+        assert!(updates[0].is_aur);
+
+        // A failed request falls back to the helper
+        let runner = MockRunner::new()
+            .respond("pacman -Qm", 0, installed)
+            .respond_full(rpc, 22, "", "curl: (22) The requested URL returned error: 503")
+            .respond("paru -Qu --aur", 0, "visual-studio-code-bin 1.90.2-1 -> 1.91.0-1\n");
+        let updates = checker(runner).check_aur_updates().await.unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].new_version, "1.91.0-1");
+    }
+
+    #[test]
+    fn test_aur_rpc_urls_are_batched() {
+        let names: Vec<String> = (0..500).map(|i| format!("package-number-{}", i)).collect();
+        let urls = UpdateChecker::aur_rpc_urls(names.iter().map(String::as_str));
+
+        assert!(urls.len() > 1);
+        assert!(urls.iter().all(|url| url.len() <= AUR_RPC_MAX_URL_LEN));
+        assert!(urls.iter().all(|url| url.starts_with(AUR_RPC_INFO_URL)));
+        let args: usize = urls.iter().map(|url| url.matches("arg%5B%5D=").count()).sum();
+        assert_eq!(args, names.len());
+
+        let urls = UpdateChecker::aur_rpc_urls(["libc++"]);
+        assert_eq!(urls, ["https://aur.archlinux.org/rpc/v5/info?arg%5B%5D=libc%2B%2B"]);
+        assert!(UpdateChecker::aur_rpc_urls(Vec::<&str>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_aur_rpc_versions() {
+        let versions = UpdateChecker::parse_aur_rpc_versions(
+            r#"{"resultcount":1,"results":[{"Name":"yay","Version":"12.3.5-1","NumVotes":2000}],"type":"multiple"}"#,
+        )
+        .unwrap();
+        assert_eq!(versions.get("yay").map(String::as_str), Some("12.3.5-1"));
+
+        let error = UpdateChecker::parse_aur_rpc_versions(
+            r#"{"error":"Too many package results.","resultcount":0,"results":[],"type":"error"}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("Too many package results"));
+        assert!(UpdateChecker::parse_aur_rpc_versions("<html>").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_apt_pipeline() {
+        let runner = MockRunner::new()
+            .respond(
+                "apt list --upgradable",
+                0,
+                "Listing...\n\
+                 curl/jammy-updates,jammy-security 7.81.0-1ubuntu1.16 amd64 [upgradable from: 7.81.0-1ubuntu1.15]\n\
+                 gnome-shell/jammy-updates 42.9-0ubuntu2.2 amd64 [upgradable from: 42.9-0ubuntu2.1]\n\
+                 linux-generic/jammy-updates 5.15.0.113.113 amd64 [upgradable from: 5.15.0.112.112]\n",
+            )
+            .respond(
+                "apt-get -s upgrade",
+                0,
+                "The following upgrades have been deferred due to phasing:\n  gnome-shell\n\
+                 The following packages have been kept back:\n  linux-generic\n\
+                 1 upgraded, 0 newly installed, 0 to remove and 2 not upgraded.\n",
+            );
+        let info = mock_check(PackageManager::Apt, runner).await.unwrap();
+
+        assert_eq!(info.official_updates, 2);
+        assert_eq!(info.security_updates, 1);
+        assert!(info.packages.iter().any(|p| p.name == "gnome-shell" && p.phased));
+        assert_eq!(info.held_back.len(), 1);
+        assert_eq!(info.held_back[0].name, "linux-generic");
+        // apt reads the lists from the last `apt update`
+        assert!(info.cached_metadata);
+    }
+
+    #[tokio::test]
+    async fn test_check_dnf_pipeline() {
+        // dnf check-update exits 100 when updates are available
+        let runner = MockRunner::new()
+            .respond(
+                "dnf check-update -q",
+                100,
+                "\nkernel.x86_64    6.9.7-200.fc40    updates\nvim-enhanced.x86_64    2:9.1.452-1.fc40    updates\npython3.11.x86_64    3.11.9-1.fc40    updates\n",
+            )
+            .respond(
+                "dnf updateinfo list security -q",
+                0,
+                "FEDORA-2024-0a1b2c3d4e Important/Sec. kernel-6.9.7-200.fc40.x86_64\n\
+                 FEDORA-2024-5f6a7b8c9d Moderate/Sec.  python3.11-3.11.9-1.fc40.x86_64\n",
+            );
+        let info = mock_check(PackageManager::Dnf, runner).await.unwrap();
+
+        assert_eq!(info.official_updates, 3);
+        assert_eq!(info.security_updates, 2);
+        assert!(info.packages.iter().any(|p| p.name == "kernel" && p.is_security));
+        // Only the trailing ".arch" is stripped from dotted names
+        assert!(info.packages.iter().any(|p| p.name == "python3.11" && p.is_security));
+        assert!(!info.cached_metadata);
+    }
+
+    #[tokio::test]
+    async fn test_check_dnf_falls_back_to_cached_metadata() {
+        let runner = MockRunner::new()
+            .respond_full(
+                "dnf check-update -q",
+                1,
+                "",
+                "Error: Failed to download metadata for repo 'updates': Cannot download repomd.xml\n",
+            )
+            .respond(
+                "dnf check-update -q --cacheonly",
+                100,
+                "\nvim-enhanced.x86_64    2:9.1.452-1.fc40    updates\n",
+            );
+        let info = mock_check(PackageManager::Dnf, runner).await.unwrap();
+        assert_eq!(info.official_updates, 1);
+        assert!(info.cached_metadata);
+
+        // Other failures are still reported
+        let runner = MockRunner::new().respond_full(
+            "dnf check-update -q",
+            1,
+            "",
+            "Error: rpmdb open failed\n",
+        );
+        assert!(mock_check(PackageManager::Dnf, runner).await.is_err());
+    }
+
+    #[test]
+    fn test_is_dnf_metadata_error() {
+        assert!(is_dnf_metadata_error(
+            "Error: Failed to download metadata for repo 'fedora': Cannot download repomd.xml"
+        ));
+        // dnf5
+        assert!(is_dnf_metadata_error(
+            "Failed to download metadata (metalink: \"https://mirrors.fedoraproject.org\") for repository \"fedora\""
+        ));
+        assert!(!is_dnf_metadata_error("Error: rpmdb open failed"));
+    }
+
+    #[tokio::test]
+    async fn test_check_zypper_and_apk_pipelines() {
+        let runner = MockRunner::new().respond(
+            "zypper list-updates",
+            0,
+            "S | Repository | Name | Version | Arch\n\
+             --+------------+------+---------+-----\n\
+             v | firefox | package | 127.0-1.1 | x86_64\n",
+        );
+        let info = mock_check(PackageManager::Zypper, runner).await.unwrap();
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.packages[0].new_version, "127.0-1.1");
+
+        let runner = MockRunner::new().respond(
+            "apk -u list",
+            0,
+            "musl-1.2.5-r1 x86_64 {musl} (MIT) [upgradable from: musl-1.2.5-r0]\n",
+        );
+        let info = mock_check(PackageManager::Apk, runner).await.unwrap();
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.packages[0].current_version, "1.2.5-r0");
+    }
+
+    #[tokio::test]
+    async fn test_check_flatpak_pipeline() {
+        let runner = MockRunner::new()
+            .respond("flatpak remotes --columns=name,options", 0, "flathub\tsystem\n")
+            .respond(
+                "flatpak remote-ls --system --updates --columns=application,version,branch,origin flathub",
+                0,
+                "org.mozilla.firefox\t127.0\tstable\tflathub\norg.gnome.Platform\t\t46\tflathub\n",
+            );
+        let info = mock_check(PackageManager::Flatpak, runner).await.unwrap();
+
+        assert_eq!(info.official_updates, 2);
+        assert_eq!(info.packages[1].new_version, "46");
+        assert!(info.warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_flatpak_keeps_remote_per_update() {
+        let runner = MockRunner::new()
+            .respond(
+                "flatpak remotes --columns=name,options",
+                0,
+                "flathub\tsystem\nfedora\tsystem\n",
+            )
+            .respond(
+                "flatpak remote-ls --system --updates --columns=application,version,branch,origin flathub",
+                0,
+                "org.mozilla.firefox\t127.0\tstable\tflathub\n",
+            )
+            .respond(
+                "flatpak remote-ls --system --updates --columns=application,version,branch,origin fedora",
+                0,
+                "org.gnome.Calculator\t46.1\tstable\tfedora\n",
+            );
+        let info = mock_check(PackageManager::Flatpak, runner).await.unwrap();
+
+        let origins: Vec<_> = info
+            .packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.origin.as_deref()))
+            .collect();
+        assert_eq!(
+            origins,
+            [
+                ("org.mozilla.firefox", Some("flathub")),
+                ("org.gnome.Calculator", Some("fedora")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_nixos_channels_pipeline() {
+        let runner = MockRunner::new()
+            .respond("sudo -n true", 0, "")
+            .respond_full(
+                "sudo nixos-rebuild dry-build --upgrade",
+                0,
+                "",
+                "these 1 derivations will be built:\n  \
+                 /nix/store/abcdefghijklmnopqrstuvwxyz012345-firefox-127.0.drv\n",
+            );
+        let info = mock_check(PackageManager::NixOS, runner).await.unwrap();
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.packages[0].name, "firefox");
+
+        // Without passwordless sudo the check stops before nixos-rebuild
+        let runner = MockRunner::new().respond("sudo -n true", 1, "");
+        let error = mock_check(PackageManager::NixOS, runner).await.unwrap_err();
+        assert!(matches!(error, CheckError::PermissionDenied(_)));
+    }
+
+    #[tokio::test]
+    async fn test_check_nix_profile_and_home_manager_pipelines() {
+        let runner = MockRunner::new().respond_full(
+            "nix --extra-experimental-features 'nix-command flakes' profile upgrade --dry-run '.*'",
+            0,
+            "",
+            "upgrading 'legacyPackages.x86_64-linux.hello' from flake 'github:NixOS/nixpkgs/0123456789abcdef' to 'github:NixOS/nixpkgs/fedcba9876543210'\n",
+        );
+        let info = mock_check(PackageManager::NixProfile, runner).await.unwrap();
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.packages[0].name, "hello");
+
+        let runner = MockRunner::new().respond_full(
+            "home-manager build --dry-run",
+            0,
+            "",
+            "these 1 paths will be fetched (1.2 MiB download, 5.0 MiB unpacked):\n  \
+             /nix/store/abcdefghijklmnopqrstuvwxyz012345-ripgrep-14.1.0\n",
+        );
+        let info = mock_check(PackageManager::HomeManager, runner).await.unwrap();
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.packages[0].name, "ripgrep");
+    }
+
+    #[tokio::test]
+    async fn test_check_failed_command_is_an_error() {
+        // A non-zero exit without any listing fails the check, after the retry
+        let runner = MockRunner::new().respond_full(
+            "apk -u list",
+            1,
+            "",
+            "ERROR: unable to lock database\n",
+        );
+        let error = mock_check(PackageManager::Apk, runner).await.unwrap_err();
+        assert!(error.to_string().contains("unable to lock database"));
+
+        // A missing binary fails to spawn
+        let error = mock_check(PackageManager::Zypper, MockRunner::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, CheckError::CommandNotFound(ref cmd) if cmd == "zypper"));
+    }
+
+    #[test]
+    fn test_parse_arch_package_line_with_arrow() {
+        let checker = UpdateChecker::new(PackageManager::Pacman);
+        let line = "linux 6.1.0-1 -> 6.2.0-1";
+        let update = checker.parse_package_line(line, false).unwrap();
+
+        assert_eq!(update.name, "linux");
+        assert_eq!(update.current_version, "6.1.0-1");
+        assert_eq!(update.new_version, "6.2.0-1");
+        assert!(!update.is_aur);
+    }
+
+    #[test]
+    fn test_parse_arch_package_line_without_arrow() {
+        let checker = UpdateChecker::new(PackageManager::Pacman);
+        let line = "firefox 120.0-1";
+        let update = checker.parse_package_line(line, false).unwrap();
+
+        assert_eq!(update.name, "firefox");
+        assert_eq!(update.current_version, "unknown");
+        assert_eq!(update.new_version, "120.0-1");
+        assert!(!update.is_aur);
+    }
+
+    #[test]
+    fn test_parse_aur_package_line() {
+        let checker = UpdateChecker::new(PackageManager::Paru);
+        let line = "yay-bin 12.0.0-1 -> 12.1.0-1";
+        let update = checker.parse_package_line(line, true).unwrap();
+
+        assert_eq!(update.name, "yay-bin");
+        assert_eq!(update.current_version, "12.0.0-1");
+        assert_eq!(update.new_version, "12.1.0-1");
+        assert!(update.is_aur);
+    }
+
+    #[test]
+    fn test_parse_nix_profile_upgrades() {
+        let output = "upgrading 'legacyPackages.x86_64-linux.hello' from flake 'github:NixOS/nixpkgs/0123456789abcdef' to 'github:NixOS/nixpkgs/fedcba9876543210'\n\
+                      upgrading 'legacyPackages.x86_64-linux.jq' from flake 'github:NixOS/nixpkgs/fedcba9876543210' to 'github:NixOS/nixpkgs/fedcba9876543210'\n";
+        let updates = UpdateChecker::parse_nix_profile_upgrades(output);
+
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].name, "hello");
+        assert_eq!(updates[0].current_version, "0123456");
+        assert_eq!(updates[0].new_version, "fedcba9");
+    }
+
+    #[test]
+    fn test_parse_pacman_depends() {
+        let output = "Name            : yay-bin
+Version         : 12.1.0-1
+Depends On      : pacman>=6.1  git
+Optional Deps   : sudo
+Required By     : None
+
+Name            : python-foo
+Version         : 1.0-1
+Depends On      : python  python-requests  libfoo.so=1-64
+                  glibc>=2.38
+Optional Deps   : None
+
+Name            : standalone
+Depends On      : None
+";
+        let depends = UpdateChecker::parse_pacman_depends(output);
+
+        assert_eq!(depends.len(), 3);
+        assert_eq!(depends[0].0, "yay-bin");
+        assert_eq!(depends[0].1, vec!["pacman", "git"]);
+        assert_eq!(depends[1].1, vec!["python", "python-requests", "libfoo.so", "glibc"]);
+        assert!(depends[2].1.is_empty());
+    }
+
+    #[test]
+    fn test_minor_version_changed() {
+        assert!(UpdateChecker::minor_version_changed("3.12.4-1", "3.13.0-1"));
+        assert!(UpdateChecker::minor_version_changed("74.2-1", "75.1-1"));
+        assert!(UpdateChecker::minor_version_changed("1:1.83.0-1", "1:1.84.0-1"));
+        assert!(!UpdateChecker::minor_version_changed("2.39-1", "2.39-2"));
+        assert!(!UpdateChecker::minor_version_changed("3.12.4-1", "3.12.5-1"));
+        assert!(!UpdateChecker::minor_version_changed("1:2.39-1", "2:2.39-1"));
+    }
+
+    #[test]
+    fn test_rebuild_hints() {
+        let depends = vec![
+            ("python-foo".to_string(), vec!["python".to_string(), "glibc".to_string()]),
+            ("yay-bin".to_string(), vec!["pacman".to_string()]),
+        ];
+        let changed: HashSet<String> = ["python".to_string(), "linux".to_string()].into();
+
+        let hints = UpdateChecker::rebuild_hints(&depends, &changed);
+        assert_eq!(
+            hints,
+            vec![RebuildHint {
+                package: "python-foo".to_string(),
+                changed_dependencies: vec!["python".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_foreign_packages() {
+        let output = "yay-bin 12.1.0-1\nvisual-studio-code-bin 1.85.0-1\n\n";
+        let foreign = UpdateChecker::parse_foreign_packages(output);
+
+        assert_eq!(foreign.len(), 2);
+        assert!(foreign.contains("yay-bin"));
+        assert!(foreign.contains("visual-studio-code-bin"));
+        assert!(!foreign.contains("linux"));
+    }
+
+    #[test]
+    fn test_parse_pacman_kernels() {
+        let output = "linux 6.9.7.arch1-1\nlinux-lts 6.6.30-1\nlinux-zen 6.10.zen1-2\n";
+        assert_eq!(
+            UpdateChecker::parse_pacman_kernels(output),
+            ["6.9.7-arch1-1", "6.6.30-1-lts", "6.10.0-zen1-2-zen"]
+        );
+    }
+
+    #[test]
+    fn test_compare_kernels() {
+        let installed = UpdateChecker::parse_dpkg_kernels(
+            "install ok installed linux-image-6.8.0-45-generic\n\
+             install ok installed linux-image-6.8.0-50-generic\n\
+             deinstall ok config-files linux-image-6.8.0-51-generic\n\
+             install ok installed linux-image-6.9.0-1-lowlatency\n",
+        );
+
+        let mismatch =
+            UpdateChecker::compare_kernels(VersionScheme::Dpkg, "6.8.0-45-generic", &installed);
+        assert_eq!(
+            mismatch,
+            Some(KernelMismatch {
+                running: "6.8.0-45-generic".to_string(),
+                installed: "6.8.0-50-generic".to_string(),
+            })
+        );
+        assert_eq!(
+            UpdateChecker::compare_kernels(VersionScheme::Dpkg, "6.8.0-50-generic", &installed),
+            None
+        );
+
+        // Only the running flavor counts, e.g. linux-lts next to linux
+        let arch = vec!["6.9.7-arch1-1".to_string(), "6.6.30-1-lts".to_string()];
+        assert_eq!(
+            UpdateChecker::compare_kernels(VersionScheme::Pacman, "6.6.30-1-lts", &arch),
+            None
+        );
+        assert!(
+            UpdateChecker::compare_kernels(VersionScheme::Pacman, "6.9.6-arch1-1", &arch).is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kernel_mismatch_dnf() {
+        let checker = UpdateChecker::new(PackageManager::Dnf).with_runner(
+            MockRunner::new()
+                .respond("uname -r", 0, "6.9.5-200.fc40.x86_64\n")
+                .respond(
+                    r"rpm -q --qf '%{VERSION}-%{RELEASE}.%{ARCH}\n' kernel-core",
+                    0,
+                    "6.9.5-200.fc40.x86_64\n6.9.7-200.fc40.x86_64\n",
+                ),
+        );
+
+        let mismatch = checker.kernel_mismatch().await.unwrap().unwrap();
+        assert_eq!(mismatch.installed, "6.9.7-200.fc40.x86_64");
+
+        // Nothing to compare against on other managers
+        let checker = UpdateChecker::new(PackageManager::Flatpak).with_runner(MockRunner::new());
+        assert_eq!(checker.kernel_mismatch().await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_pacman_sizes() {
+        let output = "linux 142918331\nglibc 10543210\n\nbroken line-without-size\n";
+        let sizes = UpdateChecker::parse_pacman_sizes(output);
+
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes["linux"], 142918331);
+        assert_eq!(sizes["glibc"], 10543210);
+    }
+
+    #[test]
+    fn test_apply_download_sizes() {
+        let mut info = UpdateInfo::new();
+        for name in ["linux", "yay-bin"] {
+            info.packages.push(PackageUpdate {
+                name: name.to_string(),
+                current_version: "1".to_string(),
+                new_version: "2".to_string(),
+                is_aur: name == "yay-bin",
+                is_security: false,
+                ..Default::default()
+            });
+        }
+
+        UpdateChecker::apply_download_sizes(&mut info, &HashMap::new());
+        assert_eq!(info.total_download_size, None);
+
+        let sizes = HashMap::from([("linux".to_string(), 1000)]);
+        UpdateChecker::apply_download_sizes(&mut info, &sizes);
+        assert_eq!(info.packages[0].download_size, Some(1000));
+        assert_eq!(info.packages[1].download_size, None);
+        assert_eq!(info.total_download_size, Some(1000));
+    }
+
+    #[test]
+    fn test_check_error_from_anyhow() {
+        let error: anyhow::Error = CheckError::LockBusy.into();
+        assert_eq!(CheckError::from(error), CheckError::LockBusy);
+
+        let error = spawn_error(
+            "checkupdates",
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        );
+        assert_eq!(
+            CheckError::from(error),
+            CheckError::CommandNotFound("checkupdates".to_string())
+        );
+
+        let error: anyhow::Error = std::io::Error::from(std::io::ErrorKind::TimedOut).into();
+        assert_eq!(CheckError::from(error), CheckError::Timeout);
+
+        let error = anyhow!("Failed to check for updates (exit 1): boom");
+        assert_eq!(
+            CheckError::from(error),
+            CheckError::Other("Failed to check for updates (exit 1): boom".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_timeout() {
+        let checker = UpdateChecker::with_options(
+            PackageManager::Dnf,
+            CheckOptions {
+                command_timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+        );
+        let error = checker.run("sleep", ["5"]).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(
+            CheckError::from(spawn_error("sleep", error)),
+            CheckError::Timeout
+        );
+
+        // Commands finishing in time are unaffected
+        let output = checker.run("true", Vec::<&str>::new()).await.unwrap();
+        assert!(output.status.success());
+    }
+
+    fn package(name: &str, is_aur: bool) -> PackageUpdate {
+        PackageUpdate {
+            name: name.to_string(),
+            current_version: "1.0".to_string(),
+            new_version: "1.1".to_string(),
+            is_aur,
+            is_security: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_package() {
+        let mut info = UpdateInfo::new();
+        info.packages = vec![package("linux", false), package("yay-bin", true)];
+        info.total_updates = info.packages.len();
+
+        let linux = info.find_package("linux").unwrap();
+        assert!(!linux.is_aur);
+        assert_eq!(linux.new_version, "1.1");
+
+        let yay = info.find_package("yay-bin").unwrap();
+        assert!(yay.is_aur);
+
+        assert!(info.find_package("firefox").is_none());
+        assert!(info.find_package("linu").is_none());
+    }
+
+    #[test]
+    fn test_has_update_for() {
+        let mut info = UpdateInfo::new();
+        assert!(!info.has_update_for("linux"));
+
+        info.packages = vec![package("linux", false), package("yay-bin", true)];
+        assert!(info.has_update_for("linux"));
+        assert!(info.has_update_for("yay-bin"));
+        assert!(!info.has_update_for("firefox"));
+    }
+
+    #[test]
+    fn test_remove_and_restore_packages() {
+        let mut info = UpdateInfo::new();
+        let mut firefox = package("firefox", false);
+        firefox.is_security = true;
+        info.packages = vec![package("linux", false), package("yay-bin", true), firefox];
+        info.total_updates = 3;
+        info.official_updates = 2;
+        info.aur_updates = 1;
+        info.security_updates = 1;
+
+        let removed = info.remove_packages(&["firefox".to_string(), "yay-bin".to_string()]);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(info.total_updates, 1);
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.aur_updates, 0);
+        assert_eq!(info.security_updates, 0);
+
+        info.restore_packages(removed);
+        assert_eq!(info.total_updates, 3);
+        assert_eq!(info.aur_updates, 1);
+        assert_eq!(info.security_updates, 1);
+
+        assert!(info.remove_packages(&[]).is_empty());
+        assert_eq!(info.total_updates, 3);
+    }
+
+    #[tokio::test]
+    async fn test_check_guix_pipeline() {
+        let runner = MockRunner::new().respond_full(
+            "guix package -u --dry-run",
+            0,
+            "",
+            "The following package would be upgraded:\n   hello 2.10 → 2.12.1\n",
+        );
+        let info = mock_check(PackageManager::Guix, runner).await.unwrap();
+        assert_eq!(info.official_updates, 1);
+        assert_eq!(info.packages[0].name, "hello");
+        // Compared against the channels of the last `guix pull`
+        assert!(info.cached_metadata);
+    }
+
+    #[test]
+    fn test_parse_guix_upgrades() {
+        let output = "substitute: updating substitutes from 'https://ci.guix.gnu.org'... 100.0%\n\
+                      The following packages would be upgraded:\n   \
+                      emacs       28.2 → 29.1     /gnu/store/0123456789abcdefghijklmnopqrstuv-emacs-29.1\n   \
+                      gcc:lib     11.3.0 → 12.3.0 /gnu/store/abcdefghijklmnopqrstuv0123456789-gcc-12.3.0-lib\n\
+                      \n\
+                      The following derivation would be built:\n   \
+                      /gnu/store/vwxyz0123456789abcdefghijklmnopq-profile.drv\n";
+        let updates = UpdateChecker::parse_guix_upgrades(output);
+
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "emacs");
+        assert_eq!(updates[0].current_version, "28.2");
+        assert_eq!(updates[0].new_version, "29.1");
+        assert_eq!(updates[1].name, "gcc:lib");
+
+        // A single upgrade, printed without the store item in a non-UTF-8 locale
+        let single = "The following package would be upgraded:\n   hello 2.10 -> 2.12.1\n";
+        assert_eq!(
+            UpdateChecker::parse_guix_upgrades(single)[0].new_version,
+            "2.12.1"
+        );
+
+        assert!(UpdateChecker::parse_guix_upgrades("nothing to be done\n").is_empty());
+    }
+
+    #[test]
+    fn test_is_guix_os_release() {
+        assert!(is_guix_os_release("NAME=\"Guix System\"\nID=guix\n"));
+        assert!(!is_guix_os_release("NAME=\"NixOS\"\nID=nixos\n"));
+    }
+
+    #[test]
+    fn test_is_transactional_os_release() {
+        let aeon = "NAME=\"openSUSE Aeon\"\nID=\"opensuse-aeon\"\nID_LIKE=\"suse opensuse\"\n";
+        assert!(is_transactional_os_release(aeon));
+
+        let microos = "NAME=\"openSUSE MicroOS\"\nID=\"opensuse-microos\"\n";
+        assert!(is_transactional_os_release(microos));
+
+        let tumbleweed = "NAME=\"openSUSE Tumbleweed\"\nID=\"opensuse-tumbleweed\"\n";
+        assert!(!is_transactional_os_release(tumbleweed));
+    }
+
+    #[test]
+    fn test_transactional_update_command() {
+        let pm = PackageManager::TransactionalUpdate;
+        assert_eq!(pm.name(), "transactional-update");
+        assert_eq!(pm.system_update_command(None), "sudo transactional-update dup");
+        assert!(!pm.supports_aur());
+    }
+
+    #[test]
+    fn test_get_preferred() {
+        let arch = [PackageManager::Paru, PackageManager::Pacman, PackageManager::Flatpak];
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&arch, false, false),
+            Some(PackageManager::Paru)
+        );
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&arch, true, false),
+            Some(PackageManager::Pacman)
+        );
+
+        let helpers_only = [PackageManager::Yay];
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&helpers_only, true, false),
+            Some(PackageManager::Yay)
+        );
+        assert_eq!(PackageManagerDetector::get_preferred(&[], true, false), None);
+    }
+
+    /// A machine with `binaries` installed (in `/usr/bin` unless given as a
+    /// path) and `files` present
+    struct FakeHost {
+        binaries: &'static [&'static str],
+        files: &'static [&'static str],
+        os_release: &'static str,
+    }
+
+    impl DetectionProbe for FakeHost {
+        fn which(&self, name: &str) -> Option<String> {
+            self.binaries.iter().find_map(|binary| {
+                let path = if binary.starts_with('/') {
+                    binary.to_string()
+                } else {
+                    format!("/usr/bin/{}", binary)
+                };
+                (path.rsplit('/').next() == Some(name)).then_some(path)
+            })
+        }
+
+        fn exists(&self, path: &str) -> bool {
+            self.files.contains(&path)
+        }
+
+        fn read(&self, path: &str) -> Option<String> {
+            (path == "/etc/os-release").then(|| self.os_release.to_string())
+        }
+    }
+
+    #[test]
+    fn test_detect_with_probe() {
+        let detect = |host: FakeHost| PackageManagerDetector::detect_with(&host);
+
+        // yay outside the trusted directories is not picked up
+        let arch = FakeHost {
+            binaries: &["pacman", "paru", "flatpak", "/tmp/yay"],
+            files: &[],
+            os_release: "ID=arch\n",
+        };
+        assert_eq!(
+            detect(arch),
+            vec![
+                PackageManager::Paru,
+                PackageManager::Pacman,
+                PackageManager::Flatpak
+            ]
+        );
+
+        let nixos = FakeHost {
+            binaries: &["nixos-rebuild", "nix", "home-manager"],
+            files: &["/etc/NIXOS"],
+            os_release: "ID=nixos\n",
+        };
+        assert_eq!(
+            detect(nixos),
+            vec![PackageManager::NixOS, PackageManager::HomeManager]
+        );
+
+        let debian_with_nix = FakeHost {
+            binaries: &["apt", "nix"],
+            files: &[],
+            os_release: "ID=debian\n",
+        };
+        assert_eq!(
+            detect(debian_with_nix),
+            vec![PackageManager::Apt, PackageManager::NixProfile]
+        );
+
+        let microos = FakeHost {
+            binaries: &["zypper"],
+            files: &["/usr/sbin/transactional-update"],
+            os_release: "NAME=\"openSUSE MicroOS\"\nID=\"opensuse-microos\"\n",
+        };
+        assert_eq!(
+            detect(microos),
+            vec![PackageManager::TransactionalUpdate, PackageManager::Zypper]
+        );
+
+        let tumbleweed = FakeHost {
+            binaries: &["zypper"],
+            files: &["/usr/sbin/transactional-update"],
+            os_release: "ID=\"opensuse-tumbleweed\"\n",
+        };
+        assert_eq!(detect(tumbleweed), vec![PackageManager::Zypper]);
+    }
+
+    #[test]
+    fn test_resolved_probe() {
+        let probe = ResolvedProbe(HashMap::from([
+            ("pacman", Some("/usr/bin/pacman".to_string())),
+            ("apt", None),
+        ]));
+        assert_eq!(probe.which("pacman").as_deref(), Some("/usr/bin/pacman"));
+        assert_eq!(probe.which("apt"), None);
+        assert_eq!(probe.which("dnf"), None);
+    }
+
+    #[test]
+    fn test_get_preferred_both_preferences() {
+        let debian = [PackageManager::Apt, PackageManager::Nala, PackageManager::Flatpak];
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&debian, true, false),
+            Some(PackageManager::Apt)
+        );
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&debian, true, true),
+            Some(PackageManager::Nala)
+        );
+
+        let arch = [PackageManager::Paru, PackageManager::Pacman, PackageManager::Flatpak];
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&arch, true, true),
+            Some(PackageManager::Pacman)
+        );
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&arch, false, true),
+            Some(PackageManager::Paru)
+        );
+    }
+
+    #[test]
+    fn test_get_preferred_nala() {
+        let debian = [PackageManager::Apt, PackageManager::Nala, PackageManager::Flatpak];
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&debian, false, false),
+            Some(PackageManager::Apt)
+        );
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&debian, false, true),
+            Some(PackageManager::Nala)
+        );
+        assert_eq!(
+            PackageManagerDetector::get_preferred(&[PackageManager::Apt], false, true),
+            Some(PackageManager::Apt)
+        );
+    }
+
+    #[test]
+    fn test_parse_nala_list() {
+        let checker = UpdateChecker::new(PackageManager::Nala);
+        let output = "curl 7.88.1-10+deb12u5 [Debian/stable-security main]
+└── is upgradable from 7.88.1-10+deb12u4
+tzdata 2024a-0+deb12u1 [Debian/stable-updates main]
+└── is upgradable from 2023c-5+deb12u1
+";
+        let updates = checker.parse_output_lines(output, false);
+
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "curl");
+        assert_eq!(updates[0].current_version, "7.88.1-10+deb12u4");
+        assert_eq!(updates[0].new_version, "7.88.1-10+deb12u5");
+        assert!(updates[0].is_security);
+        assert_eq!(updates[1].name, "tzdata");
+        assert_eq!(updates[1].current_version, "2023c-5+deb12u1");
+        assert!(!updates[1].is_security);
+    }
+
+    #[test]
+    fn test_parse_output_lines_skips_non_upgrades() {
+        let checker = UpdateChecker::new(PackageManager::Pacman);
+        let output = "\
+linux 6.6.1.arch1-1 -> 6.6.2.arch1-1
+mesa 1:24.0.1-1 -> 24.1.0-1
+python 3.12.1-1 -> 3.12.1-1
+paru-git 2.0.1.r12.gabc-1 -> latest-commit
+";
+        let packages = checker.parse_output_lines(output, false);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+
+        // mesa would lose its epoch and python is unchanged; the devel
+        // package's placeholder version cannot be compared and is kept
+        assert_eq!(names, vec!["linux", "paru-git"]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_clean_terminal_output() {
+        assert_eq!(
+            clean_terminal_output("\x1b[1m\x1b[32mlinux\x1b[0m 6.6.1-1 -> 6.6.2-1\r\n"),
+            "linux 6.6.1-1 -> 6.6.2-1\n"
+        );
+        // A spinner redrawn in place leaves only the last frame
+        assert_eq!(
+            clean_terminal_output("\u{280b} Checking\r\u{2819} Checking\r\x1b[2Kmesa 1-1 -> 2-1"),
+            "mesa 1-1 -> 2-1"
+        );
+        assert_eq!(
+            clean_terminal_output("\x1b]8;;https://x\x07vim\x1b]8;;\x07 1\u{7}\u{fffd} -> 2"),
+            "vim 1 -> 2"
+        );
+    }
 
     #[test]
-    fn test_parse_arch_package_line_with_arrow() {
+    fn test_parse_output_lines_with_escapes() {
         let checker = UpdateChecker::new(PackageManager::Pacman);
-        let line = "linux 6.1.0-1 -> 6.2.0-1";
+        let output = "\
+\x1b[0;1mlinux\x1b[0m \x1b[0;31m6.6.1.arch1-1\x1b[0m -> \x1b[0;32m6.6.2.arch1-1\x1b[0m
+:: Checking\r:: Searching\rmesa 24.0.1-1 -> 24.1.0-1\r
+";
+        let packages = checker.parse_output_lines(output, false);
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "linux");
+        assert_eq!(packages[0].current_version, "6.6.1.arch1-1");
+        assert_eq!(packages[0].new_version, "6.6.2.arch1-1");
+        assert_eq!(packages[1].name, "mesa");
+        assert_eq!(packages[1].new_version, "24.1.0-1");
+    }
+
+    #[test]
+    fn test_parse_apt_package_line() {
+        let checker = UpdateChecker::new(PackageManager::Apt);
+        let line = "firefox/jammy-updates 120.0+build1-0ubuntu0.22.04.1 amd64 [upgradable from: 119.0+build2-0ubuntu0.22.04.1]";
         let update = checker.parse_package_line(line, false).unwrap();
 
-        assert_eq!(update.name, "linux");
-        assert_eq!(update.current_version, "6.1.0-1");
-        assert_eq!(update.new_version, "6.2.0-1");
+        assert_eq!(update.name, "firefox");
+        assert_eq!(update.new_version, "120.0+build1-0ubuntu0.22.04.1");
+        assert_eq!(update.current_version, "119.0+build2-0ubuntu0.22.04.1");
         assert!(!update.is_aur);
     }
 
     #[test]
-    fn test_parse_arch_package_line_without_arrow() {
-        let checker = UpdateChecker::new(PackageManager::Pacman);
-        let line = "firefox 120.0-1";
-        let update = checker.parse_package_line(line, false).unwrap();
+    fn test_parse_apt_simulation_inst_line() {
+        let checker = UpdateChecker::new(PackageManager::Apt);
+        let update = checker
+            .parse_package_line("Inst libfoo [1.0] (1.1 amd64)", false)
+            .unwrap();
 
-        assert_eq!(update.name, "firefox");
-        assert_eq!(update.current_version, "unknown");
-        assert_eq!(update.new_version, "120.0-1");
+        assert_eq!(update.name, "libfoo");
+        assert_eq!(update.current_version, "1.0");
+        assert_eq!(update.new_version, "1.1");
         assert!(!update.is_aur);
+
+        let line = "Inst firefox [119.0+build2-0ubuntu0.22.04.1] (120.0+build1-0ubuntu0.22.04.1 Ubuntu:22.04/jammy-updates [amd64])";
+        let update = checker.parse_package_line(line, false).unwrap();
+        assert_eq!(update.name, "firefox");
+        assert_eq!(update.current_version, "119.0+build2-0ubuntu0.22.04.1");
+        assert_eq!(update.new_version, "120.0+build1-0ubuntu0.22.04.1");
     }
 
     #[test]
-    fn test_parse_aur_package_line() {
-        let checker = UpdateChecker::new(PackageManager::Paru);
-        let line = "yay-bin 12.0.0-1 -> 12.1.0-1";
-        let update = checker.parse_package_line(line, true).unwrap();
+    fn test_parse_apt_simulation_new_package() {
+        let checker = UpdateChecker::new(PackageManager::Apt);
+        let line = "Inst linux-image-6.5.0-15-generic (6.5.0-15.15~22.04.1 Ubuntu:22.04/jammy-updates [amd64])";
+        let update = checker.parse_package_line(line, false).unwrap();
 
-        assert_eq!(update.name, "yay-bin");
-        assert_eq!(update.current_version, "12.0.0-1");
-        assert_eq!(update.new_version, "12.1.0-1");
-        assert!(update.is_aur);
+        assert_eq!(update.name, "linux-image-6.5.0-15-generic");
+        assert_eq!(update.current_version, "none");
+        assert_eq!(update.new_version, "6.5.0-15.15~22.04.1");
     }
 
     #[test]
-    fn test_parse_apt_package_line() {
+    fn test_parse_apt_simulation_skips_other_lines() {
+        let checker = UpdateChecker::new(PackageManager::Apt);
+
+        assert!(checker
+            .parse_package_line("Conf libfoo (1.1 amd64)", false)
+            .is_none());
+        assert!(checker
+            .parse_package_line("NOTE: This is only a simulation!", false)
+            .is_none());
+        assert!(checker
+            .parse_package_line(
+                "1 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.",
+                false
+            )
+            .is_none());
+        assert!(checker.parse_package_line("Inst", false).is_none());
+    }
+
+    #[test]
+    fn test_parse_apt_security_updates() {
         let checker = UpdateChecker::new(PackageManager::Apt);
+
+        let line = "openssl/jammy-updates,jammy-security 3.0.2-0ubuntu1.12 amd64 [upgradable from: 3.0.2-0ubuntu1.10]";
+        assert!(checker.parse_package_line(line, false).unwrap().is_security);
+
         let line = "firefox/jammy-updates 120.0+build1-0ubuntu0.22.04.1 amd64 [upgradable from: 119.0+build2-0ubuntu0.22.04.1]";
-        let update = checker.parse_package_line(line, false).unwrap();
+        assert!(!checker.parse_package_line(line, false).unwrap().is_security);
 
-        assert_eq!(update.name, "firefox");
-        assert_eq!(update.new_version, "120.0+build1-0ubuntu0.22.04.1");
-        assert_eq!(update.current_version, "119.0+build2-0ubuntu0.22.04.1");
-        assert!(!update.is_aur);
+        let line = "Inst libssl3 [3.0.2-0ubuntu1.10] (3.0.2-0ubuntu1.12 Ubuntu:22.04/jammy-updates, Ubuntu:22.04/jammy-security [amd64])";
+        assert!(checker.parse_package_line(line, false).unwrap().is_security);
+
+        let line = "Inst libfoo [1.0] (1.1 amd64)";
+        assert!(!checker.parse_package_line(line, false).unwrap().is_security);
+    }
+
+    #[test]
+    fn test_apt_origin_filter() {
+        let checker = UpdateChecker::with_options(
+            PackageManager::Apt,
+            CheckOptions {
+                apt_origins: vec!["bookworm-backports".to_string()],
+                ..CheckOptions::default()
+            },
+        );
+
+        let line = "linux-image-amd64/bookworm-backports 6.6.13-1~bpo12+1 amd64 [upgradable from: 6.5.10-1~bpo12+1]";
+        assert!(checker.parse_package_line(line, false).is_some());
+
+        let line = "firefox-esr/stable-security 115.7.0esr-1~deb12u1 amd64 [upgradable from: 115.6.0esr-1~deb12u1]";
+        assert!(checker.parse_package_line(line, false).is_none());
+
+        let line = "Inst linux-image-amd64 [6.5.10-1~bpo12+1] (6.6.13-1~bpo12+1 Debian Backports:bookworm-backports/bookworm-backports [amd64])";
+        assert!(checker.parse_package_line(line, false).is_some());
+
+        let line = "Inst libfoo [1.0] (1.1 amd64)";
+        assert!(checker.parse_package_line(line, false).is_none());
+    }
+
+    #[test]
+    fn test_parse_apt_kept_back() {
+        let output = "Reading package lists...
+Building dependency tree...
+Calculating upgrade...
+The following packages have been kept back:
+  linux-generic linux-headers-generic
+  linux-image-generic
+The following packages will be upgraded:
+  curl libcurl4
+2 upgraded, 0 newly installed, 0 to remove and 3 not upgraded.
+";
+        let kept_back = UpdateChecker::parse_apt_kept_back(output);
+        assert_eq!(kept_back.len(), 3);
+        assert!(kept_back.contains("linux-generic"));
+        assert!(kept_back.contains("linux-image-generic"));
+        assert!(!kept_back.contains("curl"));
+
+        assert!(UpdateChecker::parse_apt_kept_back("0 upgraded, 0 newly installed").is_empty());
+    }
+
+    #[test]
+    fn test_parse_apt_phased() {
+        let output = "Calculating upgrade...
+The following upgrades have been deferred due to phasing:
+  gnome-shell gnome-shell-common
+The following packages have been kept back:
+  linux-generic
+The following packages will be upgraded:
+  curl
+1 upgraded, 0 newly installed, 0 to remove and 3 not upgraded.
+";
+        let phased = UpdateChecker::parse_apt_phased(output);
+        assert_eq!(phased.len(), 2);
+        assert!(phased.contains("gnome-shell-common"));
+        assert!(!phased.contains("linux-generic"));
+        assert_eq!(UpdateChecker::parse_apt_kept_back(output).len(), 1);
+    }
+
+    #[test]
+    fn test_apt_simulation_suites() {
+        let rest = "libssl3 [3.0.2-0ubuntu1.10] (3.0.2-0ubuntu1.12 Ubuntu:22.04/jammy-updates, Ubuntu:22.04/jammy-security [amd64])";
+        assert_eq!(
+            UpdateChecker::apt_simulation_suites(rest),
+            vec!["jammy-updates", "jammy-security"]
+        );
+        assert!(UpdateChecker::apt_simulation_suites("libfoo [1.0] (1.1 amd64)").is_empty());
+    }
+
+    #[test]
+    fn test_parse_dnf_security_names() {
+        let output = "FEDORA-2024-1a2b3c4d5e Important/Sec. kernel-6.5.0-1.fc38.x86_64\n\
+                      FEDORA-2024-6f7a8b9c0d Moderate/Sec.  python3.11-3.11.5-1.fc38.x86_64\n\n";
+        let names = UpdateChecker::parse_dnf_security_names(output);
+
+        assert_eq!(names.len(), 2);
+        assert!(names.contains("kernel"));
+        assert!(names.contains("python3.11"));
     }
 
     #[test]
@@ -1073,6 +4756,26 @@ mod tests {
         assert!(!update.is_aur);
     }
 
+    #[test]
+    fn test_parse_dnf_output_with_obsoletes() {
+        let checker = UpdateChecker::new(PackageManager::Dnf);
+        let output = "\
+Last metadata expiration check: 0:42:17 ago on Mon 04 Dec 2023 09:15:02 AM CET.
+
+firefox.x86_64                        120.0-1.fc39              updates
+kernel.x86_64                         6.6.4-200.fc39            updates
+python3-libs.x86_64                   3.12.0-2.fc39             updates
+Obsoleting Packages
+grub2-tools-efi.x86_64                1:2.06-108.fc39           updates
+    grub2-tools-efi.x86_64            1:2.06-100.fc39           @updates
+";
+        let packages = checker.parse_output_lines(output, false);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+
+        assert_eq!(names, vec!["firefox", "kernel", "python3-libs"]);
+        assert_eq!(packages[1].new_version, "6.6.4-200.fc39");
+    }
+
     #[test]
     fn test_parse_zypper_package_line() {
         let checker = UpdateChecker::new(PackageManager::Zypper);
@@ -1082,19 +4785,144 @@ mod tests {
         assert_eq!(update.name, "firefox");
         assert_eq!(update.new_version, "120.0-1.1");
         assert!(!update.is_aur);
+
+        let line = "v | Main Update Repository | firefox | 119.0-1.1 | 120.0-1.1 | x86_64";
+        let update = checker.parse_package_line(line, false).unwrap();
+        assert_eq!(update.name, "firefox");
+        assert_eq!(update.current_version, "119.0-1.1");
+        assert_eq!(update.new_version, "120.0-1.1");
     }
 
     #[test]
     fn test_parse_flatpak_package_line() {
         let checker = UpdateChecker::new(PackageManager::Flatpak);
-        let line = "Firefox\torg.mozilla.firefox\t120.0\tstable\tflathub";
+        let line = "org.mozilla.firefox\t120.0\tstable\tflathub";
         let update = checker.parse_package_line(line, false).unwrap();
 
-        assert_eq!(update.name, "Firefox");
+        assert_eq!(update.name, "org.mozilla.firefox");
         assert_eq!(update.new_version, "120.0");
+        assert_eq!(update.origin.as_deref(), Some("flathub"));
+        assert!(!update.is_aur);
+    }
+
+    #[test]
+    fn test_parse_flatpak_forced_columns_output() {
+        let checker = UpdateChecker::new(PackageManager::Flatpak);
+        let output = "org.gnome.Platform\t\t45\tflathub\n\
+                      org.videolan.VLC\t3.0.20\tstable\tflathub\n\
+                      \n\
+                      truncated-line\n";
+        let updates: Vec<_> = output
+            .lines()
+            .filter_map(|line| checker.parse_package_line(line, false))
+            .collect();
+
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "org.gnome.Platform");
+        assert_eq!(updates[0].new_version, "45");
+        assert_eq!(updates[1].name, "org.videolan.VLC");
+        assert_eq!(updates[1].new_version, "3.0.20");
+    }
+
+    #[test]
+    fn test_is_flatpak_runtime() {
+        for runtime in [
+            "org.gnome.Platform",
+            "org.freedesktop.Platform.GL.default",
+            "org.freedesktop.Sdk.Extension.rust-stable",
+            "org.kde.PlatformTheme.QGnomePlatform",
+            "org.gtk.Gtk3theme.Adwaita-dark",
+            "org.mozilla.firefox.Locale",
+            "org.electronjs.Electron2.BaseApp",
+        ] {
+            assert!(is_flatpak_runtime(runtime), "{}", runtime);
+        }
+        for app in ["org.mozilla.firefox", "org.videolan.VLC", "com.valvesoftware.Steam"] {
+            assert!(!is_flatpak_runtime(app), "{}", app);
+        }
+        // The reverse-DNS prefix is not part of the name
+        assert!(!is_flatpak_runtime("io.Platform.viewer"));
+    }
+
+    #[test]
+    fn test_parse_flatpak_remotes() {
+        let output = "flathub\tsystem\nflathub\tuser\nfedora\tsystem,disabled\nlocal\n";
+        let remotes = UpdateChecker::parse_flatpak_remotes(output);
+
+        assert_eq!(
+            remotes,
+            vec![
+                ("flathub".to_string(), "--system"),
+                ("flathub".to_string(), "--user"),
+                ("local".to_string(), "--system"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_apk_package_line() {
+        let checker = UpdateChecker::new(PackageManager::Apk);
+        let line = "py3-setuptools-68.0.0-r1 x86_64 {py3-setuptools} (MIT) [upgradable from: py3-setuptools-67.8.0-r0]";
+        let update = checker.parse_package_line(line, false).unwrap();
+
+        assert_eq!(update.name, "py3-setuptools");
+        assert_eq!(update.new_version, "68.0.0-r1");
+        assert_eq!(update.current_version, "67.8.0-r0");
         assert!(!update.is_aur);
     }
 
+    #[test]
+    fn test_parse_eopkg_package_line() {
+        let checker = UpdateChecker::new(PackageManager::Eopkg);
+        // `eopkg list-upgrades` pads names to the longest one and ends
+        // each line with a space
+        let output = "firefox         - Mozilla Firefox web browser \n\
+                      linux-current   - Linux kernel (current series) \n\
+                      libreoffice-gtk - GTK VCL plugin for LibreOffice - with Qt bits \n";
+        let updates = checker.parse_output_lines(output, false);
+
+        let names: Vec<&str> = updates.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["firefox", "linux-current", "libreoffice-gtk"]);
+        assert_eq!(updates[0].current_version, "unknown");
+        assert_eq!(updates[0].new_version, "latest");
+
+        assert!(checker
+            .parse_package_line("No packages to upgrade.", false)
+            .is_none());
+        assert!(checker
+            .parse_package_line("Updating repositories", false)
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_eopkg_package_line_with_version() {
+        let checker = UpdateChecker::new(PackageManager::Eopkg);
+        let update = checker
+            .parse_package_line("firefox   121.0-250  - Mozilla Firefox web browser ", false)
+            .unwrap();
+
+        assert_eq!(update.name, "firefox");
+        assert_eq!(update.current_version, "unknown");
+        assert_eq!(update.new_version, "121.0-250");
+    }
+
+    #[test]
+    fn test_split_apk_package() {
+        assert_eq!(
+            UpdateChecker::split_apk_package("py3-setuptools-68.0.0-r1"),
+            Some(("py3-setuptools", "68.0.0-r1".to_string()))
+        );
+        assert_eq!(
+            UpdateChecker::split_apk_package("musl-1.2.4-r2"),
+            Some(("musl", "1.2.4-r2".to_string()))
+        );
+        assert_eq!(
+            UpdateChecker::split_apk_package("gtk+2.0-2.24.33"),
+            Some(("gtk+2.0", "2.24.33".to_string()))
+        );
+        assert_eq!(UpdateChecker::split_apk_package("busybox"), None);
+    }
+
     #[test]
     fn test_skip_header_lines() {
         let checker = UpdateChecker::new(PackageManager::Apt);
@@ -1117,7 +4945,9 @@ these 2 derivations will be built:
 these 1 paths will be fetched (15.2 MiB download, 89.3 MiB unpacked):
   /nix/store/abcdefghijklmnopqrstuvwxyz012345-glibc-2.39
 ";
-        let updates = checker.parse_nixos_rebuild_output(output).unwrap();
+        let updates = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::BuildsAndFetches)
+            .unwrap();
 
         assert_eq!(updates.len(), 3);
         assert_eq!(updates[0].name, "firefox");
@@ -1127,11 +4957,36 @@ these 1 paths will be fetched (15.2 MiB download, 89.3 MiB unpacked):
         assert_eq!(updates[2].new_version, "2.39");
     }
 
+    #[test]
+    fn test_parse_nixos_rebuild_output_count_choice() {
+        let checker = UpdateChecker::new(PackageManager::NixOS);
+        let output = "\
+these 2 derivations will be built:
+  /nix/store/abcdefghijklmnopqrstuvwxyz012345-firefox-120.0.1.drv
+  /nix/store/abcdefghijklmnopqrstuvwxyz012345-system-units.drv
+these 1 paths will be fetched (15.2 MiB download, 89.3 MiB unpacked):
+  /nix/store/abcdefghijklmnopqrstuvwxyz012345-glibc-2.39
+";
+        let builds = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::Builds)
+            .unwrap();
+        assert_eq!(builds.len(), 2);
+        assert_eq!(builds[0].name, "firefox");
+
+        let fetches = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::Fetches)
+            .unwrap();
+        assert_eq!(fetches.len(), 1);
+        assert_eq!(fetches[0].name, "glibc");
+    }
+
     #[test]
     fn test_parse_nixos_up_to_date() {
         let checker = UpdateChecker::new(PackageManager::NixOS);
         let output = "System is up to date";
-        let updates = checker.parse_nixos_rebuild_output(output).unwrap();
+        let updates = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::BuildsAndFetches)
+            .unwrap();
 
         assert_eq!(updates.len(), 0);
     }
@@ -1172,6 +5027,134 @@ these 1 paths will be fetched (15.2 MiB download, 89.3 MiB unpacked):
         assert_eq!(updates[0].new_version, "def456a");
     }
 
+    #[test]
+    fn test_flake_support_error() {
+        let old_rebuild = "/run/current-system/sw/bin/nixos-rebuild: unknown option `--flake'";
+        assert!(UpdateChecker::flake_support_error(old_rebuild)
+            .unwrap()
+            .contains("Channels mode"));
+        assert!(UpdateChecker::flake_support_error(
+            "error: experimental Nix feature 'flakes' is disabled; use '--extra-experimental-features flakes' to override"
+        )
+        .unwrap()
+        .contains("nix.conf"));
+        assert!(UpdateChecker::flake_support_error("error: 'flake' is not a recognised command").is_some());
+        assert_eq!(
+            UpdateChecker::flake_support_error("error: getting status of '/etc/nixos/flake.nix': No such file"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_flake_locked_revs() {
+        let json = r#"{
+            "locks": {
+                "nodes": {
+                    "home-manager": {
+                        "inputs": { "nixpkgs": ["nixpkgs"] },
+                        "locked": { "rev": "1111111aaaaaaa", "type": "github" }
+                    },
+                    "nixpkgs": {
+                        "locked": { "rev": "abc123def4567890", "type": "github" }
+                    },
+                    "root": {
+                        "inputs": { "home-manager": "home-manager", "nixpkgs": "nixpkgs", "hm-pkgs": ["home-manager", "nixpkgs"] }
+                    }
+                },
+                "root": "root",
+                "version": 7
+            }
+        }"#;
+        let revs = UpdateChecker::parse_flake_locked_revs(json);
+
+        assert_eq!(revs.len(), 2);
+        assert_eq!(revs["nixpkgs"], "abc123def4567890");
+        assert_eq!(revs["home-manager"], "1111111aaaaaaa");
+        assert!(UpdateChecker::parse_flake_locked_revs("not json").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_flake_updates_use_locked_revs() {
+        let json = r#"{"locks":{"nodes":{"nixpkgs":{"locked":{"rev":"0f0f0f0f0f0f"}},"root":{"inputs":{"nixpkgs":"nixpkgs"}}},"root":"root"}}"#;
+        let checker = UpdateChecker::new(PackageManager::NixOS).with_runner(
+            MockRunner::new().respond("nix flake metadata --json /etc/nixos", 0, json),
+        );
+        let mut updates = checker.parse_flake_updates(
+            "Updated input 'nixpkgs': 'github:NixOS/nixpkgs/abc123def' -> 'github:NixOS/nixpkgs/def456abc'",
+        );
+        checker
+            .apply_flake_locked_revs("/etc/nixos", &mut updates)
+            .await;
+
+        assert_eq!(updates[0].current_version, "0f0f0f0");
+        assert_eq!(updates[0].new_version, "def456a");
+    }
+
+    #[test]
+    fn test_safe_executable_paths_accepted() {
+        assert!(has_safe_prefix("/usr/bin/pacman"));
+        assert!(has_safe_prefix("/usr/local/bin/paru"));
+        assert!(has_safe_prefix("/bin/apt"));
+        assert!(has_safe_prefix("/run/current-system/sw/bin/nix"));
+        assert!(has_safe_prefix("/etc/profiles/per-user/alice/bin/flatpak"));
+        assert!(has_safe_prefix("/home/linuxbrew/.linuxbrew/bin/flatpak"));
+        assert!(has_safe_prefix("/gnu/store/0123-guix-1.4.0/bin/guix"));
+    }
+
+    #[test]
+    fn test_unsafe_executable_paths_rejected() {
+        assert!(!has_safe_prefix("/tmp/pacman"));
+        assert!(!has_safe_prefix("/home/alice/bin/pacman"));
+        assert!(!has_safe_prefix("/usr/../tmp/pacman"));
+        assert!(!has_safe_prefix("usr/bin/pacman"));
+        assert!(!has_safe_prefix(""));
+        // User profiles only count through the store path they resolve to
+        assert!(!has_safe_prefix("/home/alice/.nix-profile/bin/nix"));
+        assert!(!has_safe_prefix(
+            "/nix/var/nix/profiles/per-user/alice/profile/bin/nix"
+        ));
+        assert!(!has_safe_prefix(
+            "/var/guix/profiles/per-user/alice/guix-profile/bin/guix"
+        ));
+    }
+
+    #[test]
+    fn test_profile_entries_checked_by_resolved_path() {
+        let profile = std::env::temp_dir().join(format!("profile-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&profile);
+        std::fs::create_dir_all(profile.join("bin")).unwrap();
+        let planted = profile.join("planted");
+        std::fs::write(&planted, "#!/bin/sh\n").unwrap();
+        std::os::unix::fs::symlink(&planted, profile.join("bin/nix")).unwrap();
+        std::os::unix::fs::symlink("/usr/bin/env", profile.join("bin/env")).unwrap();
+
+        assert!(!is_safe_executable_path(
+            profile.join("bin/nix").to_str().unwrap()
+        ));
+        assert!(is_safe_executable_path(
+            profile.join("bin/env").to_str().unwrap()
+        ));
+        let _ = std::fs::remove_dir_all(profile);
+    }
+
+    #[test]
+    fn test_pick_askpass() {
+        let env = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        // The environment wins over the usual locations
+        let nix = "/nix/store/0123-x11-ssh-askpass/libexec/x11-ssh-askpass";
+        assert_eq!(pick_askpass(env(&[nix]), |_| true).as_deref(), Some(nix));
+
+        // An untrusted or missing helper falls through to the installed one
+        let found = pick_askpass(
+            env(&["", "/tmp/cosmic-test-no-such-askpass"]),
+            |path| path == "/usr/lib/ssh/ssh-askpass",
+        );
+        assert_eq!(found.as_deref(), Some("/usr/lib/ssh/ssh-askpass"));
+
+        assert_eq!(pick_askpass(env(&[]), |_| false), None);
+    }
+
     #[test]
     fn test_package_manager_name() {
         assert_eq!(PackageManager::Pacman.name(), "pacman");
@@ -1179,6 +5162,75 @@ these 1 paths will be fetched (15.2 MiB download, 89.3 MiB unpacked):
         assert_eq!(PackageManager::Apt.name(), "apt");
         assert_eq!(PackageManager::Dnf.name(), "dnf");
         assert_eq!(PackageManager::NixOS.name(), "nixos");
+        assert_eq!(PackageManager::NixProfile.name(), "nix-profile");
+        assert_eq!(PackageManager::HomeManager.name(), "home-manager");
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(PackageManager::from_name("paru"), Some(PackageManager::Paru));
+        assert_eq!(PackageManager::from_name("nix-profile"), Some(PackageManager::NixProfile));
+        assert_eq!(PackageManager::from_name("emerge"), None);
+        assert!(PackageManager::Apt.supports_remote_check());
+        assert!(!PackageManager::NixOS.supports_remote_check());
+    }
+
+    #[test]
+    fn test_from_name_round_trips_every_variant() {
+        // Exhaustive on purpose: a new variant fails to compile here until
+        // it gets a name below, and then has to be in `ALL` and round-trip
+        fn expected_name(pm: PackageManager) -> &'static str {
+            match pm {
+                PackageManager::Pacman => "pacman",
+                PackageManager::Paru => "paru",
+                PackageManager::Yay => "yay",
+                PackageManager::Apt => "apt",
+                PackageManager::Nala => "nala",
+                PackageManager::Dnf => "dnf",
+                PackageManager::Zypper => "zypper",
+                PackageManager::TransactionalUpdate => "transactional-update",
+                PackageManager::Apk => "apk",
+                PackageManager::Eopkg => "eopkg",
+                PackageManager::Flatpak => "flatpak",
+                PackageManager::NixOS => "nixos",
+                PackageManager::NixProfile => "nix-profile",
+                PackageManager::HomeManager => "home-manager",
+                PackageManager::Guix => "guix",
+            }
+        }
+
+        for &pm in PackageManager::ALL {
+            assert_eq!(pm.name(), expected_name(pm));
+            assert_eq!(PackageManager::from_name(expected_name(pm)), Some(pm));
+        }
+        assert_eq!(PackageManager::from_name("Pacman"), None);
+        assert_eq!(PackageManager::from_name("emerge"), None);
+        assert_eq!(PackageManager::ALL.len(), 15);
+
+        let names: HashSet<&str> = PackageManager::ALL.iter().map(|pm| pm.name()).collect();
+        assert_eq!(names.len(), PackageManager::ALL.len(), "names must be unique");
+    }
+
+    #[test]
+    fn test_database_path() {
+        assert_eq!(PackageManager::Yay.database_path(), Some("/var/lib/pacman/local"));
+        assert_eq!(PackageManager::Nala.database_path(), Some("/var/lib/dpkg/status"));
+        assert_eq!(PackageManager::Dnf.database_path(), Some("/var/lib/rpm"));
+        assert_eq!(PackageManager::NixOS.database_path(), None);
+    }
+
+    #[test]
+    fn test_noninteractive_update_command() {
+        assert_eq!(
+            PackageManager::Pacman.noninteractive_update_command(),
+            Some(("pacman", &["-Syu", "--noconfirm"][..]))
+        );
+        assert!(PackageManager::Apt
+            .noninteractive_update_command()
+            .is_some_and(|(cmd, args)| cmd == "apt-get" && args.contains(&"-y")));
+        // AUR helpers refuse to run as root
+        assert!(PackageManager::Paru.noninteractive_update_command().is_none());
+        assert!(PackageManager::Flatpak.noninteractive_update_command().is_none());
     }
 
     #[test]
@@ -1224,6 +5276,62 @@ these 1 paths will be fetched (15.2 MiB download, 89.3 MiB unpacked):
         fs::remove_dir_all(temp_dir).unwrap();
     }
 
+    #[test]
+    fn test_nixos_config_candidates() {
+        let root = std::env::temp_dir().join(format!("nixos-config-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let home = root.join("home");
+        let etc = root.join("etc-nixos");
+        let linked = root.join("linked");
+        let channels = root.join("channels");
+        for dir in [
+            home.join("nixos-config"),
+            home.join(".nixos"),
+            etc.clone(),
+            linked.clone(),
+            channels.clone(),
+        ] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(home.join("nixos-config/flake.nix"), "{}").unwrap();
+        std::fs::write(linked.join("flake.nix"), "{}").unwrap();
+        std::os::unix::fs::symlink(linked.join("flake.nix"), etc.join("flake.nix")).unwrap();
+        std::fs::write(channels.join("configuration.nix"), "{}").unwrap();
+
+        let nix_path = format!(
+            "nixpkgs=/nix/var/nix/profiles/per-user/root/channels/nixos:nixos-config={}",
+            channels.join("configuration.nix").display()
+        );
+        let candidates = nixos_config_candidates(Some(&nix_path), home.to_str(), &etc);
+        let linked = std::fs::canonicalize(linked).unwrap();
+        // ~/.nixos has no flake.nix
+        assert_eq!(
+            candidates,
+            vec![channels, linked, home.join("nixos-config")]
+        );
+
+        // An entry inside etc_nixos leaves the symlink target first
+        let nix_path = format!("nixos-config={}", etc.join("configuration.nix").display());
+        std::fs::write(etc.join("configuration.nix"), "{}").unwrap();
+        assert_eq!(
+            nixos_config_candidates(Some(&nix_path), None, &etc),
+            vec![linked]
+        );
+
+        assert!(nixos_config_candidates(None, None, &root.join("missing")).is_empty());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_configuration_revision() {
+        let json = r#"{"configurationRevision":"0f3c2a1b-dirty","nixosVersion":"24.11.20250101.abcdef0","nixpkgsRevision":"abcdef0"}"#;
+        assert_eq!(configuration_revision(json).as_deref(), Some("0f3c2a1b"));
+
+        let json = r#"{"nixosVersion":"24.11.20250101.abcdef0","nixpkgsRevision":"abcdef0"}"#;
+        assert_eq!(configuration_revision(json), None);
+        assert_eq!(configuration_revision("24.11 (Vicuna)"), None);
+    }
+
     #[test]
     fn test_parse_nix_store_path_with_version() {
         let update = UpdateChecker::parse_nix_store_path(
@@ -1277,7 +5385,9 @@ these 3 derivations will be built:
   /nix/store/abcdefghijklmnopqrstuvwxyz012345-firefox-120.0.1.drv
   /nix/store/87g71cisdzc9k7izm28f5n6w6icm4rhw-nixos-system-p620-26.05.20260208.d6c7193.drv
 ";
-        let updates = checker.parse_nixos_rebuild_output(output).unwrap();
+        let updates = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::BuildsAndFetches)
+            .unwrap();
         assert_eq!(updates.len(), 3);
         assert_eq!(updates[0].name, "home-manager-path");
         assert_eq!(updates[1].name, "firefox");
@@ -1296,7 +5406,9 @@ these 2 derivations will be built:
 these 1 paths will be fetched (10.5 MiB download, 30.2 MiB unpacked):
   /nix/store/abcdefghijklmnopqrstuvwxyz012345-glibc-2.39
 ";
-        let updates = checker.parse_nixos_rebuild_output(output).unwrap();
+        let updates = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::BuildsAndFetches)
+            .unwrap();
         assert_eq!(updates.len(), 3);
         assert_eq!(updates[0].name, "home-manager-path");
         assert_eq!(updates[1].name, "system-units");
@@ -1308,7 +5420,9 @@ these 1 paths will be fetched (10.5 MiB download, 30.2 MiB unpacked):
     fn test_parse_nixos_rebuild_output_up_to_date() {
         let checker = UpdateChecker::new(PackageManager::NixOS);
         let output = "system is up to date\n";
-        let updates = checker.parse_nixos_rebuild_output(output).unwrap();
+        let updates = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::BuildsAndFetches)
+            .unwrap();
         assert!(updates.is_empty());
     }
 
@@ -1332,7 +5446,9 @@ these 10 derivations will be built:
   /nix/store/qhap4ys9rqdgrv2idxm0cngvixdszyir-activate.drv
   /nix/store/87g71cisdzc9k7izm28f5n6w6icm4rhw-nixos-system-p620-26.05.20260208.d6c7193.drv
 ";
-        let updates = checker.parse_nixos_rebuild_output(output).unwrap();
+        let updates = checker
+            .parse_nixos_rebuild_output(output, NixOSUpdateCount::BuildsAndFetches)
+            .unwrap();
         assert_eq!(updates.len(), 10);
 
         // Verify specific entries
@@ -1350,10 +5466,24 @@ these 10 derivations will be built:
     }
 
     // Integration tests for lock mechanism
+
+    /// Lock file of its own for each test, so tests running in parallel do
+    /// not contend for the applet's lock
+    fn test_lock_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "package-updater-lock-{}-{}.lock",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
     #[tokio::test]
     async fn test_lock_acquisition_and_release() {
         // This test verifies that a lock can be acquired and automatically released
-        let lock_result = UpdateChecker::acquire_lock().await;
+        let lock_path = test_lock_path("lock-acquisition-and-release");
+        let lock_result = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO).await;
         assert!(lock_result.is_ok(), "Failed to acquire lock");
 
         let lock_file = lock_result.unwrap();
@@ -1363,18 +5493,19 @@ these 10 derivations will be built:
         drop(lock_file);
 
         // Should be able to acquire lock again after release
-        let second_lock = UpdateChecker::acquire_lock().await;
+        let second_lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO).await;
         assert!(second_lock.is_ok(), "Failed to acquire lock after release");
     }
 
     #[tokio::test]
     async fn test_concurrent_lock_prevention() {
         // This test verifies that only one instance can hold the lock at a time
-        let lock1 = UpdateChecker::acquire_lock().await;
+        let lock_path = test_lock_path("concurrent-lock-prevention");
+        let lock1 = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO).await;
         assert!(lock1.is_ok(), "First lock acquisition should succeed");
 
         // Try to acquire lock while first one is held
-        let lock2 = UpdateChecker::acquire_lock().await;
+        let lock2 = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO).await;
         assert!(lock2.is_err(), "Second lock acquisition should fail");
         assert!(
             lock2.unwrap_err().to_string().contains("Another instance"),
@@ -1385,7 +5516,7 @@ these 10 derivations will be built:
         drop(lock1);
 
         // Now should be able to acquire
-        let lock3 = UpdateChecker::acquire_lock().await;
+        let lock3 = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO).await;
         assert!(
             lock3.is_ok(),
             "Lock acquisition should succeed after first lock released"
@@ -1399,7 +5530,10 @@ these 10 derivations will be built:
         use tokio::time::Duration;
 
         // Acquire lock in this test
-        let lock = UpdateChecker::acquire_lock().await.unwrap();
+        let lock_path = test_lock_path("lock-retry-logic");
+        let lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO)
+            .await
+            .unwrap();
 
         // Flag to indicate when lock is released
         let lock_released = Arc::new(AtomicBool::new(false));
@@ -1420,19 +5554,79 @@ these 10 derivations will be built:
         );
 
         // Now acquire should succeed
-        let new_lock = UpdateChecker::acquire_lock().await;
+        let new_lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO).await;
         assert!(
             new_lock.is_ok(),
             "Lock acquisition should succeed after wait"
         );
     }
 
+    #[tokio::test]
+    async fn test_lock_backoff_waits_for_release() {
+        let lock_path = test_lock_path("lock-backoff-waits-for-release");
+        let lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO)
+            .await
+            .unwrap();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            drop(lock);
+        });
+
+        // Released during the backoff, well within the budget
+        let started = Instant::now();
+        let new_lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::from_secs(5)).await;
+        assert!(new_lock.is_ok(), "Lock should be acquired once released");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_lock_backoff_gives_up_after_timeout() {
+        let lock_path = test_lock_path("lock-backoff-gives-up-after-timeout");
+        let _lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO)
+            .await
+            .unwrap();
+
+        let started = Instant::now();
+        let result = UpdateChecker::acquire_lock_at(&lock_path, Duration::from_millis(600)).await;
+        assert!(matches!(
+            result.map_err(CheckError::from),
+            Err(CheckError::LockBusy)
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(600));
+    }
+
+    #[test]
+    fn test_parse_lock_pid() {
+        assert_eq!(parse_lock_pid("1234\n"), Some(1234));
+        assert_eq!(parse_lock_pid(""), None);
+        assert_eq!(parse_lock_pid("not a pid"), None);
+    }
+
+    #[test]
+    fn test_live_lock_holder() {
+        // PID 1 always runs; this process and dead PIDs do not block a reset
+        assert_eq!(live_lock_holder("1\n"), Some(1));
+        assert_eq!(live_lock_holder(&std::process::id().to_string()), None);
+        assert_eq!(live_lock_holder(&u32::MAX.to_string()), None);
+        assert_eq!(live_lock_holder(""), None);
+    }
+
+    #[test]
+    fn test_process_exists() {
+        assert!(process_exists(std::process::id()));
+        // Above the kernel's maximum PID (4194304)
+        assert!(!process_exists(u32::MAX));
+    }
+
     #[tokio::test]
     async fn test_lock_file_contains_pid() {
         use std::io::Read;
 
-        let lock = UpdateChecker::acquire_lock().await.unwrap();
-        let lock_path = UpdateChecker::get_lock_path();
+        let lock_path = test_lock_path("lock-file-contains-pid");
+        let lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO)
+            .await
+            .unwrap();
 
         // Read lock file contents
         let mut file = std::fs::File::open(&lock_path).unwrap();
@@ -1482,8 +5676,9 @@ these 10 derivations will be built:
     #[tokio::test]
     async fn test_multiple_sequential_lock_acquisitions() {
         // Test that multiple sequential lock operations work correctly
+        let lock_path = test_lock_path("multiple-sequential-lock-acquisitions");
         for i in 0..5 {
-            let lock = UpdateChecker::acquire_lock().await;
+            let lock = UpdateChecker::acquire_lock_at(&lock_path, Duration::ZERO).await;
             assert!(lock.is_ok(), "Lock acquisition {} should succeed", i);
             drop(lock);
         }
@@ -1512,4 +5707,39 @@ these 10 derivations will be built:
             "Lock path should end with correct filename"
         );
     }
+
+    #[test]
+    fn test_listing_exit_codes() {
+        for pm in PackageManager::ALL {
+            let expected = match pm {
+                PackageManager::Pacman => (Some(2), None),
+                PackageManager::Paru | PackageManager::Yay => (Some(1), None),
+                PackageManager::Dnf => (None, Some(100)),
+                _ => (None, None),
+            };
+            assert_eq!(
+                (pm.no_updates_exit_code(), pm.updates_available_exit_code()),
+                expected,
+                "{}",
+                pm.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_package_page_url() {
+        assert_eq!(
+            PackageManager::Pacman.package_page_url("linux", false),
+            Some("https://archlinux.org/packages/?q=linux".to_string())
+        );
+        assert_eq!(
+            PackageManager::Paru.package_page_url("gtk2+extra", false),
+            Some("https://archlinux.org/packages/?q=gtk2%2Bextra".to_string())
+        );
+        assert_eq!(
+            PackageManager::Yay.package_page_url("visual-studio-code-bin", true),
+            Some("https://aur.archlinux.org/packages/visual-studio-code-bin".to_string())
+        );
+        assert_eq!(PackageManager::Apt.package_page_url("curl", false), None);
+    }
 }
@@ -1,12 +1,95 @@
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-/// Get the XDG runtime directory, falling back to /tmp
+/// Runtime directory resolved on first use
+static RUNTIME_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Get the XDG runtime directory
+///
+/// Falls back to a private per-user directory under /tmp when
+/// `XDG_RUNTIME_DIR` is unset, read-only or full. Writability is probed
+/// once and the result is reused for the lifetime of the process.
 pub fn runtime_dir() -> PathBuf {
-    let dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| {
-        eprintln!("Warning: XDG_RUNTIME_DIR not set, using /tmp");
-        "/tmp".to_string()
-    });
-    PathBuf::from(dir)
+    RUNTIME_DIR.get_or_init(resolve_runtime_dir).clone()
+}
+
+fn resolve_runtime_dir() -> PathBuf {
+    match std::env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => {
+            let dir = PathBuf::from(dir);
+            if is_writable_dir(&dir) {
+                return dir;
+            }
+            eprintln!(
+                "Warning: XDG_RUNTIME_DIR {} is not writable, using fallback",
+                dir.display()
+            );
+        }
+        _ => eprintln!("Warning: XDG_RUNTIME_DIR not set, using fallback"),
+    }
+    fallback_runtime_dir()
+}
+
+/// Create (or reuse) `/tmp/cosmic-package-updater-$UID` with 0700 permissions
+///
+/// An existing directory is only reused when it is a real directory owned by
+/// the current user; otherwise the shared temp directory is used as a last resort.
+fn fallback_runtime_dir() -> PathBuf {
+    let uid = nix::unistd::getuid().as_raw();
+    let dir = std::env::temp_dir().join(format!("cosmic-package-updater-{}", uid));
+
+    if let Err(e) = DirBuilder::new().mode(0o700).create(&dir) {
+        if e.kind() != std::io::ErrorKind::AlreadyExists {
+            eprintln!("Warning: Failed to create {}: {}", dir.display(), e);
+            return std::env::temp_dir();
+        }
+    }
+
+    match std::fs::symlink_metadata(&dir) {
+        Ok(meta) if meta.is_dir() && meta.uid() == uid => {
+            if meta.permissions().mode() & 0o777 != 0o700 {
+                if let Err(e) =
+                    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+                {
+                    eprintln!("Warning: Failed to restrict {}: {}", dir.display(), e);
+                }
+            }
+            dir
+        }
+        _ => {
+            eprintln!(
+                "Warning: {} is not a directory owned by the current user, using {}",
+                dir.display(),
+                std::env::temp_dir().display()
+            );
+            std::env::temp_dir()
+        }
+    }
+}
+
+/// Check that files can actually be written to `dir`
+///
+/// Writes a byte rather than only creating a file so a full tmpfs is detected too.
+fn is_writable_dir(dir: &Path) -> bool {
+    let probe = dir.join(format!(
+        ".cosmic-package-updater-probe-{}",
+        std::process::id()
+    ));
+
+    let writable = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&probe)
+        .and_then(|mut file| file.write_all(b"\0"))
+        .is_ok();
+
+    let _ = std::fs::remove_file(&probe);
+    writable
 }
 
 /// Path to the lock file for preventing concurrent update checks
@@ -18,3 +101,92 @@ pub fn lock_path() -> PathBuf {
 pub fn sync_path() -> PathBuf {
     runtime_dir().join("cosmic-package-updater.sync")
 }
+
+/// Get the applet's XDG state directory (`$XDG_STATE_HOME/cosmic-package-updater`),
+/// falling back to `~/.local/state` and finally the runtime directory
+pub fn state_dir() -> PathBuf {
+    let base = std::env::var("XDG_STATE_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .filter(|home| !home.is_empty())
+                .map(|home| PathBuf::from(home).join(".local/state"))
+        })
+        .unwrap_or_else(runtime_dir);
+    base.join("cosmic-package-updater")
+}
+
+/// Path to the debug log of update checks
+pub fn check_log_path() -> PathBuf {
+    state_dir().join("checks.log")
+}
+
+/// Path the pending versions of the last check are kept at, for the
+/// change summary after a restart
+pub fn previous_updates_path() -> PathBuf {
+    state_dir().join("previous-updates.json")
+}
+
+/// Path the update list is exported to
+pub fn export_path() -> PathBuf {
+    state_dir().join("last-updates.json")
+}
+
+/// Write `contents` to `path` through a temporary file next to it, so
+/// readers never see a partial file, creating the parent directory first
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, contents).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_writable_dir() {
+        assert!(is_writable_dir(&std::env::temp_dir()));
+        assert!(!is_writable_dir(Path::new(
+            "/nonexistent/cosmic-package-updater-test"
+        )));
+    }
+
+    #[test]
+    fn test_write_atomic_creates_parent() {
+        let dir = std::env::temp_dir().join(format!(
+            "cosmic-package-updater-export-{}",
+            std::process::id()
+        ));
+        let path = dir.join("nested/last-updates.json");
+
+        write_atomic(&path, b"{}").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+
+        write_atomic(&path, b"[]").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+        assert!(!dir.join("nested/last-updates.json.tmp").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fallback_runtime_dir_is_private() {
+        let dir = fallback_runtime_dir();
+        if dir != std::env::temp_dir() {
+            let meta = std::fs::metadata(&dir).unwrap();
+            assert!(meta.is_dir());
+            assert_eq!(meta.permissions().mode() & 0o777, 0o700);
+        }
+    }
+}
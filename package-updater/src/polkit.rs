@@ -12,16 +12,31 @@
 /// - No need for sudoers configuration
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU8, Ordering};
 use tokio::process::Command as TokioCommand;
 use zbus::{zvariant, Connection};
 
+/// Attempts of a PolicyKit D-Bus call before the bus is considered unavailable
+const DBUS_RETRY_ATTEMPTS: u32 = 3;
+const DBUS_RETRY_DELAY_MS: u64 = 200;
+
+/// Cached result of [`PolkitAuth::is_available`]
+static AVAILABILITY: AtomicU8 = AtomicU8::new(AVAILABILITY_UNKNOWN);
+const AVAILABILITY_UNKNOWN: u8 = 0;
+const AVAILABILITY_NO: u8 = 1;
+const AVAILABILITY_YES: u8 = 2;
+
 /// PolicyKit action identifier for package updates
-#[allow(dead_code)]
 pub const POLKIT_ACTION_UPDATE: &str = "com.github.cosmic-ext.package-updater.update";
 
 /// PolicyKit action identifier for checking updates
 pub const POLKIT_ACTION_CHECK: &str = "com.github.cosmic-ext.package-updater.check";
 
+/// Policy file declaring the applet's actions, for installers that write it to
+/// `/usr/share/polkit-1/actions/` themselves (`--print-polkit-policy`)
+pub const POLICY_FILE: &str =
+    include_str!("../../policy/com.github.cosmic-ext.package-updater.policy");
+
 /// PolicyKit authentication helper using D-Bus
 pub struct PolkitAuth {
     connection: Connection,
@@ -48,8 +63,42 @@ impl PolkitAuth {
     ///
     /// # Returns
     ///
-    /// `true` if authorized, `false` if not authorized or if PolicyKit is unavailable
+    /// `true` if authorized, `false` if PolicyKit refused the request.
+    ///
+    /// # Errors
+    ///
+    /// Transport errors are retried a few times with a short delay; an error is
+    /// only returned when the bus stays unavailable.
     pub async fn check_authorization(&self, action_id: &str) -> Result<bool> {
+        let mut attempt = 1;
+        loop {
+            match self.query_authorization(action_id).await {
+                Ok(is_authorized) => return Ok(is_authorized),
+                Err(e) if is_polkit_refusal(&e) => {
+                    crate::check_log::warn(format!(
+                        "PolicyKit refused the authorization check: {}",
+                        e
+                    ));
+                    return Ok(false);
+                }
+                Err(e) if attempt < DBUS_RETRY_ATTEMPTS => {
+                    crate::check_log::warn(format!(
+                        "PolicyKit authorization check failed (attempt {}): {}, retrying",
+                        attempt, e
+                    ));
+                    tokio::time::sleep(std::time::Duration::from_millis(DBUS_RETRY_DELAY_MS))
+                        .await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(anyhow!("PolicyKit authorization check failed: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Single `CheckAuthorization` call to the PolicyKit authority
+    async fn query_authorization(&self, action_id: &str) -> zbus::Result<bool> {
         let proxy = zbus::Proxy::new(
             &self.connection,
             "org.freedesktop.PolicyKit1",
@@ -72,20 +121,15 @@ impl PolkitAuth {
         let details: HashMap<&str, &str> = HashMap::new();
 
         // Check authorization
-        let result: zbus::Result<(bool, bool, HashMap<String, String>)> = proxy
-            .call(
-                "CheckAuthorization",
-                &(subject, action_id, details, 1u32, ""),
-            )
-            .await;
+        let (is_authorized, _is_challenge, _details): (bool, bool, HashMap<String, String>) =
+            proxy
+                .call(
+                    "CheckAuthorization",
+                    &(subject, action_id, details, 1u32, ""),
+                )
+                .await?;
 
-        match result {
-            Ok((is_authorized, _is_challenge, _details)) => Ok(is_authorized),
-            Err(e) => {
-                eprintln!("PolicyKit authorization check failed: {}", e);
-                Ok(false)
-            }
-        }
+        Ok(is_authorized)
     }
 
     /// Request authorization for an action (shows authentication dialog if needed)
@@ -144,22 +188,25 @@ impl PolkitAuth {
             }
         }
 
-        // Execute command with pkexec
-        let output = TokioCommand::new("pkexec")
-            .arg("--user")
-            .arg("root")
-            .arg(command)
-            .args(args)
-            .env("PKEXEC_MESSAGE", message)
-            .output()
+        // pkexec becomes the root command, which this user cannot signal, so
+        // once started it runs to completion even if the caller is cancelled
+        let mut cmd = TokioCommand::new("pkexec");
+        cmd.args(pkexec_args(command, args))
+            .env("PKEXEC_MESSAGE", message);
+        let output = tokio::spawn(async move { cmd.output().await })
             .await
+            .map_err(|e| anyhow!("pkexec task failed: {}", e))?
             .map_err(|e| anyhow!("Failed to execute command with pkexec: {}", e))?;
 
         if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if pkexec_lost_polkit(output.status.code(), &stderr) {
+                Self::invalidate_availability();
+            }
             return Err(anyhow!(
                 "Command failed with exit code: {:?}. Stderr: {}",
                 output.status.code(),
-                String::from_utf8_lossy(&output.stderr)
+                stderr
             ));
         }
 
@@ -168,10 +215,36 @@ impl PolkitAuth {
 
     /// Check if PolicyKit is available on the system
     ///
+    /// The system is probed once and the answer reused for the rest of the
+    /// process, until `invalidate_availability` finds polkit gone.
+    ///
     /// # Returns
     ///
     /// `true` if PolicyKit and pkexec are available
     pub async fn is_available() -> bool {
+        match AVAILABILITY.load(Ordering::Relaxed) {
+            AVAILABILITY_YES => return true,
+            AVAILABILITY_NO => return false,
+            _ => {}
+        }
+
+        let available = Self::probe_available().await;
+        let state = if available {
+            AVAILABILITY_YES
+        } else {
+            AVAILABILITY_NO
+        };
+        AVAILABILITY.store(state, Ordering::Relaxed);
+        available
+    }
+
+    /// Forget the cached availability so the next check probes again,
+    /// e.g. after polkit was installed or restarted
+    fn invalidate_availability() {
+        AVAILABILITY.store(AVAILABILITY_UNKNOWN, Ordering::Relaxed);
+    }
+
+    async fn probe_available() -> bool {
         // Check if pkexec is available
         let pkexec_check = std::process::Command::new("which").arg("pkexec").output();
 
@@ -195,6 +268,16 @@ impl PolkitAuth {
     }
 }
 
+/// Whether a D-Bus error is PolicyKit answering the request (e.g. NotAuthorized)
+/// rather than the bus failing to deliver it
+fn is_polkit_refusal(error: &zbus::Error) -> bool {
+    matches!(
+        error,
+        zbus::Error::MethodError(name, _, _)
+            if name.as_str().starts_with("org.freedesktop.PolicyKit1.Error.")
+    )
+}
+
 /// Fallback to sudo if PolicyKit is not available
 ///
 /// This function attempts to use PolicyKit first, and falls back to sudo
@@ -226,25 +309,27 @@ pub async fn execute_privileged(
                 {
                     Ok(output) => return Ok(output),
                     Err(e) => {
-                        eprintln!("PolicyKit execution failed: {}, falling back to sudo", e);
+                        crate::check_log::warn(format!(
+                            "PolicyKit execution failed: {}, falling back to sudo",
+                            e
+                        ));
                     }
                 }
             }
             Err(e) => {
-                eprintln!(
+                crate::check_log::warn(format!(
                     "Failed to initialize PolicyKit: {}, falling back to sudo",
                     e
-                );
+                ));
             }
         }
     }
 
     // Fallback to sudo
-    eprintln!("Using sudo fallback for privileged operation");
-    let output = TokioCommand::new("sudo")
-        .arg(command)
-        .args(args)
-        .output()
+    crate::check_log::warn("Using sudo fallback for privileged operation");
+    let mut cmd = TokioCommand::new("sudo");
+    cmd.arg(command).args(args);
+    let output = crate::runner::output_terminating_on_drop(cmd)
         .await
         .map_err(|e| anyhow!("Failed to execute with sudo: {}", e))?;
 
@@ -258,6 +343,56 @@ pub async fn execute_privileged(
     Ok(output)
 }
 
+/// Whether pkexec failed because polkit itself is gone (no agent, no
+/// daemon) rather than because the command failed or was denied
+fn pkexec_lost_polkit(code: Option<i32>, stderr: &str) -> bool {
+    code == Some(127)
+        && (stderr.contains("No authentication agent found")
+            || stderr.contains("Error checking for authorization"))
+}
+
+/// Arguments of the `pkexec` call running `command` with `args` as root
+fn pkexec_args<'a>(command: &'a str, args: &[&'a str]) -> Vec<&'a str> {
+    ["--user", "root", command]
+        .into_iter()
+        .chain(args.iter().copied())
+        .collect()
+}
+
+/// Shell-style command line of the `pkexec` call that
+/// [`execute_as_root`](PolkitAuth::execute_as_root) spawns, for display
+pub fn pkexec_command_line(command: &str, args: &[&str]) -> String {
+    std::iter::once("pkexec")
+        .chain(pkexec_args(command, args))
+        .map(|word| shell_escape::escape(word.into()).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Install updates with privilege, authorized as [`POLKIT_ACTION_UPDATE`]
+///
+/// Using a separate action from checks lets polkit rules allow unattended
+/// checks while still requiring authentication to install. Unlike
+/// [`execute_privileged`] there is no sudo fallback: without a terminal sudo
+/// cannot ask for a password.
+pub async fn execute_privileged_update(
+    command: &str,
+    args: &[&str],
+) -> Result<std::process::Output> {
+    if !PolkitAuth::is_available().await {
+        return Err(anyhow!("PolicyKit is not available"));
+    }
+    PolkitAuth::new()
+        .await?
+        .execute_as_root(
+            POLKIT_ACTION_UPDATE,
+            command,
+            args,
+            "Authentication required to install package updates",
+        )
+        .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -270,6 +405,44 @@ mod tests {
         // Don't assert true/false as it depends on system configuration
     }
 
+    #[tokio::test]
+    async fn test_polkit_availability_is_cached() {
+        let first = PolkitAuth::is_available().await;
+        assert_ne!(AVAILABILITY.load(Ordering::Relaxed), AVAILABILITY_UNKNOWN);
+        assert_eq!(PolkitAuth::is_available().await, first);
+
+        PolkitAuth::invalidate_availability();
+        assert_eq!(PolkitAuth::is_available().await, first);
+    }
+
+    #[test]
+    fn test_pkexec_lost_polkit() {
+        assert!(pkexec_lost_polkit(
+            Some(127),
+            "Error executing command as another user: No authentication agent found.\n"
+        ));
+        assert!(pkexec_lost_polkit(
+            Some(127),
+            "Error checking for authorization org.freedesktop.policykit.exec: \
+             GDBus.Error:org.freedesktop.DBus.Error.ServiceUnknown\n"
+        ));
+        // Dismissed dialog, denied, or the command itself failing
+        assert!(!pkexec_lost_polkit(Some(126), ""));
+        assert!(!pkexec_lost_polkit(
+            Some(127),
+            "Error executing command as another user: Not authorized\n"
+        ));
+        assert!(!pkexec_lost_polkit(Some(1), "E: Could not get lock\n"));
+    }
+
+    #[test]
+    fn test_pkexec_command_line() {
+        assert_eq!(
+            pkexec_command_line("apt-get", &["-y", "upgrade"]),
+            "pkexec --user root apt-get -y upgrade"
+        );
+    }
+
     #[test]
     fn test_action_constants() {
         assert_eq!(
@@ -281,4 +454,19 @@ mod tests {
             "com.github.cosmic-ext.package-updater.check"
         );
     }
+
+    #[test]
+    fn test_transport_errors_are_not_refusals() {
+        let error = zbus::Error::InputOutput(std::sync::Arc::new(std::io::Error::other(
+            "connection reset",
+        )));
+        assert!(!is_polkit_refusal(&error));
+        assert!(!is_polkit_refusal(&zbus::Error::Failure("no reply".to_string())));
+    }
+
+    #[test]
+    fn test_policy_file_declares_actions() {
+        assert!(POLICY_FILE.contains(&format!("<action id=\"{}\">", POLKIT_ACTION_UPDATE)));
+        assert!(POLICY_FILE.contains(&format!("<action id=\"{}\">", POLKIT_ACTION_CHECK)));
+    }
 }
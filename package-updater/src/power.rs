@@ -0,0 +1,131 @@
+//! Power source detection for `check_only_on_ac`.
+
+use std::path::Path;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Whether the machine runs on external power.
+///
+/// Desktops without a battery, and machines whose power supplies cannot be
+/// read, count as on AC.
+pub fn on_ac_power() -> bool {
+    on_ac_power_in(Path::new(POWER_SUPPLY_DIR))
+}
+
+fn on_ac_power_in(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return true;
+    };
+
+    let mut has_adapter = false;
+    let mut has_battery = false;
+    let mut discharging = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let read = |name: &str| {
+            std::fs::read_to_string(path.join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            "Mains" | "USB" => {
+                if read("online") == "1" {
+                    return true;
+                }
+                has_adapter = true;
+            }
+            // Peripheral batteries (mice, headsets) have scope "Device"
+            "Battery" if read("scope") != "Device" => {
+                has_battery = true;
+                discharging |= read("status") == "Discharging";
+            }
+            _ => {}
+        }
+    }
+
+    if has_adapter {
+        // An adapter that is present but offline means battery power,
+        // unless there is no system battery to run on
+        !has_battery
+    } else {
+        !discharging
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn supplies(name: &str, entries: &[(&str, &[(&str, &str)])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("power-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (supply, attributes) in entries {
+            let supply = dir.join(supply);
+            std::fs::create_dir_all(&supply).unwrap();
+            for (attribute, value) in *attributes {
+                std::fs::write(supply.join(attribute), format!("{}\n", value)).unwrap();
+            }
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_laptop_on_ac_and_battery() {
+        let plugged = supplies(
+            "plugged",
+            &[
+                ("AC", &[("type", "Mains"), ("online", "1")]),
+                ("BAT0", &[("type", "Battery"), ("status", "Charging")]),
+            ],
+        );
+        assert!(on_ac_power_in(&plugged));
+
+        let unplugged = supplies(
+            "unplugged",
+            &[
+                ("AC", &[("type", "Mains"), ("online", "0")]),
+                ("BAT0", &[("type", "Battery"), ("status", "Discharging")]),
+            ],
+        );
+        assert!(!on_ac_power_in(&unplugged));
+
+        let _ = std::fs::remove_dir_all(plugged);
+        let _ = std::fs::remove_dir_all(unplugged);
+    }
+
+    #[test]
+    fn test_battery_without_adapter_uses_status() {
+        let dir = supplies(
+            "no-adapter",
+            &[("BAT0", &[("type", "Battery"), ("status", "Discharging")])],
+        );
+        assert!(!on_ac_power_in(&dir));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_desktop_counts_as_ac() {
+        let empty = supplies("desktop", &[]);
+        assert!(on_ac_power_in(&empty));
+
+        // A discharging wireless mouse is not the system battery
+        let mouse = supplies(
+            "mouse",
+            &[(
+                "hidpp_battery_0",
+                &[
+                    ("type", "Battery"),
+                    ("scope", "Device"),
+                    ("status", "Discharging"),
+                ],
+            )],
+        );
+        assert!(on_ac_power_in(&mouse));
+
+        assert!(on_ac_power_in(Path::new("/nonexistent/power_supply")));
+        let _ = std::fs::remove_dir_all(empty);
+        let _ = std::fs::remove_dir_all(mouse);
+    }
+}
@@ -0,0 +1,368 @@
+//! Where update check commands run: on this machine, on a remote host over
+//! SSH, or, in tests, nowhere at all.
+
+use futures::future::BoxFuture;
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use std::borrow::Cow;
+use std::io;
+use std::process::{Output, Stdio};
+use tokio::process::Command as TokioCommand;
+
+/// SSH options for unattended checks: never prompt, give up quickly
+const SSH_OPTIONS: &[&str] = &["-o", "BatchMode=yes", "-o", "ConnectTimeout=10"];
+
+/// Runs the commands of an update check.
+pub trait CommandRunner: Send + Sync {
+    /// Run `program` with `args` and the extra environment variables `env`
+    /// to completion, capturing its output
+    fn output<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [String],
+        env: &'a [(&'a str, &'a str)],
+    ) -> BoxFuture<'a, io::Result<Output>>;
+
+    /// Shell-style command line for display, `env` written as leading
+    /// `NAME=value` assignments
+    fn command_line(&self, program: &str, args: &[String], env: &[(&str, &str)]) -> String;
+
+    /// Whether commands run on this machine, so local-only steps such as the
+    /// instance lock apply
+    fn is_local(&self) -> bool;
+}
+
+/// Runs commands as real processes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ProcessRunner {
+    /// Run directly on this machine
+    #[default]
+    Local,
+    /// Run through `ssh <target>`, e.g. `user@host` or a `~/.ssh/config` alias
+    Ssh(String),
+}
+
+impl ProcessRunner {
+    /// Command running `program` with `args` and the extra environment
+    /// variables `env`.
+    ///
+    /// ssh joins its arguments into one string for the remote shell, so each
+    /// word is shell-escaped to arrive unchanged, and `env` becomes leading
+    /// assignments there since ssh does not forward the local environment.
+    pub fn command<I, S>(&self, program: &str, args: I, env: &[(&str, &str)]) -> TokioCommand
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        match self {
+            ProcessRunner::Local => {
+                let mut cmd = TokioCommand::new(program);
+                cmd.args(args.into_iter().map(|arg| arg.as_ref().to_string()))
+                    .envs(env.iter().copied());
+                cmd
+            }
+            ProcessRunner::Ssh(target) => {
+                let mut cmd = TokioCommand::new("ssh");
+                // `--` ends the options, so no target is read as one
+                cmd.args(SSH_OPTIONS)
+                    .arg("--")
+                    .arg(target)
+                    .arg(Self::remote_command_line(program, args, env));
+                cmd
+            }
+        }
+    }
+
+    /// The remote shell command line for `program` with `args`, preceded by
+    /// the `env` assignments
+    fn remote_command_line<I, S>(program: &str, args: I, env: &[(&str, &str)]) -> String
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        env.iter()
+            .map(|(name, value)| {
+                format!("{}={}", name, shell_escape::escape(Cow::Borrowed(*value)))
+            })
+            .chain(std::iter::once(
+                shell_escape::escape(Cow::Borrowed(program)).into_owned(),
+            ))
+            .chain(
+                args.into_iter()
+                    .map(|arg| shell_escape::escape(Cow::Borrowed(arg.as_ref())).into_owned()),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Shell-style command line for display, as [`command`](Self::command)
+    /// would run it
+    pub fn command_line<I, S>(&self, program: &str, args: I, env: &[(&str, &str)]) -> String
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let line = Self::remote_command_line(program, args, env);
+        match self {
+            ProcessRunner::Local => line,
+            ProcessRunner::Ssh(target) => format!(
+                "ssh {} -- {} {}",
+                SSH_OPTIONS.join(" "),
+                target,
+                shell_escape::escape(Cow::Owned(line))
+            ),
+        }
+    }
+
+    /// Label for logs and the popup: "local" or the SSH target
+    pub fn label(&self) -> &str {
+        match self {
+            ProcessRunner::Local => "local",
+            ProcessRunner::Ssh(target) => target,
+        }
+    }
+}
+
+impl CommandRunner for ProcessRunner {
+    fn output<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [String],
+        env: &'a [(&'a str, &'a str)],
+    ) -> BoxFuture<'a, io::Result<Output>> {
+        Box::pin(output_terminating_on_drop(self.command(program, args, env)))
+    }
+
+    fn command_line(&self, program: &str, args: &[String], env: &[(&str, &str)]) -> String {
+        ProcessRunner::command_line(self, program, args, env)
+    }
+
+    fn is_local(&self) -> bool {
+        matches!(self, ProcessRunner::Local)
+    }
+}
+
+/// Sends SIGTERM to a process group when dropped, unless disarmed
+struct TerminateGroupOnDrop(Option<Pid>);
+
+impl Drop for TerminateGroupOnDrop {
+    fn drop(&mut self) {
+        if let Some(group) = self.0 {
+            // Fails for groups that are gone or only hold root processes
+            let _ = killpg(group, Signal::SIGTERM);
+        }
+    }
+}
+
+/// Run `cmd` to completion in its own process group, capturing its output.
+///
+/// Dropping the future, as a cancelled check does, sends the group SIGTERM
+/// instead of SIGKILL: `sudo` relays it to the privileged command, which
+/// then exits and releases its lock. A killed `sudo` would leave that
+/// command running as an orphan.
+pub async fn output_terminating_on_drop(mut cmd: TokioCommand) -> io::Result<Output> {
+    let child = cmd
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut guard = TerminateGroupOnDrop(child.id().map(|pid| Pid::from_raw(pid as i32)));
+    let output = child.wait_with_output().await;
+    guard.0 = None;
+    output
+}
+
+/// Canned command results for tests, keyed by command line.
+///
+/// Commands without a canned result fail to spawn, like a missing binary.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockRunner {
+    responses: std::collections::HashMap<String, (i32, String, String)>,
+    /// Command lines run, shared with [`calls`](Self::calls) handles
+    calls: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl MockRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `command_line`, written as [`CommandRunner::command_line`]
+    /// shows it, with `stdout` and exit code `code`
+    pub fn respond(self, command_line: &str, code: i32, stdout: &str) -> Self {
+        self.respond_full(command_line, code, stdout, "")
+    }
+
+    /// Handle on the command lines run, still readable once the runner was
+    /// moved into a checker
+    pub fn calls(&self) -> std::sync::Arc<std::sync::Mutex<Vec<String>>> {
+        self.calls.clone()
+    }
+
+    /// Answer `command_line` with both `stdout` and `stderr`
+    pub fn respond_full(
+        mut self,
+        command_line: &str,
+        code: i32,
+        stdout: &str,
+        stderr: &str,
+    ) -> Self {
+        self.responses.insert(
+            command_line.to_string(),
+            (code, stdout.to_string(), stderr.to_string()),
+        );
+        self
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for MockRunner {
+    fn output<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [String],
+        env: &'a [(&'a str, &'a str)],
+    ) -> BoxFuture<'a, io::Result<Output>> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let line = self.command_line(program, args, env);
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(line.clone());
+        }
+        let result = match self.responses.get(&line) {
+            Some((code, stdout, stderr)) => Ok(Output {
+                // A wait status keeps the exit code in its second byte
+                status: std::process::ExitStatus::from_raw(code << 8),
+                stdout: stdout.clone().into_bytes(),
+                stderr: stderr.clone().into_bytes(),
+            }),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no canned output for `{}`", line),
+            )),
+        };
+        Box::pin(std::future::ready(result))
+    }
+
+    fn command_line(&self, program: &str, args: &[String], env: &[(&str, &str)]) -> String {
+        ProcessRunner::remote_command_line(program, args, env)
+    }
+
+    /// Like a remote host: no lock or sync file on this machine
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_remote_command_line_escapes_words() {
+        assert_eq!(
+            ProcessRunner::remote_command_line("pacman", ["-Qu"], &[]),
+            "pacman -Qu"
+        );
+        assert_eq!(
+            ProcessRunner::remote_command_line("nix", ["profile", "upgrade", ".*"], &[]),
+            "nix profile upgrade '.*'"
+        );
+        assert_eq!(
+            ProcessRunner::remote_command_line("pacman", ["--print-format", "%n %s"], &[]),
+            "pacman --print-format '%n %s'"
+        );
+        assert_eq!(
+            ProcessRunner::remote_command_line("pacman", ["-Qi", "yay"], &[("LC_ALL", "C")]),
+            "LC_ALL=C pacman -Qi yay"
+        );
+    }
+
+    #[test]
+    fn test_ssh_command_targets_host() {
+        let runner = ProcessRunner::Ssh("admin@nas".to_string());
+        let cmd = runner.command("apt", ["list", "--upgradable"], &[]);
+        let cmd = cmd.as_std();
+
+        assert_eq!(cmd.get_program(), "ssh");
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+        assert_eq!(args[args.len() - 3], "--");
+        assert_eq!(args[args.len() - 2], "admin@nas");
+        assert_eq!(args[args.len() - 1], "apt list --upgradable");
+        assert!(!runner.is_local());
+        assert_eq!(runner.label(), "admin@nas");
+    }
+
+    #[test]
+    fn test_command_line_for_display() {
+        assert_eq!(
+            ProcessRunner::Local.command_line("checkupdates", Vec::<&str>::new(), &[]),
+            "checkupdates"
+        );
+        assert_eq!(
+            ProcessRunner::Ssh("nas".to_string()).command_line(
+                "apt",
+                ["list", "--upgradable"],
+                &[]
+            ),
+            "ssh -o BatchMode=yes -o ConnectTimeout=10 -- nas 'apt list --upgradable'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_runner_returns_canned_output() {
+        let runner =
+            MockRunner::new().respond("dnf check-update -q", 100, "curl.x86_64 8.0 updates\n");
+        let args = ["check-update".to_string(), "-q".to_string()];
+
+        let output = runner.output("dnf", &args, &[]).await.unwrap();
+        assert_eq!(output.status.code(), Some(100));
+        assert_eq!(output.stdout, b"curl.x86_64 8.0 updates\n");
+
+        let missing = runner.output("apt", &[], &[]).await.unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_output_terminates_process_group() {
+        let pid_file =
+            std::env::temp_dir().join(format!("runner-group-test-{}", std::process::id()));
+        let mut cmd = TokioCommand::new("sh");
+        cmd.arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}; wait", pid_file.display()));
+
+        let run = output_terminating_on_drop(cmd);
+        assert!(tokio::time::timeout(Duration::from_millis(500), run)
+            .await
+            .is_err());
+
+        // The grandchild shares the group and gets the signal too
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let proc_dir = std::path::PathBuf::from(format!("/proc/{}", pid.trim()));
+        let mut gone = false;
+        for _ in 0..40 {
+            if !proc_dir.exists() {
+                gone = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(gone, "sleep {} still running", pid.trim());
+        let _ = std::fs::remove_file(pid_file);
+    }
+
+    #[test]
+    fn test_local_command_runs_program() {
+        let cmd = ProcessRunner::Local.command("dnf", ["check-update"], &[("LC_ALL", "C")]);
+        let cmd = cmd.as_std();
+
+        assert_eq!(cmd.get_program(), "dnf");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["check-update"]);
+        let envs: Vec<_> = cmd.get_envs().collect();
+        assert_eq!(envs, [("LC_ALL".as_ref(), Some("C".as_ref()))]);
+    }
+}
@@ -0,0 +1,134 @@
+/// Resolution of the terminal emulator used to run system updates
+use regex::Regex;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+/// Terminal used when nothing else is configured
+pub const FALLBACK_TERMINAL: &str = "cosmic-term";
+
+/// Terminals whose launcher hands the window to an already running server
+/// (or forks) and returns at once, so their exit says nothing about the update
+const DAEMONIZING_TERMINALS: &[&str] = &[
+    "gnome-terminal",
+    "mate-terminal",
+    "xfce4-terminal",
+    "tilix",
+    "kgx",
+    "ptyxis",
+];
+
+/// Matches the terminal entry of COSMIC's system actions (`Terminal: "cosmic-term",`)
+static COSMIC_TERMINAL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bTerminal\s*:\s*"([^"]+)""#).unwrap());
+
+/// Resolve the system default terminal.
+///
+/// Priority order: the terminal set in COSMIC settings, `$TERMINAL`,
+/// `x-terminal-emulator` (Debian alternatives), then cosmic-term.
+pub fn default_terminal() -> String {
+    cosmic_terminal()
+        .or_else(|| {
+            std::env::var("TERMINAL")
+                .ok()
+                .map(|terminal| terminal.trim().to_string())
+                .filter(|terminal| !terminal.is_empty())
+        })
+        .or_else(|| in_path("x-terminal-emulator").then(|| "x-terminal-emulator".to_string()))
+        .unwrap_or_else(|| FALLBACK_TERMINAL.to_string())
+}
+
+/// Terminal configured in COSMIC's shortcut system actions, if any
+fn cosmic_terminal() -> Option<String> {
+    let config_home = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+
+    let path = config_home.join("cosmic/com.system76.CosmicSettings.Shortcuts/v1/system_actions");
+    let contents = std::fs::read_to_string(path).ok()?;
+    parse_cosmic_terminal(&contents)
+}
+
+/// Extract the terminal command from the contents of COSMIC's `system_actions` file
+fn parse_cosmic_terminal(contents: &str) -> Option<String> {
+    COSMIC_TERMINAL_REGEX
+        .captures(contents)
+        .map(|caps| caps[1].trim().to_string())
+        .filter(|terminal| !terminal.is_empty())
+}
+
+/// Whether the terminal returns before its window closes.
+///
+/// `x-terminal-emulator` is resolved to the terminal it links to.
+pub fn daemonizes(terminal: &str) -> bool {
+    let path = if terminal.contains('/') {
+        Some(PathBuf::from(terminal))
+    } else {
+        find_in_path(terminal)
+    };
+    let resolved = path.and_then(|path| std::fs::canonicalize(path).ok());
+    let name = resolved
+        .as_deref()
+        .and_then(|path| path.file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or(terminal);
+    is_daemonizing_name(name)
+}
+
+fn is_daemonizing_name(name: &str) -> bool {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    // Debian installs e.g. gnome-terminal.wrapper as the alternative
+    let name = name.strip_suffix(".wrapper").unwrap_or(name);
+    DAEMONIZING_TERMINALS.contains(&name)
+}
+
+/// Check whether an executable with this name is on `$PATH`
+fn in_path(name: &str) -> bool {
+    find_in_path(name).is_some()
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cosmic_terminal() {
+        let contents = r#"{
+    Terminal: "alacritty",
+    WebBrowser: "firefox",
+}"#;
+        assert_eq!(
+            parse_cosmic_terminal(contents),
+            Some("alacritty".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_cosmic_terminal_missing() {
+        assert_eq!(parse_cosmic_terminal("{ WebBrowser: \"firefox\" }"), None);
+        assert_eq!(parse_cosmic_terminal("{ Terminal: \"\" }"), None);
+        assert_eq!(parse_cosmic_terminal(""), None);
+    }
+
+    #[test]
+    fn test_daemonizing_terminals() {
+        assert!(is_daemonizing_name("gnome-terminal"));
+        assert!(is_daemonizing_name("gnome-terminal.wrapper"));
+        assert!(is_daemonizing_name("/usr/bin/xfce4-terminal"));
+        assert!(!is_daemonizing_name("cosmic-term"));
+        assert!(!is_daemonizing_name("alacritty"));
+        assert!(!daemonizes("/nonexistent/alacritty"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Relative times such as "5 minutes ago" for the popup.
+
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+/// Older than this shows the date instead
+const WEEK: u64 = 7 * DAY;
+
+/// "just now", "N minutes/hours/days ago", or "on <date>" beyond a week,
+/// for something that happened `elapsed` before `now`
+pub fn format_elapsed(elapsed: Duration, now: NaiveDateTime) -> String {
+    let secs = elapsed.as_secs();
+    if secs < MINUTE {
+        "just now".to_string()
+    } else if secs < HOUR {
+        ago(secs / MINUTE, "minute")
+    } else if secs < DAY {
+        ago(secs / HOUR, "hour")
+    } else if secs <= WEEK {
+        ago(secs / DAY, "day")
+    } else {
+        let then = chrono::Duration::from_std(elapsed)
+            .ok()
+            .and_then(|elapsed| now.checked_sub_signed(elapsed));
+        match then {
+            Some(then) => format!("on {}", then.format("%Y-%m-%d")),
+            None => ago(secs / DAY, "day"),
+        }
+    }
+}
+
+fn ago(count: u64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", count, unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(secs: u64) -> String {
+        let now = chrono::NaiveDate::from_ymd_opt(2026, 10, 14)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .unwrap();
+        format_elapsed(Duration::from_secs(secs), now)
+    }
+
+    #[test]
+    fn test_boundaries() {
+        assert_eq!(format(59), "just now");
+        assert_eq!(format(60), "1 minute ago");
+        assert_eq!(format(59 * MINUTE), "59 minutes ago");
+        assert_eq!(format(HOUR), "1 hour ago");
+        assert_eq!(format(23 * HOUR), "23 hours ago");
+        assert_eq!(format(DAY), "1 day ago");
+        assert_eq!(format(2 * DAY), "2 days ago");
+        assert_eq!(format(7 * DAY), "7 days ago");
+    }
+
+    #[test]
+    fn test_date_beyond_a_week() {
+        assert_eq!(format(7 * DAY + 1), "on 2026-10-07");
+        assert_eq!(format(30 * DAY), "on 2026-09-14");
+    }
+}
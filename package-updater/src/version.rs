@@ -0,0 +1,257 @@
+//! Package version comparison, used to spot listings that are not upgrades.
+
+use std::cmp::Ordering;
+
+/// Version ordering rules of a package format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionScheme {
+    /// pacman's `vercmp` (rpmvercmp): `epoch:version-pkgrel`
+    Pacman,
+    /// rpm/dnf: like pacman, plus `~` sorting before everything
+    Rpm,
+    /// dpkg: `epoch:upstream-revision`, `~` sorting before everything
+    Dpkg,
+}
+
+/// Compare two versions, `None` when they cannot be compared reliably.
+///
+/// Only versions starting with a digit (after the epoch) are compared, so
+/// placeholders such as "unknown" or an AUR helper's "latest-commit" are
+/// left alone.
+pub fn compare(scheme: VersionScheme, a: &str, b: &str) -> Option<Ordering> {
+    let a = Version::parse(a)?;
+    let b = Version::parse(b)?;
+
+    let segment_cmp: fn(&str, &str) -> Ordering = match scheme {
+        VersionScheme::Pacman => |x: &str, y: &str| rpmvercmp(x, y, false),
+        VersionScheme::Rpm => |x: &str, y: &str| rpmvercmp(x, y, true),
+        VersionScheme::Dpkg => dpkg_verrevcmp,
+    };
+
+    let ordering = a
+        .epoch
+        .cmp(&b.epoch)
+        .then_with(|| segment_cmp(a.version, b.version))
+        .then_with(|| match (a.release, b.release) {
+            (Some(x), Some(y)) => segment_cmp(x, y),
+            // pacman and rpm ignore a missing release; dpkg treats it as empty
+            (x, y) if scheme == VersionScheme::Dpkg => {
+                segment_cmp(x.unwrap_or(""), y.unwrap_or(""))
+            }
+            _ => Ordering::Equal,
+        });
+    Some(ordering)
+}
+
+/// `[epoch:]version[-release]`
+struct Version<'a> {
+    epoch: u64,
+    version: &'a str,
+    release: Option<&'a str>,
+}
+
+impl<'a> Version<'a> {
+    fn parse(full: &'a str) -> Option<Self> {
+        let full = full.trim();
+        let (epoch, rest) = match full.split_once(':') {
+            Some((epoch, rest)) => (epoch.parse().ok()?, rest),
+            None => (0, full),
+        };
+        let (version, release) = match rest.rsplit_once('-') {
+            Some((version, release)) => (version, Some(release)),
+            None => (rest, None),
+        };
+        version
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then_some(Version {
+                epoch,
+                version,
+                release,
+            })
+    }
+}
+
+/// rpm's segment comparison, which pacman's `vercmp` also uses
+fn rpmvercmp(a: &str, b: &str, tilde: bool) -> Ordering {
+    let (mut one, mut two) = (a.as_bytes(), b.as_bytes());
+
+    loop {
+        // Skip separators, except a tilde when it is significant
+        let is_sep = |c: u8| !(c.is_ascii_alphanumeric() || (tilde && c == b'~'));
+        let skip_one = one.iter().take_while(|c| is_sep(**c)).count();
+        let skip_two = two.iter().take_while(|c| is_sep(**c)).count();
+        one = &one[skip_one..];
+        two = &two[skip_two..];
+
+        if tilde && (one.first() == Some(&b'~') || two.first() == Some(&b'~')) {
+            match (one.first() == Some(&b'~'), two.first() == Some(&b'~')) {
+                (true, true) => {
+                    one = &one[1..];
+                    two = &two[1..];
+                    continue;
+                }
+                (true, false) => return Ordering::Less,
+                _ => return Ordering::Greater,
+            }
+        }
+
+        if one.is_empty() || two.is_empty() {
+            break;
+        }
+
+        let numeric = one[0].is_ascii_digit();
+        let take = |s: &[u8]| -> usize {
+            s.iter()
+                .take_while(|c| {
+                    if numeric {
+                        c.is_ascii_digit()
+                    } else {
+                        c.is_ascii_alphabetic()
+                    }
+                })
+                .count()
+        };
+        let (len_one, len_two) = (take(one), take(two));
+
+        // A numeric segment is newer than an alphabetic one
+        if len_two == 0 {
+            return if numeric {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let (seg_one, seg_two) = (&one[..len_one], &two[..len_two]);
+        let ordering = if numeric {
+            compare_numeric(seg_one, seg_two)
+        } else {
+            seg_one.cmp(seg_two)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+
+        one = &one[len_one..];
+        two = &two[len_two..];
+    }
+
+    match (one.first(), two.first()) {
+        (None, None) => Ordering::Equal,
+        // A remaining alphabetic segment never beats the end of the string
+        (None, Some(c)) if !c.is_ascii_alphabetic() => Ordering::Less,
+        (Some(c), _) if c.is_ascii_alphabetic() => Ordering::Less,
+        _ => Ordering::Greater,
+    }
+}
+
+/// Compare digit strings by value, ignoring leading zeros and without overflow
+fn compare_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    let strip = |s: &[u8]| -> usize { s.iter().take_while(|c| **c == b'0').count() };
+    let (a, b) = (&a[strip(a)..], &b[strip(b)..]);
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// dpkg's `verrevcmp`: alternating non-digit and digit parts, where `~`
+/// sorts before the end of the string and letters before other characters
+fn dpkg_verrevcmp(a: &str, b: &str) -> Ordering {
+    fn order(c: Option<&u8>) -> i32 {
+        match c {
+            None => 0,
+            Some(b'~') => -1,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => *c as i32,
+            Some(c) => *c as i32 + 256,
+        }
+    }
+
+    let (mut one, mut two) = (a.as_bytes(), b.as_bytes());
+    while !one.is_empty() || !two.is_empty() {
+        // Non-digit prefix
+        while one.first().is_some_and(|c| !c.is_ascii_digit())
+            || two.first().is_some_and(|c| !c.is_ascii_digit())
+        {
+            let (x, y) = (order(one.first()), order(two.first()));
+            if x != y {
+                return x.cmp(&y);
+            }
+            one = one.get(1..).unwrap_or_default();
+            two = two.get(1..).unwrap_or_default();
+        }
+
+        // Digit run
+        let len_one = one.iter().take_while(|c| c.is_ascii_digit()).count();
+        let len_two = two.iter().take_while(|c| c.is_ascii_digit()).count();
+        let ordering = compare_numeric(&one[..len_one], &two[..len_two]);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        one = &one[len_one..];
+        two = &two[len_two..];
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pacman(a: &str, b: &str) -> Option<Ordering> {
+        compare(VersionScheme::Pacman, a, b)
+    }
+
+    #[test]
+    fn test_pacman_vercmp() {
+        assert_eq!(pacman("1.0-1", "1.0-2"), Some(Ordering::Less));
+        assert_eq!(pacman("1.0.1-1", "1.0-5"), Some(Ordering::Greater));
+        assert_eq!(pacman("1.0a-1", "1.0-1"), Some(Ordering::Less));
+        assert_eq!(pacman("1.0-1", "1.0.a-1"), Some(Ordering::Greater));
+        assert_eq!(
+            pacman("6.10.3.arch1-1", "6.10.3.arch1-1"),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(pacman("1.010", "1.9"), Some(Ordering::Greater));
+        // The release only matters when both sides have one
+        assert_eq!(pacman("1.0", "1.0-3"), Some(Ordering::Equal));
+    }
+
+    #[test]
+    fn test_epoch_wins() {
+        assert_eq!(pacman("1:1.0-1", "2.0-1"), Some(Ordering::Greater));
+        assert_eq!(
+            compare(VersionScheme::Dpkg, "1:2.0", "2:1.0"),
+            Some(Ordering::Less)
+        );
+    }
+
+    #[test]
+    fn test_tilde_sorts_first() {
+        assert_eq!(
+            compare(VersionScheme::Dpkg, "1.0~rc1-1", "1.0-1"),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare(VersionScheme::Rpm, "1.0~beta", "1.0"),
+            Some(Ordering::Less)
+        );
+    }
+
+    #[test]
+    fn test_dpkg_revisions() {
+        let dpkg = |a, b| compare(VersionScheme::Dpkg, a, b);
+        assert_eq!(dpkg("2.0-1ubuntu1", "2.0-1"), Some(Ordering::Greater));
+        assert_eq!(
+            dpkg("7.88.1-10+deb12u5", "7.88.1-10+deb12u4"),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(dpkg("1.2.3", "1.2.3-0"), Some(Ordering::Equal));
+        assert_eq!(dpkg("1.2a", "1.2+"), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn test_incomparable_versions() {
+        assert_eq!(pacman("unknown", "1.0-1"), None);
+        assert_eq!(pacman("r120.abc-1", "latest-commit"), None);
+        assert_eq!(pacman("x:1.0", "1.0"), None);
+    }
+}